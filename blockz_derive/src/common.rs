@@ -21,3 +21,52 @@ pub fn create_doc(doc: String) -> TokenStream {
 pub fn create_lit_str(string: String) -> LitStr {
     LitStr::new(string.as_str(), Span::call_site())
 }
+
+/// A duration passed to a macro option, such as `"500ms"` or `"2s"`.
+#[cfg(feature = "singleton")]
+#[derive(Clone, Copy, Debug)]
+pub struct DurationMeta {
+    nanos: u64,
+}
+
+#[cfg(feature = "singleton")]
+impl DurationMeta {
+    /// Create an expression that builds the duration.
+    pub fn to_expr(self) -> TokenStream {
+        let std = crate::paths::std_path();
+        let nanos = self.nanos;
+        quote! { #std::time::Duration::from_nanos(#nanos) }
+    }
+}
+
+#[cfg(feature = "singleton")]
+impl darling::FromMeta for DurationMeta {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        let value = value.trim();
+        let unit_start = value
+            .find(|ch: char| !ch.is_ascii_digit())
+            .unwrap_or(value.len());
+        let (amount, unit) = value.split_at(unit_start);
+        let amount: u64 = amount.parse().map_err(|_| {
+            darling::Error::custom(format!("invalid duration '{}': expected an amount", value))
+        })?;
+        let unit_nanos: u64 = match unit.trim() {
+            "ns" => 1,
+            "us" => 1_000,
+            "ms" => 1_000_000,
+            "s" => 1_000_000_000,
+            "m" => 60_000_000_000,
+            "h" => 3_600_000_000_000,
+            _ => {
+                return Err(darling::Error::custom(format!(
+                    "invalid duration '{}': expected one of the units ns, us, ms, s, m, h",
+                    value
+                )))
+            }
+        };
+        amount
+            .checked_mul(unit_nanos)
+            .map(|nanos| Self { nanos })
+            .ok_or_else(|| darling::Error::custom(format!("duration '{}' is too large", value)))
+    }
+}
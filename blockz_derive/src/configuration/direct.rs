@@ -4,9 +4,14 @@ use quote::quote;
 
 use syn::DeriveInput;
 
+use crate::factory::Factory;
 use crate::factory::ReusableFactory;
 use crate::paths;
+use crate::ProcMacroErrorExt;
 
+use super::bound_generics;
+use super::global::GlobalConfigurationFactory;
+use super::self_type;
 use super::ConfigurationOpts;
 use super::DynFactory;
 use super::DynFactoryProduct;
@@ -14,12 +19,16 @@ use super::DynFactoryProduct;
 /// Factory that builds a Configuration implementation based on DirectConfiguration.
 pub(super) struct DirectConfigurationFactory {
     input: DeriveInput,
+    global: bool,
 }
 
 impl DirectConfigurationFactory {
     /// Create a new direct configuration factory.
-    pub fn new_dyn(input: DeriveInput, _: &mut ConfigurationOpts) -> DynFactory {
-        Box::new(Self { input })
+    pub fn new_dyn(input: DeriveInput, opts: &mut ConfigurationOpts) -> DynFactory {
+        Box::new(Self {
+            input,
+            global: opts.global,
+        })
     }
 }
 
@@ -30,12 +39,28 @@ impl ReusableFactory for DirectConfigurationFactory {
         // gather paths to dependencies
         let blockz = paths::blockz_path();
 
+        let global = if self.global {
+            let global = GlobalConfigurationFactory::new(&self.input, None)
+                .build()
+                .map_err(|err| {
+                    let err: Box<dyn ProcMacroErrorExt> = Box::new(err);
+                    err
+                })?;
+            Some(global)
+        } else {
+            None
+        };
+
+        // carry the generics through
+        let type_name = self_type(&self.input);
+        let generics = bound_generics(&self.input, vec![syn::parse_quote! { #type_name: Send }]);
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+
         // return the implementation
-        let type_name = &self.input.ident;
         Ok(quote! {
             #[automatically_derived]
             #[async_trait::async_trait]
-            impl #blockz::configuration::Configuration for #type_name {
+            impl #impl_generics #blockz::configuration::Configuration for #type_name #where_clause {
                 type Opts = #type_name;
                 type Result = <#blockz::configuration::DirectConfiguration<#type_name> as #blockz::configuration::Configuration>::Result;
 
@@ -43,6 +68,15 @@ impl ReusableFactory for DirectConfigurationFactory {
                     <#blockz::configuration::DirectConfiguration<#type_name> as #blockz::configuration::Configuration>::load(opts).await
                 }
             }
+
+            #[automatically_derived]
+            impl #impl_generics #blockz::configuration::BlockingConfiguration for #type_name #where_clause {
+                fn load_blocking(opts: Self::Opts) -> Self::Result {
+                    <#blockz::configuration::DirectConfiguration<#type_name> as #blockz::configuration::BlockingConfiguration>::load_blocking(opts)
+                }
+            }
+
+            #global
         })
     }
 }
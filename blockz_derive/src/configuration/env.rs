@@ -3,17 +3,27 @@
 use darling::FromMeta;
 
 use proc_macro2::TokenStream;
+use proc_macro2::TokenTree;
 
 use quote::quote;
 
+use syn::Data;
+use syn::DataEnum;
 use syn::DeriveInput;
 use syn::Expr;
+use syn::WherePredicate;
 
 use crate::common;
+use crate::factory::Factory;
 use crate::factory::ReusableFactory;
 use crate::paths;
 use crate::ProcMacroErrorExt;
 
+use super::bound_generics;
+use super::env_enum::EnvEnumLoadFactory;
+use super::env_enum::DEFAULT_TAG;
+use super::global::GlobalConfigurationFactory;
+use super::self_type;
 use super::ConfigurationOpts;
 use super::DynFactory;
 use super::DynFactoryProduct;
@@ -22,6 +32,7 @@ use super::DynFactoryProduct;
 pub(super) struct EnvConfigurationFactory {
     input: DeriveInput,
     opts: EnvConfigurationOpts,
+    global: bool,
 }
 
 /// Configuration options for a configuration backed by envy.
@@ -31,22 +42,32 @@ pub(super) struct EnvConfigurationOpts {
     prefix: Option<String>,
     #[darling(default)]
     prefix_source: Option<String>,
+    #[darling(default)]
+    tag: Option<String>,
 }
 
 impl EnvConfigurationFactory {
     /// Create a new envy configuration factory.
     pub fn new_dyn(input: DeriveInput, opts: &mut ConfigurationOpts) -> DynFactory {
         let env = opts.env.take().unwrap_or_default();
-        Box::new(Self { input, opts: env })
+        Box::new(Self {
+            input,
+            opts: env,
+            global: opts.global,
+        })
     }
 
     fn get_configuration_impl_opts(&self) -> TokenStream {
         // gather paths to dependencies
         let blockz = paths::blockz_path();
 
-        let type_name = &self.input.ident;
-        let default_opts = quote! {
-            <#blockz::configuration::EnvConfiguration<#type_name> as #blockz::configuration::Configuration>::Opts
+        let type_name = self_type(&self.input);
+        let default_opts = if let Data::Enum(_) = &self.input.data {
+            quote! { Option<String> }
+        } else {
+            quote! {
+                <#blockz::configuration::EnvConfiguration<#type_name> as #blockz::configuration::Configuration>::Opts
+            }
         };
 
         if self.opts.prefix.is_some() || self.opts.prefix_source.is_some() {
@@ -56,6 +77,18 @@ impl EnvConfigurationFactory {
         }
     }
 
+    /// Check whether the configuration can be loaded without an async runtime.
+    ///
+    /// This is not the case when the prefix source awaits something.
+    fn is_blocking(&self) -> bool {
+        !self
+            .opts
+            .prefix_source
+            .as_deref()
+            .and_then(|prefix_source| prefix_source.parse::<TokenStream>().ok())
+            .is_some_and(contains_await)
+    }
+
     fn get_configuration_impl_load_arg(&self) -> Result<TokenStream, syn::Error> {
         let tokens: TokenStream = if let Some(prefix) = &self.opts.prefix {
             let lit_prefix = common::create_lit_str(prefix.clone());
@@ -64,7 +97,7 @@ impl EnvConfigurationFactory {
             let expr: Expr = syn::parse_str(prefix_source).map_err(|err: syn::Error| {
                 syn::Error::new(
                     err.span(),
-                    format!("failed to parse prefix source tokens: {}", err.to_string()),
+                    format!("failed to parse prefix source tokens: {}", err),
                 )
             })?;
             quote! { Some(#expr) }
@@ -73,6 +106,85 @@ impl EnvConfigurationFactory {
         };
         Ok(tokens)
     }
+
+    /// Build the global accessors, if requested.
+    fn get_global(&self) -> Result<Option<TokenStream>, Box<dyn ProcMacroErrorExt>> {
+        if !self.global {
+            return Ok(None);
+        }
+        let envy = paths::envy_path();
+        GlobalConfigurationFactory::new(&self.input, Some(quote! { #envy::Error }))
+            .build()
+            .map(Some)
+            .map_err(|err| {
+                let err: Box<dyn ProcMacroErrorExt> = Box::new(err);
+                err
+            })
+    }
+
+    /// Build the implementation for an enum, whose variant is selected by a tag variable.
+    fn build_enum(&self, data: &DataEnum, load_arg: &TokenStream) -> DynFactoryProduct {
+        // gather paths to dependencies
+        let blockz = paths::blockz_path();
+        let envy = paths::envy_path();
+
+        let tag = self.opts.tag.as_deref().unwrap_or(DEFAULT_TAG);
+        let mut factory = EnvEnumLoadFactory::new(data, &self.input.generics, tag, load_arg);
+        let load = ReusableFactory::build(&mut factory).map_err(|err| {
+            let err: Box<dyn ProcMacroErrorExt> = Box::new(err);
+            err
+        })?;
+
+        // carry the generics through, the variant fields must be deserializable
+        let type_name = self_type(&self.input);
+        let mut predicates: Vec<WherePredicate> = vec![syn::parse_quote! { #type_name: Send }];
+        predicates.extend(factory.field_predicates());
+        let generics = bound_generics(&self.input, predicates);
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+        // awaiting prefix sources can only be loaded asynchronously
+        let opts = self.get_configuration_impl_opts();
+        let global = self.get_global()?;
+        if !self.is_blocking() {
+            return Ok(quote! {
+                #[automatically_derived]
+                #[async_trait::async_trait]
+                impl #impl_generics #blockz::configuration::Configuration for #type_name #where_clause {
+                    type Opts = #opts;
+                    type Result = Result<Self, #envy::Error>;
+
+                    #[allow(unused_variables)]
+                    async fn load(opts: Self::Opts) -> Self::Result {
+                        #load
+                    }
+                }
+
+                #global
+            });
+        }
+        Ok(quote! {
+            #[automatically_derived]
+            #[async_trait::async_trait]
+            impl #impl_generics #blockz::configuration::Configuration for #type_name #where_clause {
+                type Opts = #opts;
+                type Result = Result<Self, #envy::Error>;
+
+                async fn load(opts: Self::Opts) -> Self::Result {
+                    <Self as #blockz::configuration::BlockingConfiguration>::load_blocking(opts)
+                }
+            }
+
+            #[automatically_derived]
+            impl #impl_generics #blockz::configuration::BlockingConfiguration for #type_name #where_clause {
+                #[allow(unused_variables)]
+                fn load_blocking(opts: Self::Opts) -> Self::Result {
+                    #load
+                }
+            }
+
+            #global
+        })
+    }
 }
 
 impl ReusableFactory for EnvConfigurationFactory {
@@ -89,12 +201,48 @@ impl ReusableFactory for EnvConfigurationFactory {
             err
         })?;
 
+        match &self.input.data {
+            Data::Enum(data) => return self.build_enum(data, &load_arg),
+            _ if self.opts.tag.is_some() => {
+                let err: Box<dyn ProcMacroErrorExt> = Box::new(syn::Error::new_spanned(
+                    &self.input.ident,
+                    "the tag option is only supported by enum configurations",
+                ));
+                return Err(err);
+            }
+            _ => (),
+        }
+
+        // carry the generics through
+        let serde = paths::serde_path();
+        let type_name = self_type(&self.input);
+        let generics = bound_generics(
+            &self.input,
+            vec![syn::parse_quote! { #type_name: for<'de> #serde::Deserialize<'de> + Send }],
+        );
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+        // awaiting prefix sources can only be loaded asynchronously
+        let blocking = if self.is_blocking() {
+            quote! {
+                #[automatically_derived]
+                impl #impl_generics #blockz::configuration::BlockingConfiguration for #type_name #where_clause {
+                    #[allow(unused_variables)]
+                    fn load_blocking(opts: Self::Opts) -> Self::Result {
+                        <#blockz::configuration::EnvConfiguration<#type_name> as #blockz::configuration::BlockingConfiguration>::load_blocking(#load_arg)
+                    }
+                }
+            }
+        } else {
+            TokenStream::new()
+        };
+
         // return the implementation
-        let type_name = &self.input.ident;
+        let global = self.get_global()?;
         Ok(quote! {
             #[automatically_derived]
             #[async_trait::async_trait]
-            impl #blockz::configuration::Configuration for #type_name {
+            impl #impl_generics #blockz::configuration::Configuration for #type_name #where_clause {
                 type Opts = #opts;
                 type Result = <#blockz::configuration::EnvConfiguration<#type_name> as #blockz::configuration::Configuration>::Result;
 
@@ -102,6 +250,19 @@ impl ReusableFactory for EnvConfigurationFactory {
                     <#blockz::configuration::EnvConfiguration<#type_name> as #blockz::configuration::Configuration>::load(#load_arg).await
                 }
             }
+
+            #blocking
+
+            #global
         })
     }
 }
+
+/// Check whether a token stream contains an `.await`.
+fn contains_await(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(ident) => ident == "await",
+        TokenTree::Group(group) => contains_await(group.stream()),
+        _ => false,
+    })
+}
@@ -0,0 +1,270 @@
+//! Env configuration factory for enums.
+
+use darling::FromVariant;
+
+use proc_macro2::TokenStream;
+
+use quote::format_ident;
+use quote::quote;
+
+use syn::DataEnum;
+use syn::Fields;
+use syn::Generics;
+use syn::Variant;
+use syn::WherePredicate;
+
+use crate::common;
+use crate::factory::ReusableFactory;
+use crate::paths;
+
+/// Default name of the variable that selects the variant.
+pub(super) const DEFAULT_TAG: &str = "kind";
+
+/// Factory that builds the body of the blocking loader of an enum configuration.
+///
+/// The tag variable (`{prefix}{TAG}`) selects the variant, whose fields are then loaded from the
+/// variables with the variant's sub-prefix (`{prefix}{VARIANT}_`).
+pub(super) struct EnvEnumLoadFactory<'i> {
+    data: &'i DataEnum,
+    generics: &'i Generics,
+    tag: &'i str,
+    load_arg: &'i TokenStream,
+}
+
+/// Options for an enum variant.
+#[derive(FromVariant)]
+#[darling(attributes(configuration))]
+struct EnvVariantOpts {
+    ident: syn::Ident,
+    #[darling(default)]
+    rename: Option<String>,
+}
+
+/// A variant that can be selected by the tag variable.
+struct TaggedVariant<'i> {
+    variant: &'i Variant,
+    tag: String,
+}
+
+impl<'i> EnvEnumLoadFactory<'i> {
+    /// Create a new enum load factory.
+    pub fn new(
+        data: &'i DataEnum,
+        generics: &'i Generics,
+        tag: &'i str,
+        load_arg: &'i TokenStream,
+    ) -> Self {
+        Self {
+            data,
+            generics,
+            tag,
+            load_arg,
+        }
+    }
+
+    /// Create the predicates that generic enums need for loading their variants.
+    pub fn field_predicates(&self) -> Vec<WherePredicate> {
+        let serde = paths::serde_path();
+        self.data
+            .variants
+            .iter()
+            .flat_map(|variant| variant.fields.iter())
+            .map(|field| {
+                let ty = &field.ty;
+                syn::parse_quote! { #ty: for<'de> #serde::Deserialize<'de> + Send }
+            })
+            .collect()
+    }
+
+    fn get_tagged_variants(&self) -> Result<Vec<TaggedVariant<'i>>, darling::Error> {
+        let mut errors = Vec::new();
+        let mut variants: Vec<TaggedVariant<'i>> = Vec::with_capacity(self.data.variants.len());
+        for variant in self.data.variants.iter() {
+            let opts = match EnvVariantOpts::from_variant(variant) {
+                Ok(value) => value,
+                Err(err) => {
+                    errors.push(err);
+                    continue;
+                }
+            };
+            let EnvVariantOpts { ident, rename } = opts;
+            let tag = rename
+                .unwrap_or_else(|| snake_case(&ident.to_string()))
+                .to_lowercase();
+            if variants.iter().any(|other| other.tag == tag) {
+                errors.push(
+                    darling::Error::custom(format!("duplicate variant tag '{}'", tag))
+                        .with_span(variant),
+                );
+                continue;
+            }
+            variants.push(TaggedVariant { variant, tag });
+        }
+        if variants.is_empty() && errors.is_empty() {
+            errors.push(darling::Error::custom(
+                "enum configurations must have at least one variant",
+            ));
+        }
+        if errors.is_empty() {
+            Ok(variants)
+        } else {
+            Err(darling::Error::multiple(errors))
+        }
+    }
+
+    /// Build the expression that loads a single variant.
+    fn build_variant_load(
+        &self,
+        tagged: &TaggedVariant<'_>,
+    ) -> Result<TokenStream, darling::Error> {
+        // gather paths to dependencies
+        let blockz = paths::blockz_path();
+        let serde = paths::serde_path();
+
+        let ident = &tagged.variant.ident;
+        let lit_sub_prefix = common::create_lit_str(format!("{}_", tagged.tag.to_uppercase()));
+        let sub_prefix = quote! { Some(format!("{}{}", prefix, #lit_sub_prefix)) };
+        match &tagged.variant.fields {
+            Fields::Unit => Ok(quote! { Ok(Self::#ident) }),
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                Ok(quote! {
+                    <#blockz::configuration::EnvConfiguration<#ty> as #blockz::configuration::BlockingConfiguration>::load_blocking(#sub_prefix)
+                        .map(Self::#ident)
+                })
+            }
+            Fields::Unnamed(_) => Err(darling::Error::custom(
+                "tuple variants of enum configurations must have exactly one field",
+            )
+            .with_span(tagged.variant)),
+            Fields::Named(fields) => {
+                let serde_crate = common::create_lit_str(serde.to_string());
+                let fields_ident = format_ident!("{}Fields", ident);
+                let names: Vec<_> = fields.named.iter().map(|field| &field.ident).collect();
+                let defs = fields.named.iter().map(|field| {
+                    let serde_attrs = field
+                        .attrs
+                        .iter()
+                        .filter(|attr| attr.path.is_ident("serde"));
+                    let name = &field.ident;
+                    let ty = &field.ty;
+                    quote! { #(#serde_attrs)* #name: #ty }
+                });
+
+                // the fields of generic enums are loaded with a generic struct that redeclares the
+                // type parameters of the enum, bounded by the fields
+                let (fields_generics, fields_bound, fields_marker) =
+                    if self.generics.params.is_empty() {
+                        (TokenStream::new(), TokenStream::new(), TokenStream::new())
+                    } else {
+                        let std = paths::std_path();
+                        let params: Vec<_> = self
+                            .generics
+                            .type_params()
+                            .map(|param| &param.ident)
+                            .collect();
+                        let bound = common::create_lit_str(
+                            fields
+                                .named
+                                .iter()
+                                .map(|field| {
+                                    let ty = &field.ty;
+                                    format!("{}: {}::Deserialize<'de>", quote! { #ty }, serde)
+                                })
+                                .collect::<Vec<String>>()
+                                .join(", "),
+                        );
+                        (
+                            quote! { <#(#params,)*> },
+                            quote! { #[serde(bound(deserialize = #bound))] },
+                            quote! {
+                                #[serde(skip)]
+                                __blockz_marker: #std::marker::PhantomData<fn() -> (#(#params,)*)>,
+                            },
+                        )
+                    };
+                Ok(quote! {
+                    {
+                        #[derive(#serde::Deserialize)]
+                        #[serde(crate = #serde_crate)]
+                        #fields_bound
+                        struct #fields_ident #fields_generics {
+                            #(#defs,)*
+                            #fields_marker
+                        }
+
+                        <#blockz::configuration::EnvConfiguration<#fields_ident #fields_generics> as #blockz::configuration::BlockingConfiguration>::load_blocking(#sub_prefix)
+                            .map(|fields| Self::#ident { #(#names: fields.#names,)* })
+                    }
+                })
+            }
+        }
+    }
+}
+
+impl<'i> ReusableFactory for EnvEnumLoadFactory<'i> {
+    type Product = Result<TokenStream, darling::Error>;
+
+    /// Build the body of the blocking loader.
+    fn build(&mut self) -> Self::Product {
+        // gather paths to dependencies
+        let envy = paths::envy_path();
+        let std = paths::std_path();
+
+        let variants = self.get_tagged_variants()?;
+        let mut errors = Vec::new();
+        let mut arms = Vec::with_capacity(variants.len());
+        for tagged in variants.iter() {
+            match self.build_variant_load(tagged) {
+                Ok(load) => {
+                    let lit_tag = common::create_lit_str(tagged.tag.clone());
+                    arms.push(quote! { #lit_tag => #load, });
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(darling::Error::multiple(errors));
+        }
+
+        let expected = common::create_lit_str(
+            variants
+                .iter()
+                .map(|tagged| tagged.tag.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        let lit_tag = common::create_lit_str(self.tag.to_uppercase());
+        let load_arg = self.load_arg;
+        Ok(quote! {
+            let prefix: Option<String> = #load_arg;
+            let prefix = prefix.unwrap_or_default();
+            let tag_var = format!("{}{}", prefix, #lit_tag);
+            let tag = #std::env::var(&tag_var).map_err(|_| {
+                #envy::Error::Custom(format!(
+                    "missing value for {} (expected one of: {})",
+                    tag_var, #expected
+                ))
+            })?;
+            match tag.to_lowercase().as_str() {
+                #(#arms)*
+                _ => Err(#envy::Error::Custom(format!(
+                    "unknown variant '{}' provided by {} (expected one of: {})",
+                    tag, tag_var, #expected
+                ))),
+            }
+        })
+    }
+}
+
+/// Convert a variant name to snake case, the same way serde's `rename_all = "snake_case"` does.
+fn snake_case(variant: &str) -> String {
+    let mut snake = String::with_capacity(variant.len() + 4);
+    for (index, ch) in variant.char_indices() {
+        if index > 0 && ch.is_uppercase() {
+            snake.push('_');
+        }
+        snake.push(ch.to_ascii_lowercase());
+    }
+    snake
+}
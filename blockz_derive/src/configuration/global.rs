@@ -0,0 +1,116 @@
+//! Global configuration factory.
+
+use convert_case::Case;
+use convert_case::Casing;
+
+use proc_macro2::TokenStream;
+
+use quote::format_ident;
+use quote::quote;
+
+use syn::DeriveInput;
+
+use crate::common;
+use crate::factory::Factory;
+use crate::paths;
+
+/// Prefix for the generated global configuration static.
+const GLOBAL_STATIC_PREFIX: &str = "BLOCKZ_CONFIGURATION_GLOBAL_";
+
+/// Factory that builds the global accessors of a configuration.
+///
+/// The configuration is loaded with EasyConfiguration and stored in a static (a
+/// GlobalConfiguration, which runs a single load at a time), which makes reading it lock free.
+pub(super) struct GlobalConfigurationFactory<'f> {
+    input: &'f DeriveInput,
+    error: Option<TokenStream>,
+}
+
+impl<'f> GlobalConfigurationFactory<'f> {
+    /// Create a new global configuration factory.
+    ///
+    /// The error must be the error type of the configuration result, if loading the configuration
+    /// can fail.
+    pub fn new(input: &'f DeriveInput, error: Option<TokenStream>) -> Self {
+        Self { input, error }
+    }
+}
+
+impl<'f> Factory for GlobalConfigurationFactory<'f> {
+    type Product = syn::Result<TokenStream>;
+
+    /// Build the global configuration static and accessors.
+    fn build(self) -> Self::Product {
+        // a static can't be generic
+        if !self.input.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &self.input.generics,
+                "global configurations can't be generic",
+            ));
+        }
+
+        // get paths to deps
+        let blockz = paths::blockz_path();
+        let std = paths::std_path();
+
+        let type_name = &self.input.ident;
+        let static_ident = format_ident!(
+            "{}{}",
+            GLOBAL_STATIC_PREFIX,
+            type_name.to_string().to_case(Case::UpperSnake)
+        );
+
+        // infallible configurations are wrapped in Ok
+        let (error, load) = match self.error {
+            Some(error) => (
+                error,
+                quote! { <Self as #blockz::configuration::EasyConfiguration>::load().await? },
+            ),
+            None => (
+                quote! { #std::convert::Infallible },
+                quote! { <Self as #blockz::configuration::EasyConfiguration>::load().await },
+            ),
+        };
+
+        // create doc comments
+        let static_doc = common::create_doc(format!("Global configuration for {}.", type_name));
+        let panic_msg = common::create_lit_str(format!(
+            "{0}::init_global() must be called before {0}::global()",
+            type_name
+        ));
+
+        Ok(quote! {
+            #static_doc
+            #[automatically_derived]
+            static #static_ident: #blockz::configuration::GlobalConfiguration<#type_name> =
+                #blockz::configuration::GlobalConfiguration::new();
+
+            #[automatically_derived]
+            impl #type_name {
+                /// Load the configuration and store it globally.
+                ///
+                /// If the configuration was already initialized, it is not loaded again. Concurrent
+                /// calls wait for a single load, and a failed load is retried by the next call.
+                pub async fn init_global() -> Result<&'static Self, #error> {
+                    #static_ident
+                        .init(|| async { Ok::<_, #error>(#load) })
+                        .await
+                }
+
+                /// Get the global configuration.
+                ///
+                /// # Panics
+                ///
+                /// This panics if the configuration was not initialized with init_global.
+                pub fn global() -> &'static Self {
+                    #static_ident.get().expect(#panic_msg)
+                }
+
+                /// Get the global configuration, if it was initialized.
+                pub fn try_global() -> Option<&'static Self> {
+                    #static_ident.get()
+                }
+            }
+        })
+    }
+}
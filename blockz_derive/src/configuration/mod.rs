@@ -4,12 +4,19 @@ mod direct;
 #[cfg(feature = "env_configuration")]
 #[cfg_attr(docsrs, doc(cfg(feature = "env_configuration")))]
 mod env;
+#[cfg(feature = "env_configuration")]
+mod env_enum;
+mod global;
 
 use darling::FromDeriveInput;
 
 use proc_macro2::TokenStream;
 
+use quote::quote;
+
 use syn::DeriveInput;
+use syn::Generics;
+use syn::WherePredicate;
 
 use crate::factory::Factory;
 use crate::factory::ReusableFactory;
@@ -30,6 +37,28 @@ type DynFactory = Box<dyn ReusableFactory<Product = DynFactoryProduct>>;
 /// A function that creates a specialized factory.
 type FnNewDynFactory = Box<dyn FnOnce(DeriveInput, &mut ConfigurationOpts) -> DynFactory>;
 
+/// Create the configuration type, including its generic parameters.
+fn self_type(input: &DeriveInput) -> TokenStream {
+    let type_name = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    quote! { #type_name #ty_generics }
+}
+
+/// Add predicates to the where clause of the configuration generics.
+///
+/// The predicates are only added if the configuration is generic, so the errors of non-generic
+/// configurations keep pointing at the type itself.
+fn bound_generics<I>(input: &DeriveInput, predicates: I) -> Generics
+where
+    I: IntoIterator<Item = WherePredicate>,
+{
+    let mut generics = input.generics.clone();
+    if !generics.params.is_empty() {
+        generics.make_where_clause().predicates.extend(predicates);
+    }
+    generics
+}
+
 /// A macro that automatically builds the functions for selecting the dynamic factory used for
 /// producing the macro output.
 macro_rules! feature_factory {
@@ -72,6 +101,8 @@ struct ConfigurationOpts {
     env: Option<EnvConfigurationOpts>,
     #[darling(default)]
     direct: bool,
+    #[darling(default)]
+    global: bool,
 }
 
 impl ConfigurationFactory {
@@ -18,14 +18,17 @@ mod paths;
 use proc_macro::TokenStream;
 
 use syn::parse_macro_input;
+use syn::AttributeArgs;
 use syn::DeriveInput;
 use syn::ItemFn;
+use syn::ItemImpl;
 
 use self::configuration::ConfigurationFactory;
 use self::errors::ProcMacroErrorExt;
 use self::factory::Factory;
 use self::singleton::SingletonFactory;
 use self::singleton::SingletonFnFactory;
+use self::singleton::SingletonImplFactory;
 
 /// Use a factory to produce a token stream.
 macro_rules! factory {
@@ -52,20 +55,137 @@ macro_rules! factory {
 ///
 /// - `#[singleton(lock = "mutex")]` _(default)_
 /// - `#[singleton(lock = "rwlock")]`
+/// - `#[singleton(lock = "swap")]`
+/// - `#[singleton(lock = "none")]`
+/// - `#[singleton(lock = "std_mutex")]`
+/// - `#[singleton(lock = "parking_lot")]`
+///
+/// The swap lock is meant for read-mostly singletons: uses with an immutable reference read a
+/// snapshot of the singleton without locking, while uses with a mutable reference modify a clone
+/// of the singleton and publish it when they finish (one at a time). This requires that the struct
+/// or enum is [Clone], and the `swap` feature of blockz (enabled by default).
+///
+/// Singletons that are never mutated after their initialization don't need a lock at all. These
+/// can't have `&mut self` singleton fns (or be replaced) and they also get a synchronous
+/// `get_singleton() -> &'static Self` accessor. This requires that the struct or enum is [Sync].
+///
+/// The sync locks (`std_mutex` and `parking_lot`) are meant for singletons used by sync code, such
+/// as callbacks or `spawn_blocking` closures: they can only be used by sync singleton fns (async
+/// ones fail to compile) and the Singleton::*use_*singleton_blocking functions (the async ones
+/// panic), and they don't support guards, lazy initialization or lock timeouts.
+///
+/// A static can't be generic, so generic types must declare the instantiations that are
+/// singletons, each of them getting its own static:
+///
+/// - `#[singleton(instance = "Cache<String, User>", instance = "Cache<u64, Session>")]`
+///
+/// Instead of calling `init_singleton`, a singleton can be initialized lazily by an async
+/// function that returns a `Result` with the singleton. The function runs on the first use of the
+/// singleton, exactly once even if the first uses are concurrent. If it fails, the use fails with
+/// `SingletonError::InitFailed` (or panics) and the next use retries the initialization, after an
+/// optional backoff:
+///
+/// - `#[singleton(init = "DbConnPool::connect")]`
+/// - `#[singleton(init = "DbConnPool::connect", init_backoff = "5s")]`
+///
+/// By default, uses of the singleton wait for the lock indefinitely. With a lock timeout, uses
+/// that don't acquire the lock in time fail with `SingletonError::LockTimeout` (or panic):
+///
+/// - `#[singleton(lock_timeout = "2s")]`
+///
+/// A lazily initialized singleton whose value is taken out (with `take_singleton` or, with the
+/// `test-util` feature, `reset_singleton`) is initialized again by its next use. Singletons
+/// without a lock don't implement MutableSingleton, so their values can't be replaced or taken
+/// out.
+///
+/// The async locks and the timeouts come from tokio by default (requires the `tokio-runtime`
+/// feature). Singletons used by crates that must not depend on a particular executor (async-std,
+/// smol etc) can use the executor-independent ones from `blockz::singleton::agnostic` instead
+/// (requires the `singleton_agnostic` feature, which doesn't pull in tokio):
+///
+/// - `#[singleton(runtime = "tokio")]` _(default)_
+/// - `#[singleton(runtime = "agnostic")]`
+///
+/// Besides singleton fns, the singleton can be borrowed for a scope with `read_guard().await` (and
+/// `write_guard().await`, for singletons with a lock), which return guards that keep the singleton
+/// locked until they are dropped. They come from GuardSingleton and MutableGuardSingleton, which
+/// singletons with a sync lock don't implement. The guards can be projected to a field with
+/// `SingletonReadGuard::map` and `SingletonWriteGuard::map`.
+///
+/// Tests that need a singleton with a different value can override it for a future with
+/// `Singleton::scope(value, fut).await`, without touching the singleton. The override is local
+/// to the future, with any executor, so tests with their own scopes can run concurrently. The
+/// value is dropped with the scope (even if the future panics), so `get_singleton` panics within
+/// it: borrow the value with `read_guard().await` there.
+///
+/// Watched singletons publish their value after each change (initialization, replacement,
+/// `&mut self` singleton fns and released write guards) to the subscribers created by
+/// `WatchSingleton::subscribe()`, which are `tokio::sync::watch` receivers (so watched singletons
+/// require the `tokio-runtime` feature). The published value is
+/// either a clone of the singleton (which must be [Clone]) or a projection of it, for a type that
+/// implements `From<&Self>`:
+///
+/// - `#[singleton(watch)]`
+/// - `#[singleton(watch = "RouteCount")]`
+///
+/// Keyed singletons hold a value for each key, such as the connection pools of the primary and
+/// replica databases, and implement KeyedSingleton instead of Singleton. Each key is initialized
+/// with `init_singleton_keyed(key, value)` and used by the `*_keyed` functions, which take the key
+/// as their first argument. The keys are strings by default, or a type that is [Clone], `Hash` and
+/// `Eq`, such as an enum. Keyed singletons can't be watched or initialized lazily:
+///
+/// - `#[singleton(keyed)]`
+/// - `#[singleton(keyed = "DbRole")]`
+///
+/// Sharded singletons split their state into a number of shards, each with its own lock, to reduce
+/// the contention on singletons such as counters. Their singleton fns are routed to the shard of
+/// the argument named by `shard_by`, which must implement `Hash`. They are keyed singletons whose
+/// keys are the indices of the shards, and they also implement ShardedSingleton, which initializes
+/// all the shards with `init_shards(|shard| value)` and locks all of them for whole-state
+/// operations with `read_shards().await`. Sharded singletons can't be watched or initialized
+/// lazily:
+///
+/// - `#[singleton(shards = 16, shard_by = "user_id")]`
+///
+/// Actor singletons don't have a lock: their value lives in a task that serves the uses one at a
+/// time, in the order in which they arrive in its bounded mailbox. Singleton fns are sent to the
+/// task as messages and run there, against the value it owns, so their arguments must be
+/// `'static` (owned) and a panic resumes in the caller; guards borrow the value from the task
+/// until they are dropped. When the mailbox is full, uses either wait for room
+/// (`overflow = "wait"`, the default) or fail with `SingletonError::MailboxFull`
+/// (`overflow = "fail"`). Taking the value out of the actor stops its task: the uses in the mailbox
+/// either run first (`stop = "drain"`, the default) or fail with `SingletonError::Stopped`
+/// (`stop = "abort"`). Actor singletons require the tokio runtime and they can't be keyed, sharded
+/// or initialized lazily:
+///
+/// - `#[singleton(actor)]`
+/// - `#[singleton(actor(mailbox = 64, overflow = "fail", stop = "abort"))]`
+///
+/// Trait singletons hold a boxed trait object (`Box<dyn DbApi + Send + Sync>`) instead of the
+/// type itself, so that tests can install a mock of the singleton with `init_singleton` or
+/// `scope`. The trait is generated from the singleton fns by `#[singleton_impl]` (see
+/// [macro@singleton_impl]), and the lazy initialization function of a trait singleton returns the
+/// boxed trait object. Trait singletons can't go without a lock:
+///
+/// - `#[singleton(trait = "DbApi")]`
 ///
 /// Required available imports:
 /// - [anyhow]
 /// - [async_trait]
 /// - [blockz]
 /// - [once_cell]
-/// - [tokio]
+/// - [tokio] (only with `runtime = "tokio"`)
+/// - [parking_lot] (only with `lock = "parking_lot"`)
 ///
 /// [Send]: https://doc.rust-lang.org/stable/std/marker/trait.Send.html
+/// [Clone]: https://doc.rust-lang.org/stable/std/clone/trait.Clone.html
+/// [Sync]: https://doc.rust-lang.org/stable/std/marker/trait.Sync.html
 /// [anyhow]: https://docs.rs/anyhow
 /// [async_trait]: https://docs.rs/async_trait
 /// [blockz]: https://github.com/selftechio/blockz
 /// [once_cell]: https://docs.rs/once_cell
 /// [tokio]: https://docs.rs/tokio
+/// [parking_lot]: https://docs.rs/parking_lot
 #[proc_macro_derive(Singleton, attributes(singleton))]
 #[cfg(feature = "singleton")]
 #[cfg_attr(docsrs, doc(cfg(feature = "singleton")))]
@@ -81,18 +201,93 @@ pub fn derive_singleton(input: TokenStream) -> TokenStream {
 /// The input function must have the following properties:
 ///
 /// - the first argument must be a reference receiver (&self or &mut self)
-/// - function arguments identifiers must not conflict with other identifiers from the function body
-///   (such as other function names in function calls, struct fields etc)
-/// - references are not allowed (use Box\<T\> or Arc\<T\> instead)
+/// - the output must not borrow from the singleton, which is only locked while the function runs
+///   (except for guard methods, see below)
+///
+/// Arguments may be references (such as `&str` or `&[u8]`), including multiple arguments with
+/// elided lifetimes. The output may borrow from them if their lifetime is named:
+/// `async fn find<'a>(&self, names: &'a [String]) -> Option<&'a String>`.
+///
+/// Generic type parameters, where-clauses and `impl Trait` arguments are supported, such as
+/// `async fn put<K: Into<String>>(&mut self, key: K, value: impl AsRef<[u8]>)`. Async singleton
+/// fns move their arguments into a Send future, so the generic types get a Send bound (or a Sync
+/// bound, if they are only used behind shared references).
+///
+/// Arguments can be irrefutable patterns, such as `(x, y): (i64, i64)` or `Point { x, y }: Point`.
+/// The modified method binds them to identifiers, and the pattern is restored before running the
+/// body of the method.
+///
+/// The modified method panics if the singleton has not been initialized. With
+/// `#[singleton_fn(fallible)]`, the method returns a `Result` instead, failing with
+/// `SingletonError::NotInitialized`.
+///
+/// With `#[singleton_fn(timeout = "500ms")]`, the method also returns a `Result`, failing with
+/// `SingletonError::LockTimeout` if the call (including the lock acquisition) does not finish in
+/// time. The call is dropped when it times out, which releases the lock.
+///
+/// Sync methods become sync functions that block the current thread while waiting for the lock
+/// (see Singleton::use_singleton_blocking), so they can't have a timeout.
+///
+/// With `#[singleton_fn(guard)]`, a sync method that returns a reference into the singleton, such
+/// as `fn routes(&self) -> &RoutingTable`, becomes an async function that returns a guard which
+/// dereferences to it (`impl Deref<Target = RoutingTable>`, or `impl DerefMut` for `&mut self`
+/// methods that return `&mut RoutingTable`). The singleton stays locked until the guard is
+/// dropped. Guard methods can be fallible, but they can't have a timeout, and they need a
+/// singleton with an async lock (or no lock, for `&self` methods).
+///
+/// Methods of keyed singletons must be marked with `#[singleton_fn(keyed)]`. The modified method
+/// is renamed to `<name>_on` and takes the key as its first argument, such as
+/// `DbPool::query_on("replica", sql)`.
+///
+/// Methods of sharded singletons are routed to the shard of their argument named by the `shard_by`
+/// of the singleton, and methods that don't have the argument fail to compile. A method can be
+/// routed by another argument with `#[singleton_fn(shard_by = "name")]`.
 #[proc_macro_attribute]
 #[cfg(feature = "singleton")]
 #[cfg_attr(docsrs, doc(cfg(feature = "singleton")))]
-pub fn singleton_fn(_: TokenStream, item: TokenStream) -> TokenStream {
+pub fn singleton_fn(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as AttributeArgs);
     let input = parse_macro_input!(item as ItemFn);
-    factory!(SingletonFnFactory::new(&input))
+    factory!(SingletonFnFactory::new(&args, &input))
 }
 
-/// Derive the Configuration trait.
+/// Generate the trait of a trait singleton from the singleton fns of an impl block.
+///
+/// The `#[singleton_fn]` methods of the impl block become the methods of an async trait (through
+/// [async_trait]) that is implemented by the type, while the type gets their facade fns. The
+/// singleton fns of a trait singleton call the trait methods of its inner value, which is either
+/// the type or any other implementation of the trait, such as a mock:
+///
+/// - `#[singleton_impl(trait = "DbApi")]` on `impl DbConnPool`, with `#[singleton(trait = "DbApi")]`
+///   on `DbConnPool`
+/// - `DbConnPool::init_singleton(Box::new(pool))` installs the real implementation and
+///   `DbConnPool::scope(Box::new(mock), fut)` overrides it in a test
+///
+/// The trait is `pub` by default: `#[singleton_impl(trait = "DbApi", vis = "pub(crate)")]`.
+///
+/// Attributes of the trait are given with `trait_attr`, which can be repeated. They are placed
+/// before the `#[async_trait]` of the trait, so a mock that checks the calls it receives can be
+/// generated with [mockall]:
+///
+/// - `#[singleton_impl(trait = "DbApi", trait_attr = "cfg_attr(test, mockall::automock)")]`
+/// - `DbConnPool::scope(Box::new(mock), fut)`, with a `MockDbApi` whose expectations are set by
+///   `mock.expect_query().times(1).returning(...)`
+///
+/// The trait is used as a trait object, so the singleton fns can't have generic type parameters
+/// or `impl Trait` arguments, and they can't be guard singleton fns.
+///
+/// [async_trait]: https://docs.rs/async_trait
+/// [mockall]: https://docs.rs/mockall
+#[proc_macro_attribute]
+#[cfg(feature = "singleton")]
+#[cfg_attr(docsrs, doc(cfg(feature = "singleton")))]
+pub fn singleton_impl(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as AttributeArgs);
+    let input = parse_macro_input!(item as ItemImpl);
+    factory!(SingletonImplFactory::new(&args, &input))
+}
+
+/// Derive the Configuration and BlockingConfiguration traits.
 ///
 /// This requires that the struct or enum is [Send].
 ///
@@ -108,6 +303,24 @@ pub fn singleton_fn(_: TokenStream, item: TokenStream) -> TokenStream {
 /// The prefix source will be interpreted as an expression that will be used to source the prefix.
 /// You can use either constants or functions.
 ///
+/// Generic configurations are supported, with the configuration being loadable for the generic
+/// arguments that satisfy the requirements of the source (for example Deserialize for env
+/// configurations).
+///
+/// Env configurations can also be derived for enums. The variant is selected by the
+/// `{prefix}KIND` variable and its fields are read from the variables with the `{prefix}{VARIANT}_`
+/// prefix, where the variant name is converted to snake case:
+///
+/// - the tag variable can be changed: `#[configuration(env(prefix = "MY_PREFIX", tag = "backend"))]`
+/// - a variant can be renamed: `#[configuration(rename = "mem")]`
+/// - variants can be unit variants, struct variants or tuple variants with a single field that
+///   implements Deserialize
+///
+/// Adding `global` (for example `#[configuration(env(prefix = "MY_PREFIX"), global)]`) generates
+/// `init_global()`, which loads the configuration with EasyConfiguration and stores it in a static,
+/// and `global()` / `try_global()`, which return a `&'static` reference to it without locking.
+/// Concurrent `init_global()` calls wait for a single load, and a failed load can be retried.
+///
 /// Required available imports:
 /// - [anyhow]
 /// - [async_trait]
@@ -119,7 +332,7 @@ pub fn singleton_fn(_: TokenStream, item: TokenStream) -> TokenStream {
 /// [async_trait]: https://docs.rs/async_trait
 /// [blockz]: https://github.com/selftechio/blockz
 /// [config]: https://docs.rs/config
-#[proc_macro_derive(Configuration, attributes(configuration))]
+#[proc_macro_derive(Configuration, attributes(configuration, serde))]
 #[cfg(feature = "configuration")]
 #[cfg_attr(docsrs, doc(cfg(feature = "configuration")))]
 pub fn derive_configuration(input: TokenStream) -> TokenStream {
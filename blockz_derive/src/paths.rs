@@ -65,3 +65,23 @@ pub(crate) fn std_path() -> TokenStream {
 pub(crate) fn std_path() -> TokenStream {
     quote! { ::std }
 }
+
+#[cfg(feature = "no_absolute_paths")]
+pub(crate) fn serde_path() -> TokenStream {
+    quote! { serde }
+}
+
+#[cfg(not(feature = "no_absolute_paths"))]
+pub(crate) fn serde_path() -> TokenStream {
+    quote! { ::serde }
+}
+
+#[cfg(feature = "no_absolute_paths")]
+pub(crate) fn parking_lot_path() -> TokenStream {
+    quote! { parking_lot }
+}
+
+#[cfg(not(feature = "no_absolute_paths"))]
+pub(crate) fn parking_lot_path() -> TokenStream {
+    quote! { ::parking_lot }
+}
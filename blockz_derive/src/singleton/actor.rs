@@ -0,0 +1,98 @@
+//! Singleton actor.
+
+use darling::FromMeta;
+
+use proc_macro2::TokenStream;
+
+use quote::quote;
+
+use syn::NestedMeta;
+
+use crate::paths;
+
+/// The default capacity of the mailbox of an actor.
+const DEFAULT_MAILBOX: usize = 32;
+
+/// The options of an actor singleton: `#[singleton(actor)]` or
+/// `#[singleton(actor(mailbox = 64, overflow = "fail", stop = "abort"))]`.
+#[derive(Default)]
+pub(super) struct SingletonActor(SingletonActorOpts);
+
+#[derive(Default, FromMeta)]
+struct SingletonActorOpts {
+    #[darling(default)]
+    mailbox: Option<usize>,
+    #[darling(default)]
+    overflow: ActorOverflow,
+    #[darling(default)]
+    stop: ActorStop,
+}
+
+/// What a use does when the mailbox of the actor is full.
+#[derive(Default, FromMeta)]
+enum ActorOverflow {
+    /// Wait until the mailbox has room.
+    #[darling(rename = "wait")]
+    #[default]
+    Wait,
+    /// Fail with SingletonError::MailboxFull.
+    #[darling(rename = "fail")]
+    Fail,
+}
+
+/// What happens to the uses in the mailbox when the actor is stopped.
+#[derive(Default, FromMeta)]
+enum ActorStop {
+    /// The uses in the mailbox run before the actor stops.
+    #[darling(rename = "drain")]
+    #[default]
+    Drain,
+    /// The uses in the mailbox fail with SingletonError::Stopped.
+    #[darling(rename = "abort")]
+    Abort,
+}
+
+impl SingletonActor {
+    /// Create the expression for the configuration of the actor.
+    pub fn to_config_expr(&self) -> TokenStream {
+        let blockz = paths::blockz_path();
+        let mailbox = self.0.mailbox.unwrap_or(DEFAULT_MAILBOX);
+        let overflow = match self.0.overflow {
+            ActorOverflow::Wait => quote! { Wait },
+            ActorOverflow::Fail => quote! { Fail },
+        };
+        let stop = match self.0.stop {
+            ActorStop::Drain => quote! { Drain },
+            ActorStop::Abort => quote! { Abort },
+        };
+        quote! {
+            #blockz::singleton::actor::ActorConfig {
+                mailbox: #mailbox,
+                overflow: #blockz::singleton::actor::ActorOverflow::#overflow,
+                stop: #blockz::singleton::actor::ActorStop::#stop,
+            }
+        }
+    }
+}
+
+impl FromMeta for SingletonActor {
+    fn from_word() -> darling::Result<Self> {
+        Ok(Self::default())
+    }
+
+    fn from_list(items: &[NestedMeta]) -> darling::Result<Self> {
+        let opts = SingletonActorOpts::from_list(items)?;
+        if opts.mailbox == Some(0) {
+            let mailbox = items.iter().find(|item| match item {
+                NestedMeta::Meta(meta) => meta.path().is_ident("mailbox"),
+                NestedMeta::Lit(_) => false,
+            });
+            let err = darling::Error::custom("the mailbox of an actor must hold at least 1 use");
+            return Err(match mailbox {
+                Some(mailbox) => err.with_span(mailbox),
+                None => err,
+            });
+        }
+        Ok(Self(opts))
+    }
+}
@@ -1,30 +1,409 @@
 //! Implement the singleton static.
 
-use proc_macro2::Ident;
 use proc_macro2::TokenStream;
 
+use quote::format_ident;
 use quote::quote;
 
 use crate::common;
 use crate::factory::Factory;
 use crate::paths;
 
-use super::lock::SingletonLock;
+use super::keyed::SingletonKey;
+use super::SingletonInstance;
+use super::SingletonOpts;
 
 /// Factory that builds a singleton static.
 pub(super) struct SingletonStaticFactory<'f> {
-    static_ident: &'f Ident,
-    type_name: &'f Ident,
-    lock: &'f SingletonLock,
+    instance: &'f SingletonInstance,
+    opts: &'f SingletonOpts,
 }
 
 impl<'f> SingletonStaticFactory<'f> {
     /// Create a new singleton static factory.
-    pub fn new(static_ident: &'f Ident, type_name: &'f Ident, lock: &'f SingletonLock) -> Self {
-        Self {
-            static_ident,
-            type_name,
-            lock,
+    pub fn new(instance: &'f SingletonInstance, opts: &'f SingletonOpts) -> Self {
+        Self { instance, opts }
+    }
+
+    /// Build the statement that fails fast while backing off from a failed initialization.
+    fn build_backoff(&self, last_failure: TokenStream) -> Option<TokenStream> {
+        let blockz = paths::blockz_path();
+        let name = &self.instance.name;
+        self.opts.init_backoff.map(|backoff| {
+            let backoff = backoff.to_expr();
+            quote! {
+                {
+                    let last_failure = #last_failure;
+                    if let Some((failed_at, reason)) = &*last_failure {
+                        if failed_at.elapsed() < #backoff {
+                            return Err(#blockz::singleton::SingletonError::InitFailed(#name, reason.clone()));
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Build the static that holds the entry of the singleton, the scope local that overrides it in
+    /// a scope and the function that gets the entry in effect.
+    ///
+    /// The entries of scopes are owned by Singleton::scope, so the entry in effect is either the
+    /// static or a handle to the entry of the scope.
+    fn build_entry(&self) -> TokenStream {
+        // get paths to deps
+        let blockz = paths::blockz_path();
+        let once_cell = paths::once_cell_path();
+        let std = paths::std_path();
+
+        let static_ident = &self.instance.static_ident;
+        let scope_static_ident = &self.instance.scope_static_ident;
+        let entry_fn_ident = &self.instance.entry_fn_ident;
+        let entry_type = self.instance.to_entry_type(self.opts);
+
+        // create doc comments
+        let doc = common::create_doc(format!("Singleton for {}.", self.instance.name));
+        let scope_doc = common::create_doc(format!(
+            "Scope that overrides the singleton for {}.",
+            self.instance.name
+        ));
+        let entry_doc = common::create_doc(format!(
+            "Get the entry of the singleton for {}, or of the scope that overrides it.",
+            self.instance.name
+        ));
+
+        quote! {
+            #doc
+            #[automatically_derived]
+            static #static_ident: #entry_type = (
+                #once_cell::sync::OnceCell::new(),
+                #std::sync::atomic::AtomicBool::new(false),
+            );
+
+            #blockz::singleton::scope_local! {
+                #scope_doc
+                static #scope_static_ident: #std::sync::Arc<#entry_type>;
+            }
+
+            #entry_doc
+            #[automatically_derived]
+            #[doc(hidden)]
+            fn #entry_fn_ident() -> #blockz::singleton::scope::Entry<#entry_type> {
+                #blockz::singleton::scope::Entry::current(&#scope_static_ident, &#static_ident)
+            }
+        }
+    }
+
+    /// Build the lazy initialization of the singleton, if it has an init function.
+    ///
+    /// The initialization runs while holding the singleton lock, so concurrent first accesses run
+    /// the init function only once. The last failure is kept in a static, so failed
+    /// initializations are retried by the next access (after the backoff, if there is one).
+    fn build_lazy_init(&self) -> TokenStream {
+        let init = if let Some(value) = &self.opts.init {
+            value
+        } else {
+            return TokenStream::new();
+        };
+
+        // get paths to deps
+        let anyhow = paths::anyhow_path();
+        let blockz = paths::blockz_path();
+        let std = paths::std_path();
+
+        let entry_fn_ident = &self.instance.entry_fn_ident;
+        let init_static_ident = &self.instance.init_static_ident;
+        let get_fn_ident = &self.instance.get_fn_ident;
+        let inner_type = &self.instance.inner;
+        let name = &self.instance.name;
+        let lock_ident = format_ident!("inner_lock");
+        let new_lock_expr = self
+            .opts
+            .lock
+            .to_new_lock_expr(&self.opts.runtime, quote! { None });
+        let lock_guard_expr = self.opts.lock.to_mut_guard(&self.opts.runtime, &lock_ident);
+
+        // watched singletons publish the initialized value
+        let publish = self.opts.watch.as_ref().map(|watch| {
+            self.instance.to_unscoped(watch.to_publish(
+                &self.instance.watch_static_ident,
+                &self.instance.inner,
+                quote! { &inner },
+            ))
+        });
+
+        // fail fast while backing off
+        let backoff = self.build_backoff(quote! {
+            #init_static_ident
+                .lock()
+                .unwrap_or_else(#std::sync::PoisonError::into_inner)
+        });
+
+        // create doc comments
+        let init_doc = common::create_doc(format!(
+            "Last failed lazy initialization of the singleton for {}.",
+            name
+        ));
+        let get_doc = common::create_doc(format!(
+            "Initialize the singleton for {}, if it holds no value.",
+            name
+        ));
+
+        quote! {
+            #init_doc
+            #[automatically_derived]
+            static #init_static_ident: #std::sync::Mutex<Option<(#std::time::Instant, String)>> =
+                #std::sync::Mutex::new(None);
+
+            #get_doc
+            #[automatically_derived]
+            #[doc(hidden)]
+            async fn #get_fn_ident() -> Result<(), #blockz::singleton::SingletonError> {
+                let entry = #entry_fn_ident();
+                let #lock_ident = entry.0.get_or_init(|| #new_lock_expr);
+                let mut inner_guard = #lock_guard_expr;
+                if inner_guard.is_some() {
+                    return Ok(());
+                }
+                #backoff
+                let result: Result<#inner_type, _> = #init().await;
+                let failure = match result {
+                    Ok(inner) => {
+                        #publish
+                        *inner_guard = Some(inner);
+                        entry.1.store(true, #std::sync::atomic::Ordering::SeqCst);
+                        None
+                    }
+                    Err(err) => {
+                        let reason = format!("{:#}", #anyhow::Error::from(err));
+                        Some((#std::time::Instant::now(), reason))
+                    }
+                };
+                let mut last_failure = #init_static_ident
+                    .lock()
+                    .unwrap_or_else(#std::sync::PoisonError::into_inner);
+                *last_failure = failure;
+                match &*last_failure {
+                    Some((_, reason)) => Err(#blockz::singleton::SingletonError::InitFailed(#name, reason.clone())),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Build the registry of a keyed singleton and the function that gets the entry of a key.
+    ///
+    /// Each key gets an entry like the static (and the initialized flag) of a singleton, which is
+    /// created by the first use of the key and never removed, so its lock can be borrowed for the
+    /// 'static lifetime.
+    fn build_registry(&self) -> TokenStream {
+        let keyed = if let Some(value) = &self.opts.keyed {
+            value
+        } else {
+            return TokenStream::new();
+        };
+
+        // get paths to deps
+        let once_cell = paths::once_cell_path();
+        let std = paths::std_path();
+
+        let static_ident = &self.instance.static_ident;
+        let entry_fn_ident = &self.instance.entry_fn_ident;
+        let key_type = keyed.to_key_type();
+        let owned_key_type = keyed.to_owned_key_type();
+        let entry_type = self.instance.to_entry_type(self.opts);
+
+        // sharded singletons have a fixed table of shards
+        if let SingletonKey::Shards(shards) = keyed {
+            return self.build_shard_table(*shards, entry_type);
+        }
+
+        // create doc comments
+        let doc = common::create_doc(format!(
+            "Registry of the keys of the singleton for {}.",
+            self.instance.name
+        ));
+        let entry_doc = common::create_doc(format!(
+            "Get the entry of a key in the singleton for {}, creating it if needed.",
+            self.instance.name
+        ));
+
+        quote! {
+            #doc
+            #[automatically_derived]
+            static #static_ident: #once_cell::sync::Lazy<
+                #std::sync::RwLock<#std::collections::HashMap<#owned_key_type, &'static #entry_type>>,
+            > = #once_cell::sync::Lazy::new(Default::default);
+
+            #entry_doc
+            #[automatically_derived]
+            #[doc(hidden)]
+            fn #entry_fn_ident(key: &#key_type) -> &'static #entry_type {
+                let entry = #static_ident
+                    .read()
+                    .unwrap_or_else(#std::sync::PoisonError::into_inner)
+                    .get(key)
+                    .copied();
+                if let Some(entry) = entry {
+                    return entry;
+                }
+                let mut registry = #static_ident
+                    .write()
+                    .unwrap_or_else(#std::sync::PoisonError::into_inner);
+                *registry
+                    .entry(#std::borrow::ToOwned::to_owned(key))
+                    .or_insert_with(|| {
+                        Box::leak(Box::new((
+                            #once_cell::sync::OnceCell::new(),
+                            #std::sync::atomic::AtomicBool::new(false),
+                        )))
+                    })
+            }
+        }
+    }
+
+    /// Build the table of the shards of a sharded singleton and the function that gets the entry
+    /// of a shard.
+    ///
+    /// Each shard gets an entry like the entry of a key in a keyed singleton, but the shards are
+    /// created together with the table.
+    fn build_shard_table(&self, shards: usize, entry_type: TokenStream) -> TokenStream {
+        // get paths to deps
+        let once_cell = paths::once_cell_path();
+
+        let static_ident = &self.instance.static_ident;
+        let entry_fn_ident = &self.instance.entry_fn_ident;
+        let name = &self.instance.name;
+
+        // create doc comments
+        let doc = common::create_doc(format!(
+            "Shards of the singleton for {}.",
+            self.instance.name
+        ));
+        let entry_doc = common::create_doc(format!(
+            "Get the entry of a shard in the singleton for {}.",
+            self.instance.name
+        ));
+
+        quote! {
+            #doc
+            #[automatically_derived]
+            static #static_ident: #once_cell::sync::Lazy<Vec<#entry_type>> =
+                #once_cell::sync::Lazy::new(|| (0..#shards).map(|_| Default::default()).collect());
+
+            #entry_doc
+            #[automatically_derived]
+            #[doc(hidden)]
+            fn #entry_fn_ident(key: &usize) -> &'static #entry_type {
+                match #static_ident.get(*key) {
+                    Some(entry) => entry,
+                    None => panic!(
+                        "{}: singleton: shard {} is out of range (the singleton has {} shards)",
+                        #name,
+                        key,
+                        #shards,
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Build the static that publishes the values of the singleton, if it is watched.
+    fn build_watch_static(&self) -> TokenStream {
+        let watch = if let Some(value) = &self.opts.watch {
+            value
+        } else {
+            return TokenStream::new();
+        };
+
+        // get paths to deps
+        let blockz = paths::blockz_path();
+        let once_cell = paths::once_cell_path();
+
+        let watch_static_ident = &self.instance.watch_static_ident;
+        let sender_type = watch.to_sender_type(&self.instance.inner);
+        let doc = common::create_doc(format!(
+            "Publishes the values of the singleton for {}.",
+            self.instance.name
+        ));
+        quote! {
+            #doc
+            #[automatically_derived]
+            static #watch_static_ident: #once_cell::sync::Lazy<#sender_type> =
+                #once_cell::sync::Lazy::new(|| #blockz::singleton::watch::channel(None).0);
+        }
+    }
+
+    /// Build the lazy initialization of a singleton without a lock, if it has an init function.
+    ///
+    /// The initialization is guarded by an async mutex that holds the last failure, so concurrent
+    /// first accesses run the init function only once.
+    fn build_unlocked_lazy_init(&self) -> TokenStream {
+        let init = if let Some(value) = &self.opts.init {
+            value
+        } else {
+            return TokenStream::new();
+        };
+
+        // get paths to deps
+        let anyhow = paths::anyhow_path();
+        let blockz = paths::blockz_path();
+        let once_cell = paths::once_cell_path();
+        let std = paths::std_path();
+        let sync = self.opts.runtime.sync_path();
+
+        let init_static_ident = &self.instance.init_static_ident;
+        let get_fn_ident = &self.instance.get_fn_ident;
+        let entry_type = self.instance.to_entry_type(self.opts);
+        let inner_type = &self.instance.inner;
+        let name = &self.instance.name;
+
+        // fail fast while backing off
+        let backoff = self.build_backoff(quote! { &*last_failure });
+
+        // create doc comments
+        let init_doc = common::create_doc(format!(
+            "Lazy initialization guard for the singleton for {}.",
+            name
+        ));
+        let get_doc = common::create_doc(format!(
+            "Get the singleton for {} from the entry in effect, initializing it if needed.",
+            name
+        ));
+
+        quote! {
+            #init_doc
+            #[automatically_derived]
+            static #init_static_ident: #once_cell::sync::Lazy<
+                #sync::Mutex<Option<(#std::time::Instant, String)>>,
+            > = #once_cell::sync::Lazy::new(|| #sync::Mutex::new(None));
+
+            #get_doc
+            #[automatically_derived]
+            #[doc(hidden)]
+            async fn #get_fn_ident(
+                entry: &#entry_type,
+            ) -> Result<&#inner_type, #blockz::singleton::SingletonError> {
+                if let Some(value) = entry.0.get() {
+                    return Ok(value);
+                }
+                let mut last_failure = #init_static_ident.lock().await;
+                if let Some(value) = entry.0.get() {
+                    return Ok(value);
+                }
+                #backoff
+                let result: Result<#inner_type, _> = #init().await;
+                match result {
+                    Ok(inner) => {
+                        *last_failure = None;
+                        Ok(entry.0.get_or_init(move || inner))
+                    }
+                    Err(err) => {
+                        let reason = format!("{:#}", #anyhow::Error::from(err));
+                        *last_failure = Some((#std::time::Instant::now(), reason.clone()));
+                        Err(#blockz::singleton::SingletonError::InitFailed(#name, reason))
+                    }
+                }
+            }
         }
     }
 }
@@ -34,22 +413,34 @@ impl<'f> Factory for SingletonStaticFactory<'f> {
 
     /// Build the singleton static.
     fn build(self) -> Self::Product {
-        // get paths to deps
-        let once_cell = paths::once_cell_path();
+        // keyed singletons have a registry of the keys
+        if self.opts.keyed.is_some() {
+            return Ok(self.build_registry());
+        }
 
-        // create doc comment
-        let doc = common::create_doc(format!("Singleton for {}.", self.type_name));
+        // get the entry of the singleton and its scope
+        let entry = self.build_entry();
 
-        // get lock type
-        let lock_type = self.lock.to_type(self.type_name);
+        // singletons without a lock hold the value directly
+        if !self.opts.lock.has_lock() {
+            let lazy_init = self.build_unlocked_lazy_init();
+            return Ok(quote! {
+                #entry
+                #lazy_init
+            });
+        }
+
+        // get the lazy initialization
+        let lazy_init = self.build_lazy_init();
+
+        // get the static that publishes the values of a watched singleton
+        let watch_static = self.build_watch_static();
 
         // return implementation
-        let static_ident = self.static_ident;
         Ok(quote! {
-            #doc
-            #[automatically_derived]
-            static #static_ident: #once_cell::sync::OnceCell<#lock_type> =
-                #once_cell::sync::OnceCell::new();
+            #entry
+            #watch_static
+            #lazy_init
         })
     }
 }
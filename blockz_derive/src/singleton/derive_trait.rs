@@ -1,216 +1,1338 @@
-//! Implement the singleton static.
+//! Implement the singleton trait.
 
+use proc_macro2::Ident;
 use proc_macro2::TokenStream;
 
 use quote::format_ident;
 use quote::quote;
 
-use syn::Ident;
-
 use crate::common;
 use crate::factory::Factory;
 use crate::paths;
 
+use super::keyed;
+use super::keyed::SingletonKey;
 use super::lock::SingletonLock;
+use super::SingletonInstance;
+use super::SingletonOpts;
 
 /// Factory that builds a singleton trait implementation.
 pub(super) struct SingletonTraitFactory<'f> {
-    static_ident: &'f Ident,
-    type_name: &'f Ident,
-    lock: &'f SingletonLock,
+    instance: &'f SingletonInstance,
+    opts: &'f SingletonOpts,
+}
+
+/// A flavour of the Singleton::use_*singleton* functions.
+#[derive(Clone, Copy)]
+struct UseFn {
+    /// Whether the function uses a mutable singleton.
+    mutable: bool,
+    /// Whether the function passes an argument to the clojure.
+    with_arg: bool,
+    /// Whether the function returns an error instead of panicking.
+    fallible: bool,
+    /// Whether the whole call is bounded by a timeout (which implies fallible).
+    timeout: bool,
+}
+
+impl UseFn {
+    /// All the flavours of the use functions.
+    fn all() -> impl Iterator<Item = UseFn> {
+        (0..12).map(|bits| UseFn {
+            mutable: bits & 1 != 0,
+            with_arg: bits & 2 != 0,
+            fallible: bits & 4 != 0 || bits & 8 != 0,
+            timeout: bits & 8 != 0,
+        })
+    }
 }
 
 impl<'f> SingletonTraitFactory<'f> {
     /// Create a new singleton trait factory.
-    pub fn new(static_ident: &'f Ident, type_name: &'f Ident, lock: &'f SingletonLock) -> Self {
-        Self {
-            static_ident,
-            type_name,
-            lock,
+    pub fn new(instance: &'f SingletonInstance, opts: &'f SingletonOpts) -> Self {
+        Self { instance, opts }
+    }
+
+    /// Create the expression for the entry of the singleton (or, for keyed singletons, for the
+    /// entry of the key).
+    fn build_entry(&self) -> TokenStream {
+        let entry_fn_ident = &self.instance.entry_fn_ident;
+        if self.opts.keyed.is_some() {
+            quote! { #entry_fn_ident(key) }
+        } else {
+            quote! { #entry_fn_ident() }
+        }
+    }
+
+    /// Create the statement that gets the entry in effect (as entry), which the expressions of
+    /// the static borrow from.
+    fn build_entry_stmt(&self) -> TokenStream {
+        let entry = self.build_entry();
+        quote! { let entry = #entry; }
+    }
+
+    /// Create the statement that borrows the entry in effect for the 'static lifetime (as entry),
+    /// along with the owner of the entry of a scope (as entry_owner), which guards keep alive.
+    ///
+    /// The entries of keyed singletons are never dropped, so they don't have an owner.
+    fn build_static_entry_stmt(&self) -> TokenStream {
+        let entry = self.build_entry();
+        if self.opts.keyed.is_some() {
+            quote! { let entry = #entry; }
+        } else {
+            quote! {
+                // SAFETY: the entry is only borrowed by the guard, which keeps its owner
+                let (entry, entry_owner) = unsafe { #entry.into_static() };
+            }
+        }
+    }
+
+    /// Create the expression that makes a guard keep the owner of the entry (see
+    /// build_static_entry_stmt).
+    fn build_keep_owner(&self, guard_ident: &Ident, guard: TokenStream) -> TokenStream {
+        if self.opts.keyed.is_some() {
+            return guard;
+        }
+        let blockz = paths::blockz_path();
+        quote! { #blockz::singleton::#guard_ident::with_owner(#guard, entry_owner) }
+    }
+
+    /// Create the expression for the static that holds the singleton (or, for keyed singletons,
+    /// for the entry of the key), from the entry in effect.
+    fn build_static(&self) -> TokenStream {
+        quote! { entry.0 }
+    }
+
+    /// Create the expression for the static that tracks whether the singleton holds a value (or,
+    /// for keyed singletons, for the entry of the key), from the entry in effect.
+    fn build_initialized_static(&self) -> TokenStream {
+        quote! { entry.1 }
+    }
+
+    /// Create the name of a function of the singleton traits.
+    ///
+    /// The functions of keyed singletons get the `_keyed` suffix.
+    fn build_fn_ident(&self, name: &str) -> Ident {
+        if self.opts.keyed.is_some() {
+            format_ident!("{}_keyed", name)
+        } else {
+            format_ident!("{}", name)
+        }
+    }
+
+    /// Create the key input of the functions of keyed singletons.
+    fn build_key_input(&self) -> TokenStream {
+        if self.opts.keyed.is_some() {
+            let blockz = paths::blockz_path();
+            quote! { key: &<Self as #blockz::singleton::KeyedSingleton>::Key, }
+        } else {
+            TokenStream::new()
+        }
+    }
+
+    /// Create the statement that discards the key in the functions of keyed singletons that
+    /// panic.
+    fn build_discard_key(&self) -> TokenStream {
+        if self.opts.keyed.is_some() {
+            quote! { let _ = key; }
+        } else {
+            TokenStream::new()
+        }
+    }
+
+    /// Create the expression that gets the singleton lock, creating an empty one if needed.
+    fn build_get_lock(&self) -> TokenStream {
+        let static_expr = self.build_static();
+        let new_lock_expr = self
+            .opts
+            .lock
+            .to_new_lock_expr(&self.opts.runtime, quote! { None });
+        quote! { #static_expr.get_or_init(|| #new_lock_expr) }
+    }
+
+    /// Create the statement that marks whether the singleton holds a value.
+    fn build_set_initialized(&self, value: bool) -> TokenStream {
+        let initialized_static = self.build_initialized_static();
+        let std = paths::std_path();
+        quote! { #initialized_static.store(#value, #std::sync::atomic::Ordering::SeqCst); }
+    }
+
+    /// Create the statement that publishes a new value of a watched singleton, from an expression
+    /// that is a reference to it.
+    fn build_publish(&self, value: TokenStream) -> TokenStream {
+        match &self.opts.watch {
+            Some(watch) => self.instance.to_unscoped(watch.to_publish(
+                &self.instance.watch_static_ident,
+                &self.instance.inner,
+                value,
+            )),
+            None => TokenStream::new(),
+        }
+    }
+
+    /// Create the statement that publishes that a watched singleton holds no value.
+    fn build_publish_none(&self) -> TokenStream {
+        match &self.opts.watch {
+            Some(watch) => self
+                .instance
+                .to_unscoped(watch.to_publish_none(&self.instance.watch_static_ident)),
+            None => TokenStream::new(),
         }
     }
 
     /// Implement Singleton::init_singleton.
     fn impl_init_singleton(&self) -> TokenStream {
-        let static_ident = self.static_ident;
-        let type_name = self.type_name;
+        let name = &self.instance.name;
 
         // get paths to deps
         let anyhow = paths::anyhow_path();
 
         // create lit str error message
-        let err_msg =
-            common::create_lit_str(format!("{}: singleton: already initialized", type_name));
+        let err_msg = common::create_lit_str(format!("{}: singleton: already initialized", name));
 
-        // create doc comment
-        let doc = common::create_doc(format!("Initialize the singleton for {}.", type_name));
+        // create the function name and the doc comment
+        let fn_ident = self.build_fn_ident("init_singleton");
+        let key_input = self.build_key_input();
+        let doc = common::create_doc(format!("Initialize the singleton for {}.", name));
 
-        // create ident for the inner value var name
-        let inner_ident = format_ident!("inner");
+        // singletons without a lock hold the value directly
+        let entry_stmt = self.build_entry_stmt();
+        if !self.opts.lock.has_lock() {
+            let static_expr = self.build_static();
+            return quote! {
+                #doc
+                #[automatically_derived]
+                fn #fn_ident(#key_input inner: Self::Inner) -> #anyhow::Result<()> {
+                    #entry_stmt
+                    if #static_expr.set(inner).is_err() {
+                        Err(anyhow::anyhow!(#err_msg))
+                    } else {
+                        Ok(())
+                    }
+                }
+            };
+        }
 
-        // create new lock expression
-        let new_lock_expr = self.lock.to_new_lock_expr(&inner_ident);
+        // the lock is in use only if the singleton holds a value
+        let lock_ident = format_ident!("inner_lock");
+        let get_lock = self.build_get_lock();
+        let try_lock_guard_expr = self
+            .opts
+            .lock
+            .to_try_mut_guard(&self.opts.runtime, &lock_ident);
+        let set_initialized = self.build_set_initialized(true);
+        let publish = self.build_publish(quote! { &inner });
 
         // return implementation
         quote! {
             #doc
             #[automatically_derived]
-            fn init_singleton(#inner_ident: Self::Inner) -> #anyhow::Result<()> {
-                if #static_ident.set(#new_lock_expr).is_err() {
-                    Err(anyhow::anyhow!(#err_msg))
-                } else {
-                    Ok(())
-                }
+            fn #fn_ident(#key_input inner: Self::Inner) -> #anyhow::Result<()> {
+                #entry_stmt
+                let #lock_ident = #get_lock;
+                // the lock guard borrows the entry, so it's dropped before it
+                let result = match #try_lock_guard_expr {
+                    Ok(mut inner_guard) if inner_guard.is_none() => {
+                        #publish
+                        *inner_guard = Some(inner);
+                        #set_initialized
+                        Ok(())
+                    }
+                    _ => Err(anyhow::anyhow!(#err_msg)),
+                };
+                result
             }
         }
     }
 
-    /// Implement Singleton::use_singleton.
-    fn impl_use_singleton(&self) -> TokenStream {
-        let static_ident = self.static_ident;
-        let type_name = self.type_name;
-
+    /// Implement Singleton::scope.
+    ///
+    /// The scope holds its entry, which is dropped (with the value) when the future is done or
+    /// dropped, even if it panics, unless guards still hold it.
+    fn impl_scope(&self) -> TokenStream {
         // get paths to deps
-        let blockz = paths::blockz_path();
+        let once_cell = paths::once_cell_path();
+        let std = paths::std_path();
 
-        // create doc comment
+        let scope_static_ident = &self.instance.scope_static_ident;
         let doc = common::create_doc(format!(
-            "Run an async function using an immutable {}.",
-            type_name
+            "Override the singleton for {} while running a future.",
+            self.instance.name
         ));
 
-        // create lock ident
-        let lock_ident = format_ident!("inner_lock");
-
-        // get lock guard expression
-        let lock_guard_expr = self.lock.to_guard(&lock_ident);
+        // singletons without a lock hold the value directly
+        let inner = if self.opts.lock.has_lock() {
+            self.opts
+                .lock
+                .to_new_lock_expr(&self.opts.runtime, quote! { Some(inner) })
+        } else {
+            quote! { inner }
+        };
 
-        // return implementation
         quote! {
             #doc
             #[automatically_derived]
-            async fn use_singleton<F, R>(clojure: F) -> R
+            async fn scope<F>(inner: Self::Inner, fut: F) -> F::Output
             where
-                F: for<'c> #blockz::singleton::SingletonFn<'c, #type_name, R> + Send,
-                R: Send,
+                F: #std::future::Future + Send,
+                F::Output: Send,
             {
-                let #lock_ident = #static_ident.get().unwrap();
-                let inner_guard = #lock_guard_expr;
-                let inner_deref: &#type_name = &*inner_guard;
-                clojure.call_once(inner_deref).await
+                let entry = #std::sync::Arc::new((
+                    #once_cell::sync::OnceCell::with_value(#inner),
+                    #std::sync::atomic::AtomicBool::new(true),
+                ));
+                #scope_static_ident.scope(entry, fut).await
             }
         }
     }
 
-    /// Implement Singleton::use_mut_singleton.
-    fn impl_use_mut_singleton(&self) -> TokenStream {
-        let static_ident = self.static_ident;
-        let type_name = self.type_name;
+    /// Implement Singleton::is_initialized.
+    fn impl_is_initialized(&self) -> TokenStream {
+        let initialized_static = self.build_initialized_static();
+        let name = &self.instance.name;
 
         // get paths to deps
-        let blockz = paths::blockz_path();
+        let std = paths::std_path();
 
-        // create doc comment
+        // create the function name and the doc comment
+        let fn_ident = self.build_fn_ident("is_initialized");
+        let key_input = self.build_key_input();
         let doc = common::create_doc(format!(
-            "Run an async function using a mutable {}.",
-            type_name
+            "Check whether the singleton for {} has been initialized.",
+            name
         ));
 
-        // create lock ident
+        // singletons without a lock hold the value directly
+        let entry_stmt = self.build_entry_stmt();
+        let is_initialized = if self.opts.lock.has_lock() {
+            quote! { #initialized_static.load(#std::sync::atomic::Ordering::SeqCst) }
+        } else {
+            let static_expr = self.build_static();
+            quote! { #static_expr.get().is_some() }
+        };
+
+        // return implementation
+        quote! {
+            #doc
+            #[automatically_derived]
+            fn #fn_ident(#key_input) -> bool {
+                #entry_stmt
+                #is_initialized
+            }
+        }
+    }
+
+    /// Implement MutableSingleton::replace_singleton, MutableSingleton::take_singleton and
+    /// MutableSingleton::reset_singleton (with the test-util feature).
+    ///
+    /// All of them wait for the singleton lock, so in-flight uses finish against the old value.
+    fn impl_replace_fns(&self) -> TokenStream {
+        let name = &self.instance.name;
+
+        // get paths to deps
+        let std = paths::std_path();
+
+        // create doc comments
+        let replace_doc = common::create_doc(format!(
+            "Replace the value of the singleton for {}, returning the old one.",
+            name
+        ));
+        let take_doc =
+            common::create_doc(format!("Take the value out of the singleton for {}.", name));
+        let reset_doc = common::create_doc(format!(
+            "Reset the singleton for {} to its uninitialized state.",
+            name
+        ));
+
+        // create the function names
+        let replace_ident = self.build_fn_ident("replace_singleton");
+        let take_ident = self.build_fn_ident("take_singleton");
+        let reset_ident = self.build_fn_ident("reset_singleton");
+        let key_input = self.build_key_input();
+
+        let entry_stmt = self.build_entry_stmt();
         let lock_ident = format_ident!("inner_lock");
+        let get_lock = self.build_get_lock();
+        let lock_guard_expr = self.opts.lock.to_checked_guard(
+            self.opts.lock.to_mut_guard(&self.opts.runtime, &lock_ident),
+            name,
+            quote! { panic!("{}", err) },
+        );
+        let set_initialized = self.build_set_initialized(true);
+        let set_uninitialized = self.build_set_initialized(false);
+        let publish = self.build_publish(quote! { &inner });
+        let publish_none = self.build_publish_none();
 
-        // get mut lock guard expression
-        let mut_lock_guard_expr = self.lock.to_mut_guard(&lock_ident);
+        // lazily initialized singletons also forget the last failed initialization
+        let reset_init = self.opts.init.as_ref().map(|_| {
+            let init_static_ident = &self.instance.init_static_ident;
+            self.instance.to_unscoped(quote! {
+                *#init_static_ident
+                    .lock()
+                    .unwrap_or_else(#std::sync::PoisonError::into_inner) = None;
+            })
+        });
+
+        // take the value out of the lock, or let the lock take it out (actors are stopped)
+        let take = match self.opts.lock.to_take(&lock_ident) {
+            Some(take) => quote! {{
+                #set_uninitialized
+                #publish_none
+                #take
+            }},
+            None => quote! {{
+                let mut inner_guard = #lock_guard_expr;
+                #set_uninitialized
+                #publish_none
+                inner_guard.take()
+            }},
+        };
+
+        // resetting is only meant for tests
+        let reset = cfg!(feature = "test-util").then(|| {
+            quote! {
+                #reset_doc
+                #[automatically_derived]
+                async fn #reset_ident(#key_input) {
+                    #entry_stmt
+                    let #lock_ident = #get_lock;
+                    #reset_init
+                    #take;
+                }
+            }
+        });
 
         // return implementation
+        quote! {
+            #replace_doc
+            #[automatically_derived]
+            async fn #replace_ident(#key_input inner: Self::Inner) -> Option<Self::Inner> {
+                #entry_stmt
+                let #lock_ident = #get_lock;
+                let mut inner_guard = #lock_guard_expr;
+                #set_initialized
+                #publish
+                inner_guard.replace(inner)
+            }
+
+            #take_doc
+            #[automatically_derived]
+            async fn #take_ident(#key_input) -> Option<Self::Inner> {
+                #entry_stmt
+                let #lock_ident = #get_lock;
+                #take
+            }
+
+            #reset
+        }
+    }
+
+    /// Implement MutableSingleton (or MutableKeyedSingleton) for singletons behind a lock, with
+    /// the mutable use functions, or the synchronous accessor for singletons without a lock.
+    fn impl_lock_specific(&self, mutable_use_fns: TokenStream) -> TokenStream {
+        let blockz = paths::blockz_path();
+        let type_name = &self.instance.ty;
+        if self.opts.lock.has_lock() {
+            let mutable_trait = if self.opts.keyed.is_some() {
+                format_ident!("MutableKeyedSingleton")
+            } else {
+                format_ident!("MutableSingleton")
+            };
+            let replace_fns = self.impl_replace_fns();
+            return quote! {
+                #[async_trait::async_trait]
+                #[automatically_derived]
+                impl #blockz::singleton::#mutable_trait for #type_name {
+                    #mutable_use_fns
+                    #replace_fns
+                }
+            };
+        }
+
+        let static_expr = self.build_static();
+        let fn_ident = self.build_fn_ident("get_singleton");
+        let key_input = self.build_key_input();
+        let not_initialized = self.build_not_initialized(false);
+        let doc = common::create_doc(format!("Get the singleton for {}.", self.instance.name));
+
+        // the value of a scope is dropped with the scope, so it can't be borrowed for 'static
+        let entry = self.build_entry();
+        let (entry_stmt, scope_panic_doc) = if self.opts.keyed.is_some() {
+            (quote! { let entry = #entry; }, TokenStream::new())
+        } else {
+            let panic_msg = common::create_lit_str(format!(
+                "{0}: singleton: the value of a scope can't be borrowed for 'static \
+                 (use {0}::read_guard in scopes)",
+                self.instance.name
+            ));
+            (
+                quote! {
+                    let entry = match #entry.as_static() {
+                        Some(entry) => entry,
+                        None => panic!("{}", #panic_msg),
+                    };
+                },
+                quote! {
+                    ///
+                    /// It also panics within a scope of the singleton (see `Singleton::scope`),
+                    /// whose value is dropped with the scope: use `read_guard` there instead.
+                },
+            )
+        };
+        quote! {
+            #[automatically_derived]
+            impl #type_name {
+                #doc
+                ///
+                /// # Panics
+                ///
+                /// This panics if the singleton has not been initialized.
+                #scope_panic_doc
+                #[allow(dead_code)]
+                pub fn #fn_ident(#key_input) -> &'static Self {
+                    #entry_stmt
+                    match #static_expr.get() {
+                        Some(value) => value,
+                        None => #not_initialized,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Implement GuardSingleton and MutableGuardSingleton (or their keyed versions) for singletons
+    /// with an async lock (or without a lock, which only implement GuardSingleton).
+    ///
+    /// Singletons with a sync lock can't hold their lock across await points, so they don't
+    /// implement them.
+    fn impl_guard_traits(&self) -> TokenStream {
+        if !self.opts.lock.is_async() {
+            return TokenStream::new();
+        }
+        let blockz = paths::blockz_path();
+        let type_name = &self.instance.ty;
+        let (guard_trait, mutable_guard_trait) = if self.opts.keyed.is_some() {
+            (
+                format_ident!("GuardKeyedSingleton"),
+                format_ident!("MutableGuardKeyedSingleton"),
+            )
+        } else {
+            (
+                format_ident!("GuardSingleton"),
+                format_ident!("MutableGuardSingleton"),
+            )
+        };
+        let read_guard_fns = self.impl_guard_fns(false);
+        let write_guard_impl = self.opts.lock.has_lock().then(|| {
+            let write_guard_fns = self.impl_guard_fns(true);
+            quote! {
+                #[async_trait::async_trait]
+                #[automatically_derived]
+                impl #blockz::singleton::#mutable_guard_trait for #type_name {
+                    #write_guard_fns
+                }
+            }
+        });
+        quote! {
+            #[async_trait::async_trait]
+            #[automatically_derived]
+            impl #blockz::singleton::#guard_trait for #type_name {
+                #read_guard_fns
+            }
+
+            #write_guard_impl
+        }
+    }
+
+    /// Implement AsyncSingleton for singletons that can be used by async functions.
+    fn impl_async_singleton(&self) -> TokenStream {
+        if !self.opts.lock.is_async() {
+            return TokenStream::new();
+        }
+        let blockz = paths::blockz_path();
+        let type_name = &self.instance.ty;
+        quote! {
+            #[automatically_derived]
+            impl #blockz::singleton::AsyncSingleton for #type_name {}
+        }
+    }
+
+    /// Implement KeyedSingleton::singleton_keys.
+    fn impl_singleton_keys(&self) -> TokenStream {
+        let static_ident = &self.instance.static_ident;
+
+        // get paths to deps
+        let std = paths::std_path();
+
+        // singletons without a lock hold the value directly
+        let is_initialized = if self.opts.lock.has_lock() {
+            quote! { entry.1.load(#std::sync::atomic::Ordering::SeqCst) }
+        } else {
+            quote! { entry.0.get().is_some() }
+        };
+        let doc = common::create_doc(format!(
+            "Get the keys of the singleton for {} that have been initialized.",
+            self.instance.name
+        ));
+        // sharded singletons have a table of shards instead of a registry
+        if let Some(SingletonKey::Shards(_)) = &self.opts.keyed {
+            return quote! {
+                #doc
+                #[automatically_derived]
+                fn singleton_keys() -> Vec<usize> {
+                    #static_ident
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, entry)| #is_initialized)
+                        .map(|(key, _)| key)
+                        .collect()
+                }
+            };
+        }
         quote! {
             #doc
             #[automatically_derived]
-            async fn use_mut_singleton<F, R>(clojure: F) -> R
-            where
-                F: for<'c> #blockz::singleton::SingletonFnMut<'c, Self::Inner, R> + Send,
-                R: Send,
-            {
-                let #lock_ident = #static_ident.get().unwrap();
-                let mut inner_guard = #mut_lock_guard_expr;
-                let inner_deref: &mut #type_name = &mut *inner_guard;
-                clojure.call_once(inner_deref).await
+            fn singleton_keys() -> Vec<<Self::Key as #std::borrow::ToOwned>::Owned> {
+                #static_ident
+                    .read()
+                    .unwrap_or_else(#std::sync::PoisonError::into_inner)
+                    .iter()
+                    .filter(|(_, entry)| #is_initialized)
+                    .map(|(key, _)| #std::clone::Clone::clone(key))
+                    .collect()
+            }
+        }
+    }
+
+    /// Implement the routing of the singleton fns of singletons that are not keyed: UnshardedRoute,
+    /// or ActorRoute for actor singletons, which sends the calls to the actor.
+    fn impl_route(&self) -> TokenStream {
+        let blockz = paths::blockz_path();
+        let type_name = &self.instance.ty;
+        if self.opts.keyed.is_some() {
+            return TokenStream::new();
+        }
+        if !matches!(self.opts.lock, SingletonLock::Actor(_)) {
+            return quote! {
+                #[automatically_derived]
+                impl #blockz::singleton::route::UnshardedRoute for #type_name {}
+            };
+        }
+
+        let name = &self.instance.name;
+        let inner_type = &self.instance.inner;
+        let std = paths::std_path();
+        let tokio = paths::tokio_path();
+        let entry_stmt = self.build_entry_stmt();
+        let get_lock = self.build_get_lock();
+
+        // the calls run in the task of the actor, so the scope is checked by the caller
+        let after = match &self.opts.watch {
+            Some(watch) => {
+                let scope_static_ident = &self.instance.scope_static_ident;
+                let publish = watch.to_publish(
+                    &self.instance.watch_static_ident,
+                    &self.instance.inner,
+                    quote! { inner },
+                );
+                quote! {
+                    if #scope_static_ident.try_with(|_| ()).is_err() {
+                        Some(|inner: &#inner_type| { #publish })
+                    } else {
+                        None
+                    }
+                }
+            }
+            None => quote! { None },
+        };
+
+        // the lock timeout bounds the whole call, since the call runs in the actor
+        let timeout = match self.opts.lock_timeout {
+            Some(lock_timeout) => {
+                let lock_timeout = lock_timeout.to_expr();
+                quote! { timeout.or(Some(#lock_timeout)) }
+            }
+            None => quote! { timeout },
+        };
+
+        quote! {
+            #[automatically_derived]
+            impl #blockz::singleton::route::ActorRoute for #type_name {
+                fn call_actor<C>(
+                    call: C,
+                    timeout: Option<#std::time::Duration>,
+                ) -> impl #std::future::Future<
+                    Output = Result<C::Output, #blockz::singleton::SingletonError>,
+                > + Send + 'static
+                where
+                    C: #blockz::singleton::actor::ActorCall<Self::Inner>,
+                {
+                    let after: Option<fn(&#inner_type)> = #after;
+                    #entry_stmt
+                    let call = #get_lock.call(call, after);
+                    let timeout = #timeout;
+                    async move {
+                        let result = match timeout {
+                            Some(timeout) => match #tokio::time::timeout(timeout, call).await {
+                                Ok(result) => result,
+                                Err(_) => {
+                                    return Err(#blockz::singleton::SingletonError::LockTimeout(#name))
+                                }
+                            },
+                            None => call.await,
+                        };
+                        result.map_err(|err| err.into_singleton_error(#name))
+                    }
+                }
             }
         }
     }
 
-    /// Implement Singleton::use_singleton_with_arg.
-    fn impl_use_singleton_with_arg(&self) -> TokenStream {
-        let static_ident = self.static_ident;
-        let type_name = self.type_name;
+    /// Implement WatchSingleton for watched singletons.
+    fn impl_watch(&self) -> TokenStream {
+        let watch = if let Some(value) = &self.opts.watch {
+            value
+        } else {
+            return TokenStream::new();
+        };
 
         // get paths to deps
         let blockz = paths::blockz_path();
 
-        // create doc comment
+        let type_name = &self.instance.ty;
+        let watch_static_ident = &self.instance.watch_static_ident;
+        let watched_type = watch.to_watched_type(&self.instance.inner);
         let doc = common::create_doc(format!(
-            "Run an async function using an immutable {} and an argument.",
-            type_name
+            "Subscribe to the changes of the singleton for {}.",
+            self.instance.name
         ));
+        quote! {
+            #[automatically_derived]
+            impl #blockz::singleton::WatchSingleton for #type_name {
+                type Watched = #watched_type;
+
+                #doc
+                fn subscribe() -> #blockz::singleton::watch::Receiver<Option<Self::Watched>> {
+                    #watch_static_ident.subscribe()
+                }
+            }
+        }
+    }
+
+    /// Implement ShardedSingleton for sharded singletons.
+    fn impl_sharded(&self) -> TokenStream {
+        let shards = if let Some(SingletonKey::Shards(value)) = &self.opts.keyed {
+            value
+        } else {
+            return TokenStream::new();
+        };
+
+        // get paths to deps
+        let blockz = paths::blockz_path();
+
+        // singleton fns are routed by the argument with this name
+        let shard_by = match &self.opts.shard_by {
+            Some(name) => keyed::arg_name_type(name),
+            None => quote! { #blockz::singleton::route::NoShardBy },
+        };
+
+        let type_name = &self.instance.ty;
+        quote! {
+            #[automatically_derived]
+            impl #blockz::singleton::ShardedSingleton for #type_name {
+                const SHARDS: usize = #shards;
+
+                type ShardBy = #shard_by;
+            }
+        }
+    }
+
+    /// Create the expression that either returns an error or panics if the singleton holds no
+    /// value.
+    fn build_not_initialized(&self, fallible: bool) -> TokenStream {
+        let blockz = paths::blockz_path();
+        let name = &self.instance.name;
+        if fallible {
+            quote! { return Err(#blockz::singleton::SingletonError::NotInitialized(#name)) }
+        } else {
+            let panic_msg = common::create_lit_str(format!(
+                "{0}: singleton: not initialized (call {0}::{1} first)",
+                name,
+                self.build_fn_ident("init_singleton"),
+            ));
+            quote! { panic!("{}", #panic_msg) }
+        }
+    }
+
+    /// Create the statement that panics because a singleton with a sync lock can't be used by
+    /// async functions.
+    fn build_sync_lock_panic(&self) -> TokenStream {
+        let panic_msg = common::create_lit_str(format!(
+            "{}: singleton: singletons with a sync lock can only be used by blocking functions",
+            self.instance.name
+        ));
+        quote! { panic!("{}", #panic_msg) }
+    }
+
+    /// Create the statements that get a reference to the inner value of a singleton without a
+    /// lock (as inner_deref).
+    fn build_acquire_unlocked(&self, use_fn: UseFn) -> TokenStream {
+        let static_expr = self.build_static();
+        let get_fn_ident = &self.instance.get_fn_ident;
+        let inner_type = &self.instance.inner;
+        if self.opts.init.is_some() {
+            let on_err = if use_fn.fallible {
+                quote! { return Err(err) }
+            } else {
+                quote! { panic!("{}", err) }
+            };
+            quote! {
+                let inner_deref: &#inner_type = match #get_fn_ident(&*entry).await {
+                    Ok(value) => value,
+                    Err(err) => #on_err,
+                };
+            }
+        } else {
+            let not_initialized = self.build_not_initialized(use_fn.fallible);
+            quote! {
+                let inner_deref: &#inner_type = match #static_expr.get() {
+                    Some(value) => value,
+                    None => #not_initialized,
+                };
+            }
+        }
+    }
+
+    /// Create the statements that prepare the singleton lock (as inner_lock) and the expression
+    /// that locks it, running the lazy initialization and bounding the lock acquisition by the
+    /// lock timeout.
+    fn build_lock_guard(&self, use_fn: UseFn) -> (TokenStream, TokenStream) {
+        let name = &self.instance.name;
+
+        // get paths to deps
+        let blockz = paths::blockz_path();
 
         // create lock ident
         let lock_ident = format_ident!("inner_lock");
+        let get_lock = self.build_get_lock();
+
+        // lazily initialized singletons are initialized first
+        let get_fn_ident = &self.instance.get_fn_ident;
+        let initialized_static = self.build_initialized_static();
+        let std = paths::std_path();
+        let lazy_init = if self.opts.init.is_some() {
+            let on_err = if use_fn.fallible {
+                quote! { return Err(err); }
+            } else {
+                quote! { panic!("{}", err); }
+            };
+            quote! {
+                if !#initialized_static.load(#std::sync::atomic::Ordering::SeqCst) {
+                    if let Err(err) = #get_fn_ident().await {
+                        #on_err
+                    }
+                }
+            }
+        } else {
+            TokenStream::new()
+        };
 
         // get lock guard expression
-        let lock_guard_expr = self.lock.to_guard(&lock_ident);
+        let lock_guard_expr = if use_fn.mutable {
+            self.opts.lock.to_mut_guard(&self.opts.runtime, &lock_ident)
+        } else {
+            self.opts.lock.to_guard(&self.opts.runtime, &lock_ident)
+        };
+
+        // either return an error or panic if the lock is not acquired in time
+        let lock_guard_expr = match self.opts.lock_timeout {
+            Some(lock_timeout) => {
+                let lock_timeout = lock_timeout.to_expr();
+                let timed_out = quote! { #blockz::singleton::SingletonError::LockTimeout(#name) };
+                let on_timeout = if use_fn.fallible {
+                    quote! { return Err(#timed_out) }
+                } else {
+                    quote! { panic!("{}", #timed_out) }
+                };
+                let lock_guard_expr = self
+                    .opts
+                    .runtime
+                    .to_timeout(lock_timeout, quote! { async { #lock_guard_expr } });
+                quote! {
+                    match #lock_guard_expr {
+                        Ok(guard) => guard,
+                        Err(_) => #on_timeout,
+                    }
+                }
+            }
+            None => lock_guard_expr,
+        };
+
+        // either return an error or panic if the lock can't be acquired
+        let on_err = if use_fn.fallible {
+            quote! { return Err(err) }
+        } else {
+            quote! { panic!("{}", err) }
+        };
+        let lock_guard_expr = self
+            .opts
+            .lock
+            .to_checked_guard(lock_guard_expr, name, on_err);
+
+        (
+            quote! {
+                #lazy_init
+                let #lock_ident = #get_lock;
+            },
+            lock_guard_expr,
+        )
+    }
+
+    /// Create the statements that lock the singleton and get a reference to its inner value (as
+    /// inner_deref).
+    fn build_acquire_locked(&self, use_fn: UseFn) -> TokenStream {
+        let inner_type = &self.instance.inner;
+        let (prepare_lock, lock_guard_expr) = self.build_lock_guard(use_fn);
+
+        // either return an error or panic if the singleton holds no value
+        let not_initialized = self.build_not_initialized(use_fn.fallible);
+
+        if use_fn.mutable {
+            quote! {
+                #prepare_lock
+                let mut inner_guard = #lock_guard_expr;
+                let inner_deref: &mut #inner_type = match &mut *inner_guard {
+                    Some(value) => value,
+                    None => #not_initialized,
+                };
+            }
+        } else {
+            quote! {
+                #prepare_lock
+                let inner_guard = #lock_guard_expr;
+                let inner_deref: &#inner_type = match &*inner_guard {
+                    Some(value) => value,
+                    None => #not_initialized,
+                };
+            }
+        }
+    }
+
+    /// Create the type of the guards returned by Singleton::read_guard (or
+    /// MutableSingleton::write_guard).
+    fn build_guard_type(&self, mutable: bool) -> TokenStream {
+        let inner_type = &self.instance.inner;
+
+        // get paths to deps
+        let blockz = paths::blockz_path();
+
+        // the lock holds an optional value, singletons without a lock hold the value directly
+        let inner = if self.opts.lock.has_lock() {
+            quote! { Option<#inner_type> }
+        } else {
+            quote! { #inner_type }
+        };
+        if mutable {
+            let lock_guard_type = self.opts.lock.to_mut_guard_type(&self.opts.runtime, inner);
+            quote! { #blockz::singleton::SingletonWriteGuard<#lock_guard_type, #inner_type> }
+        } else {
+            let lock_guard_type = self.opts.lock.to_guard_type(&self.opts.runtime, inner);
+            quote! { #blockz::singleton::SingletonReadGuard<#lock_guard_type, #inner_type> }
+        }
+    }
+
+    /// Implement GuardSingleton::read_guard and GuardSingleton::try_read_guard (or
+    /// MutableGuardSingleton::write_guard and MutableGuardSingleton::try_write_guard).
+    fn impl_guard_fns(&self, mutable: bool) -> TokenStream {
+        let name = &self.instance.name;
+
+        // get paths to deps
+        let blockz = paths::blockz_path();
+
+        let (guard_type_ident, guard_ident, as_inner) = if mutable {
+            (
+                format_ident!("WriteGuard"),
+                format_ident!("SingletonWriteGuard"),
+                quote! { Option::as_mut },
+            )
+        } else {
+            (
+                format_ident!("ReadGuard"),
+                format_ident!("SingletonReadGuard"),
+                quote! { Option::as_ref },
+            )
+        };
+
+        let guard_fns = [false, true].iter().map(|fallible| {
+            let fallible = *fallible;
+            let use_fn = UseFn {
+                mutable,
+                with_arg: false,
+                fallible,
+                timeout: false,
+            };
+
+            // create the function name and the doc comment
+            let fn_ident = self.build_fn_ident(&format!(
+                "{}{}_guard",
+                if fallible { "try_" } else { "" },
+                if mutable { "write" } else { "read" },
+            ));
+            let key_input = self.build_key_input();
+            let doc = common::create_doc(format!(
+                "Lock the singleton for {} and get a guard that gives {} access to it.",
+                name,
+                if mutable { "exclusive" } else { "shared" },
+            ));
+
+            // get the guard, which keeps the entry of a scope alive
+            let not_initialized = self.build_not_initialized(fallible);
+            let static_entry_stmt = self.build_static_entry_stmt();
+            let acquire = if self.opts.lock.has_lock() {
+                let (prepare_lock, lock_guard_expr) = self.build_lock_guard(use_fn);
+                // watched singletons publish the changes made through the guard
+                let new_guard = if mutable && self.opts.watch.is_some() {
+                    let publish = self.build_publish(quote! { inner });
+                    quote! {
+                        #blockz::singleton::#guard_ident::with_release(inner_guard, |inner_guard| {
+                            if let Some(inner) = &**inner_guard {
+                                #publish
+                            }
+                        })
+                    }
+                } else {
+                    quote! { #blockz::singleton::#guard_ident::new(inner_guard) }
+                };
+                let new_guard = self.build_keep_owner(&guard_ident, new_guard);
+                quote! {
+                    #static_entry_stmt
+                    #prepare_lock
+                    let inner_guard = #lock_guard_expr;
+                    let guard = match #blockz::singleton::#guard_ident::try_map(
+                        #new_guard,
+                        #as_inner,
+                    ) {
+                        Ok(guard) => guard,
+                        Err(_) => #not_initialized,
+                    };
+                }
+            } else {
+                let acquire = self.build_acquire_unlocked(use_fn);
+                let new_guard = self.build_keep_owner(
+                    &guard_ident,
+                    quote! { #blockz::singleton::#guard_ident::new(inner_deref) },
+                );
+                quote! {
+                    #static_entry_stmt
+                    #acquire
+                    let guard = #new_guard;
+                }
+            };
+
+            let (result, ret) = if fallible {
+                (
+                    quote! { Result<Self::#guard_type_ident, #blockz::singleton::SingletonError> },
+                    quote! { Ok(guard) },
+                )
+            } else {
+                (quote! { Self::#guard_type_ident }, quote! { guard })
+            };
+            quote! {
+                #doc
+                #[automatically_derived]
+                async fn #fn_ident(#key_input) -> #result {
+                    #acquire
+                    #ret
+                }
+            }
+        });
+
+        let guard_type = self.build_guard_type(mutable);
+        quote! {
+            type #guard_type_ident = #guard_type;
+            #(#guard_fns)*
+        }
+    }
+
+    /// Implement one of the Singleton::*use_*singleton_blocking functions.
+    ///
+    /// These don't run the lazy initialization and they don't time out.
+    fn impl_blocking_use_fn(&self, mutable: bool, fallible: bool) -> TokenStream {
+        let static_expr = self.build_static();
+        let inner_type = &self.instance.inner;
+        let name = &self.instance.name;
+
+        // get paths to deps
+        let blockz = paths::blockz_path();
+
+        // create the function name and the doc comment
+        let fn_ident = self.build_fn_ident(&format!(
+            "{}use_{}singleton_blocking",
+            if fallible { "try_" } else { "" },
+            if mutable { "mut_" } else { "" },
+        ));
+        let key_input = self.build_key_input();
+        let doc = common::create_doc(format!(
+            "Run a function using {} {}, blocking the current thread while waiting for the lock.",
+            if mutable { "a mutable" } else { "an immutable" },
+            name,
+        ));
+
+        // get the inner value (singletons without a lock are never used mutably, since they
+        // don't implement MutableSingleton)
+        let not_initialized = self.build_not_initialized(fallible);
+        let on_err = if fallible {
+            quote! { return Err(err) }
+        } else {
+            quote! { panic!("{}", err) }
+        };
+        let lock_ident = format_ident!("inner_lock");
+        let acquire = if !self.opts.lock.has_lock() {
+            quote! {
+                let inner_deref: &#inner_type = match #static_expr.get() {
+                    Some(value) => value,
+                    None => #not_initialized,
+                };
+            }
+        } else if mutable {
+            let get_lock = self.build_get_lock();
+            let lock_guard_expr = self.opts.lock.to_checked_guard(
+                self.opts
+                    .lock
+                    .to_blocking_mut_guard(&self.opts.runtime, &lock_ident),
+                name,
+                on_err.clone(),
+            );
+            quote! {
+                let #lock_ident = #get_lock;
+                let mut inner_guard = #lock_guard_expr;
+                let inner_deref: &mut #inner_type = match &mut *inner_guard {
+                    Some(value) => value,
+                    None => #not_initialized,
+                };
+            }
+        } else {
+            let get_lock = self.build_get_lock();
+            let lock_guard_expr = self.opts.lock.to_checked_guard(
+                self.opts
+                    .lock
+                    .to_blocking_guard(&self.opts.runtime, &lock_ident),
+                name,
+                on_err,
+            );
+            quote! {
+                let #lock_ident = #get_lock;
+                let inner_guard = #lock_guard_expr;
+                let inner_deref: &#inner_type = match &*inner_guard {
+                    Some(value) => value,
+                    None => #not_initialized,
+                };
+            }
+        };
+
+        // the signature of the function
+        let inner_ref = if mutable {
+            quote! { &mut Self::Inner }
+        } else {
+            quote! { &Self::Inner }
+        };
+        let (result, call) = if fallible {
+            (
+                quote! { Result<R, #blockz::singleton::SingletonError> },
+                quote! { Ok(clojure(inner_deref)) },
+            )
+        } else {
+            (quote! { R }, quote! { clojure(inner_deref) })
+        };
+
+        // watched singletons publish the changes made by mutable uses
+        let call = if mutable && self.opts.watch.is_some() {
+            let publish = self.build_publish(quote! { &*inner_deref });
+            quote! {
+                let result = #call;
+                #publish
+                result
+            }
+        } else {
+            call
+        };
+        let entry_stmt = self.build_entry_stmt();
+        let body = quote! {
+            #entry_stmt
+            #acquire
+            #call
+        };
 
         // return implementation
         quote! {
             #doc
             #[automatically_derived]
-            async fn use_singleton_with_arg<F, A, R>(clojure: F, arg: A) -> R
+            fn #fn_ident<F, R>(#key_input clojure: F) -> #result
             where
-                F: for<'c> #blockz::singleton::SingletonFnWithArg<'c, Self::Inner, A, R> + Send,
-                A: Send,
-                R: Send
+                F: FnOnce(#inner_ref) -> R,
             {
-                let #lock_ident = #static_ident.get().unwrap();
-                let inner_guard = #lock_guard_expr;
-                let inner_deref: &#type_name = &*inner_guard;
-                clojure.call_once(inner_deref, arg).await
+                #body
             }
         }
     }
 
-    /// Implement Singleton::use_singleton_mut_with_arg.
-    fn impl_use_mut_singleton_with_arg(&self) -> TokenStream {
-        let static_ident = self.static_ident;
-        let type_name = self.type_name;
+    /// Implement one of the Singleton::use_*singleton* functions.
+    fn impl_use_fn(&self, use_fn: UseFn) -> TokenStream {
+        let name = &self.instance.name;
 
         // get paths to deps
         let blockz = paths::blockz_path();
 
-        // create doc comment
+        // create the function name, the clojure trait and the doc comment
+        let fn_ident = self.build_fn_ident(&format!(
+            "{}use_{}singleton{}{}",
+            if use_fn.fallible && !use_fn.timeout {
+                "try_"
+            } else {
+                ""
+            },
+            if use_fn.mutable { "mut_" } else { "" },
+            if use_fn.with_arg { "_with_arg" } else { "" },
+            if use_fn.timeout { "_timeout" } else { "" },
+        ));
+        let key_input = self.build_key_input();
+        let fn_trait = format_ident!(
+            "SingletonFn{}{}",
+            if use_fn.mutable { "Mut" } else { "" },
+            if use_fn.with_arg { "WithArg" } else { "" },
+        );
         let doc = common::create_doc(format!(
-            "Run an async function using a mutable {} and an argument.",
-            type_name
+            "Run an async function using {} {}{}{}.",
+            if use_fn.mutable {
+                "a mutable"
+            } else {
+                "an immutable"
+            },
+            name,
+            if use_fn.with_arg {
+                " and an argument"
+            } else {
+                ""
+            },
+            if use_fn.timeout {
+                ", failing if it does not finish in time"
+            } else {
+                ""
+            },
         ));
 
-        // create lock ident
-        let lock_ident = format_ident!("inner_lock");
+        // get the inner value, or panic if the singleton can't be used asynchronously (singletons
+        // without a lock are never used mutably, since they don't implement MutableSingleton)
+        let std = paths::std_path();
+        let acquire = if !self.opts.lock.is_async() {
+            Err(self.build_sync_lock_panic())
+        } else if self.opts.lock.has_lock() {
+            Ok(self.build_acquire_locked(use_fn))
+        } else {
+            Ok(self.build_acquire_unlocked(use_fn))
+        };
+        let entry_stmt = self.build_entry_stmt();
+        let acquire = acquire.map(|acquire| {
+            quote! {
+                #entry_stmt
+                #acquire
+            }
+        });
+
+        // the argument of the function
+        let (arg_generic, arg_input, arg_bound, arg_call) = if use_fn.with_arg {
+            (
+                quote! { A, },
+                quote! { , arg: A },
+                quote! { A: Send, },
+                quote! { , arg },
+            )
+        } else {
+            (
+                TokenStream::new(),
+                TokenStream::new(),
+                TokenStream::new(),
+                TokenStream::new(),
+            )
+        };
 
-        // get mut lock guard expression
-        let mut_lock_guard_expr = self.lock.to_mut_guard(&lock_ident);
+        // the result of the function
+        let (result, call) = if use_fn.fallible {
+            (
+                quote! { Result<R, #blockz::singleton::SingletonError> },
+                quote! { Ok(clojure.call_once(inner_deref #arg_call).await) },
+            )
+        } else {
+            (
+                quote! { R },
+                quote! { clojure.call_once(inner_deref #arg_call).await },
+            )
+        };
+
+        // watched singletons publish the changes made by mutable uses
+        let call = if use_fn.mutable && self.opts.watch.is_some() {
+            let publish = self.build_publish(quote! { &*inner_deref });
+            quote! {
+                let result = #call;
+                #publish
+                result
+            }
+        } else {
+            call
+        };
+
+        // the body of the function, bounded by the timeout if there is one
+        let timeout_input = if use_fn.timeout {
+            quote! { , timeout: #std::time::Duration }
+        } else {
+            TokenStream::new()
+        };
+        let body = match acquire {
+            Ok(acquire) if use_fn.timeout => {
+                let timeout_expr = self
+                    .opts
+                    .runtime
+                    .to_timeout(quote! { timeout }, quote! { async move { #acquire #call } });
+                quote! {
+                    match #timeout_expr {
+                        Ok(result) => result,
+                        Err(_) => Err(#blockz::singleton::SingletonError::LockTimeout(#name)),
+                    }
+                }
+            }
+            Ok(acquire) => quote! {
+                #acquire
+                #call
+            },
+            Err(panic) => {
+                let timeout_call = if use_fn.timeout {
+                    quote! { , timeout }
+                } else {
+                    TokenStream::new()
+                };
+                let discard_key = self.build_discard_key();
+                quote! {
+                    #discard_key
+                    let _ = (clojure #arg_call #timeout_call);
+                    #panic
+                }
+            }
+        };
 
         // return implementation
         quote! {
             #doc
             #[automatically_derived]
-            async fn use_mut_singleton_with_arg<F, A, R>(clojure: F, arg: A) -> R
+            async fn #fn_ident<F, #arg_generic R>(
+                #key_input
+                clojure: F #arg_input #timeout_input
+            ) -> #result
             where
-                F: for<'c> #blockz::singleton::SingletonFnMutWithArg<'c, Self::Inner, A, R> + Send,
-                A: Send,
-                R: Send
+                F: for<'c> #blockz::singleton::#fn_trait<'c, Self::Inner, #arg_generic R> + Send,
+                #arg_bound
+                R: Send,
             {
-                let #lock_ident = #static_ident.get().unwrap();
-                let mut inner_guard = #mut_lock_guard_expr;
-                let inner_deref: &mut #type_name = &mut *inner_guard;
-                clojure.call_once(inner_deref, arg).await
+                #body
             }
         }
     }
@@ -221,28 +1343,71 @@ impl<'f> Factory for SingletonTraitFactory<'f> {
 
     /// Build the trait implementation.
     fn build(self) -> Self::Product {
+        let inner_type = &self.instance.inner;
         // get paths to deps
         let blockz = paths::blockz_path();
 
         // create implementations
         let init_singleton = self.impl_init_singleton();
-        let use_singleton = self.impl_use_singleton();
-        let use_singleton_mut = self.impl_use_mut_singleton();
-        let use_singleton_with_arg = self.impl_use_singleton_with_arg();
-        let use_singleton_mut_with_arg = self.impl_use_mut_singleton_with_arg();
+        let is_initialized = self.impl_is_initialized();
+        // the mutable use functions belong to MutableSingleton (or MutableKeyedSingleton)
+        let (mutable_use_fns, use_fns): (Vec<_>, Vec<_>) =
+            UseFn::all().partition(|use_fn| use_fn.mutable);
+        let use_fns = use_fns.into_iter().map(|use_fn| self.impl_use_fn(use_fn));
+        let blocking_use_fns = [false, true]
+            .iter()
+            .map(|fallible| self.impl_blocking_use_fn(false, *fallible));
+        let mutable_use_fns = mutable_use_fns
+            .into_iter()
+            .map(|use_fn| self.impl_use_fn(use_fn));
+        let mutable_blocking_use_fns = [false, true]
+            .iter()
+            .map(|fallible| self.impl_blocking_use_fn(true, *fallible));
+        let async_singleton = self.impl_async_singleton();
+        let guard_traits = self.impl_guard_traits();
+        let lock_specific = self.impl_lock_specific(quote! {
+            #(#mutable_use_fns)*
+            #(#mutable_blocking_use_fns)*
+        });
+        let watch = self.impl_watch();
+        let sharded = self.impl_sharded();
+        let route = self.impl_route();
+
+        // keyed singletons implement KeyedSingleton instead of Singleton
+        let (singleton_trait, keyed_items) = match &self.opts.keyed {
+            Some(keyed) => {
+                let key_type = keyed.to_key_type();
+                let singleton_keys = self.impl_singleton_keys();
+                (
+                    format_ident!("KeyedSingleton"),
+                    quote! {
+                        type Key = #key_type;
+                        #singleton_keys
+                    },
+                )
+            }
+            None => (format_ident!("Singleton"), self.impl_scope()),
+        };
 
         // return implementation
-        let type_name = self.type_name;
+        let type_name = &self.instance.ty;
         quote! {
             #[async_trait::async_trait]
-            impl #blockz::singleton::Singleton for #type_name {
-                type Inner = #type_name;
+            impl #blockz::singleton::#singleton_trait for #type_name {
+                type Inner = #inner_type;
+                #keyed_items
                 #init_singleton
-                #use_singleton
-                #use_singleton_mut
-                #use_singleton_with_arg
-                #use_singleton_mut_with_arg
+                #is_initialized
+                #(#use_fns)*
+                #(#blocking_use_fns)*
             }
+
+            #lock_specific
+            #async_singleton
+            #guard_traits
+            #watch
+            #sharded
+            #route
         }
     }
 }
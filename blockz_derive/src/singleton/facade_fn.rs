@@ -3,35 +3,52 @@
 use crate::factory::Factory;
 use crate::paths;
 
-use super::singleton_fns::SingletonFnArgs;
+use super::keyed;
+use super::singleton_fns;
 use super::singleton_fns::SingletonFnType;
+use super::SingletonFnOpts;
 
 use proc_macro2::Ident;
+use proc_macro2::Span;
 use proc_macro2::TokenStream;
 
+use quote::format_ident;
 use quote::quote;
+use quote::quote_spanned;
 
+use syn::ext::IdentExt;
 use syn::parse::Parser;
+use syn::spanned::Spanned;
 use syn::Attribute;
 use syn::Block;
 use syn::Error;
 use syn::FnArg;
 use syn::ItemFn;
+use syn::Pat;
 use syn::Result;
+use syn::ReturnType;
+use syn::Type;
 
 /// Factory that builds the implementation of singleton fns.
 pub(super) struct FacadeFnFactory<'f> {
     base: &'f ItemFn,
     fn_type: &'f SingletonFnType<'f>,
+    opts: &'f SingletonFnOpts,
     impl_fn: &'f ItemFn,
 }
 
 impl<'f> FacadeFnFactory<'f> {
     /// Create a new FacadeFnFactory.
-    pub fn new(base: &'f ItemFn, fn_type: &'f SingletonFnType, impl_fn: &'f ItemFn) -> Self {
+    pub fn new(
+        base: &'f ItemFn,
+        fn_type: &'f SingletonFnType,
+        opts: &'f SingletonFnOpts,
+        impl_fn: &'f ItemFn,
+    ) -> Self {
         Self {
             base,
             fn_type,
+            opts,
             impl_fn,
         }
     }
@@ -74,12 +91,31 @@ impl<'f> FacadeFnFactory<'f> {
             .sig
             .inputs
             .iter()
-            .cloned()
             .filter(|arg| !matches!(arg, FnArg::Receiver(_)))
+            .cloned()
             .collect();
         Ok(())
     }
 
+    /// Bind the arguments of the target function to the identifiers used by the singleton call.
+    ///
+    /// Arguments that are patterns (such as `(a, b): (u32, u32)`) are restored by the impl fn.
+    fn bind_fn_args(&self, target: &mut ItemFn) {
+        let arg_idents = match self.fn_type {
+            SingletonFnType::NonMutWithArg(arg) | SingletonFnType::MutWithArg(arg) => {
+                arg.facade_fn_arg_idents()
+            }
+            SingletonFnType::NonMut | SingletonFnType::Mut => return,
+        };
+        let args = target.sig.inputs.iter_mut().filter_map(|arg| match arg {
+            FnArg::Typed(arg) => Some(arg),
+            FnArg::Receiver(_) => None,
+        });
+        for (arg, ident) in args.zip(arg_idents) {
+            *arg.pat = syn::parse_quote! { #ident };
+        }
+    }
+
     /// Replace the block of a function.
     fn replace_fn_block(target: &mut ItemFn, block: TokenStream) -> Result<()> {
         let block: Block = match syn::parse2(quote! {
@@ -102,7 +138,7 @@ impl<'f> FacadeFnFactory<'f> {
                 ));
             }
         };
-        target.block = Box::new(block);
+        *target.block = block;
         Ok(())
     }
 
@@ -124,37 +160,398 @@ impl<'f> FacadeFnFactory<'f> {
         Ok(())
     }
 
-    /// Builds a Singleton::use_singleton.
-    fn build_use_singleton_stmt(fn_ident: &Ident) -> TokenStream {
-        quote! {
-            Self::use_singleton(Self::#fn_ident).await
+    /// Builds a call to the Singleton::use_*singleton* function that matches the fn type.
+    ///
+    /// Fallible singleton fns use the Singleton::try_use_*singleton* functions and singleton fns
+    /// with a timeout use the Singleton::use_*singleton*_timeout functions.
+    fn build_use_singleton_stmt(&self, fn_ident: &Ident) -> TokenStream {
+        if self.opts.guard {
+            return self.build_guard_stmt(fn_ident);
+        }
+        if self.base.sig.asyncness.is_none() {
+            return self.build_use_singleton_blocking_stmt(fn_ident);
+        }
+        let (prefix, suffix, timeout) = match self.opts.timeout {
+            Some(timeout) => {
+                let timeout = timeout.to_expr();
+                ("", "_timeout", quote! { , #timeout })
+            }
+            None if self.opts.fallible => ("try_", "", TokenStream::new()),
+            None => ("", "", TokenStream::new()),
+        };
+        let key = self.build_key_arg();
+        match self.fn_type {
+            SingletonFnType::NonMut => {
+                let use_fn = self.build_use_fn_ident(format!("{}use_singleton{}", prefix, suffix));
+                quote! { Self::#use_fn(#key Self::#fn_ident #timeout).await }
+            }
+            SingletonFnType::NonMutWithArg(arg) => {
+                let use_fn =
+                    self.build_use_fn_ident(format!("{}use_singleton_with_arg{}", prefix, suffix));
+                let arg = arg.build_impl_fn_call_arg();
+                quote! { Self::#use_fn(#key Self::#fn_ident, #arg #timeout).await }
+            }
+            SingletonFnType::Mut => {
+                let use_fn =
+                    self.build_use_fn_ident(format!("{}use_mut_singleton{}", prefix, suffix));
+                quote! { Self::#use_fn(#key Self::#fn_ident #timeout).await }
+            }
+            SingletonFnType::MutWithArg(arg) => {
+                let use_fn = self
+                    .build_use_fn_ident(format!("{}use_mut_singleton_with_arg{}", prefix, suffix));
+                let arg = arg.build_impl_fn_call_arg();
+                quote! { Self::#use_fn(#key Self::#fn_ident, #arg #timeout).await }
+            }
+        }
+    }
+
+    /// Builds a call that maps the singleton guard to the output of the impl fn, for guard
+    /// singleton fns.
+    ///
+    /// Immutable fns map the guard from Singleton::read_guard, mutable fns map the guard from
+    /// MutableSingleton::write_guard (or the try_* variants, for fallible fns).
+    fn build_guard_stmt(&self, fn_ident: &Ident) -> TokenStream {
+        let blockz = paths::blockz_path();
+        let (mutable, arg) = match self.fn_type {
+            SingletonFnType::NonMut => (false, None),
+            SingletonFnType::NonMutWithArg(arg) => (false, Some(arg.build_impl_fn_call_arg())),
+            SingletonFnType::Mut => (true, None),
+            SingletonFnType::MutWithArg(arg) => (true, Some(arg.build_impl_fn_call_arg())),
+        };
+        let arg = arg.map(|arg| quote! { , #arg });
+        let guard_fn = self.build_use_fn_ident(format!(
+            "{}{}_guard",
+            if self.opts.fallible { "try_" } else { "" },
+            if mutable { "write" } else { "read" },
+        ));
+        let key = self.build_key_arg();
+        let guard_ident = if mutable {
+            format_ident!("SingletonWriteGuard")
+        } else {
+            format_ident!("SingletonReadGuard")
+        };
+        let map = quote! { #blockz::singleton::#guard_ident::map };
+        let project = quote! { move |inner| Self::#fn_ident(inner #arg) };
+        if self.opts.fallible {
+            quote! {
+                match Self::#guard_fn(#key).await {
+                    Ok(guard) => Ok(#map(guard, #project)),
+                    Err(err) => Err(err),
+                }
+            }
+        } else {
+            quote! { #map(Self::#guard_fn(#key).await, #project) }
         }
     }
 
-    /// Builds a Singleton::use_singleton_with_arg.
-    fn build_use_singleton_with_arg_stmt(fn_ident: &Ident, arg: &SingletonFnArgs) -> TokenStream {
-        let arg = arg.build_impl_fn_call_arg();
-        quote! {
-            Self::use_singleton_with_arg(Self::#fn_ident, #arg).await
+    /// Builds a call to the Singleton::*use_*singleton_blocking function that matches the fn
+    /// type, for sync singleton fns.
+    fn build_use_singleton_blocking_stmt(&self, fn_ident: &Ident) -> TokenStream {
+        let prefix = if self.opts.fallible { "try_" } else { "" };
+        let (use_fn, arg) = match self.fn_type {
+            SingletonFnType::NonMut => (format!("{}use_singleton_blocking", prefix), None),
+            SingletonFnType::NonMutWithArg(arg) => (
+                format!("{}use_singleton_blocking", prefix),
+                Some(arg.build_impl_fn_call_arg()),
+            ),
+            SingletonFnType::Mut => (format!("{}use_mut_singleton_blocking", prefix), None),
+            SingletonFnType::MutWithArg(arg) => (
+                format!("{}use_mut_singleton_blocking", prefix),
+                Some(arg.build_impl_fn_call_arg()),
+            ),
+        };
+        let use_fn = self.build_use_fn_ident(use_fn);
+        let key = self.build_key_arg();
+        let arg = arg.map(|arg| quote! { , #arg });
+        quote! { Self::#use_fn(#key move |inner| Self::#fn_ident(inner #arg)) }
+    }
+
+    /// Builds the ident of a singleton function, with the `_keyed` suffix: the facade calls the
+    /// function of the key of the call (see build_key_stmt).
+    fn build_use_fn_ident(&self, name: String) -> Ident {
+        format_ident!("{}_keyed", name)
+    }
+
+    /// Builds the key argument passed to the singleton functions.
+    ///
+    /// The key is hygienic, so it doesn't clash with the arguments of the base function.
+    fn build_key_arg(&self) -> TokenStream {
+        let key = Self::key_ident();
+        quote! { #key, }
+    }
+
+    /// Check whether the singleton fn routes its calls by the `shard_by` argument of its
+    /// singleton: all singleton fns do, except the keyed ones and the ones that name their own
+    /// `shard_by` argument.
+    fn is_routed(&self) -> bool {
+        !self.opts.keyed && self.opts.shard_by.is_none()
+    }
+
+    /// Builds the statement that gets the key of the call.
+    ///
+    /// Keyed singleton fns take the key as an argument. Singleton fns with a `shard_by` argument
+    /// get the shard of the argument. Other singleton fns pass their named arguments to
+    /// `route_args`, which gets the shard of the `shard_by` argument of a sharded singleton (and
+    /// fails to compile if the fn doesn't have the argument), or the key of singletons that
+    /// aren't sharded.
+    fn build_key_stmt(&self) -> Result<TokenStream> {
+        if self.opts.keyed {
+            return Ok(TokenStream::new());
+        }
+        let blockz = paths::blockz_path();
+        let key = Self::key_ident();
+        let arg_idents = match self.fn_type {
+            SingletonFnType::NonMutWithArg(arg) | SingletonFnType::MutWithArg(arg) => {
+                arg.facade_fn_arg_idents()
+            }
+            SingletonFnType::NonMut | SingletonFnType::Mut => Vec::new(),
+        };
+        // the arguments bound to an identifier, with their names
+        let named_args: Vec<_> = self
+            .base
+            .sig
+            .inputs
+            .iter()
+            .filter_map(|arg| match arg {
+                FnArg::Typed(arg) => Some(arg),
+                FnArg::Receiver(_) => None,
+            })
+            .zip(arg_idents)
+            .filter_map(|(arg, ident)| match &*arg.pat {
+                Pat::Ident(pat) => Some((pat.ident.unraw(), ident)),
+                _ => None,
+            })
+            .collect();
+        let shard_by = match &self.opts.shard_by {
+            Some(value) => value,
+            None => {
+                let std = paths::std_path();
+                // errors point to the fn: it doesn't have the shard_by argument
+                let span = self.base.sig.ident.span();
+                let args = named_args.iter().rev().fold(
+                    quote_spanned! {span=> () },
+                    |tail, (name, ident)| {
+                        let name = keyed::arg_name_type(&name.to_string());
+                        quote_spanned! {span=>
+                            ((#std::marker::PhantomData::<#name>, &#ident), #tail)
+                        }
+                    },
+                );
+                return Ok(quote_spanned! {span=>
+                    let #key = &Self::route_args(&#args);
+                });
+            }
+        };
+        let arg_ident = named_args
+            .into_iter()
+            .find_map(|(name, ident)| if name == shard_by { Some(ident) } else { None })
+            .ok_or_else(|| {
+                Error::new(
+                    self.base.sig.ident.span(),
+                    format!("shard_by argument '{}' not found", shard_by),
+                )
+            })?;
+        Ok(quote! {
+            let #key = &<Self as #blockz::singleton::ShardedSingleton>::shard_of(&#arg_ident);
+        })
+    }
+
+    /// Get the ident of the key argument of keyed singleton fns.
+    fn key_ident() -> Ident {
+        Ident::new("key", Span::mixed_site())
+    }
+
+    /// Add the key argument to a keyed singleton fn and rename it to `<name>_on`.
+    fn make_keyed_fn(target: &mut ItemFn) -> Result<()> {
+        let blockz = paths::blockz_path();
+        let key = Self::key_ident();
+        let key_arg: FnArg = syn::parse2(quote! {
+            #key: &<Self as #blockz::singleton::KeyedSingleton>::Key
+        })?;
+        target.sig.inputs.insert(0, key_arg);
+        target.sig.ident = format_ident!("{}_on", target.sig.ident);
+        Ok(())
+    }
+
+    /// Builds a check that the singleton can be used mutably, if the fn type is mutable.
+    ///
+    /// The error points to the receiver of the base function. The check also brings the mutable
+    /// trait into scope for the use statement.
+    fn build_mutable_check(&self) -> TokenStream {
+        if !matches!(
+            self.fn_type,
+            SingletonFnType::Mut | SingletonFnType::MutWithArg(_)
+        ) {
+            return TokenStream::new();
+        }
+        let blockz = paths::blockz_path();
+        let span = match self.base.sig.inputs.first() {
+            Some(receiver) => receiver.span(),
+            None => self.base.sig.ident.span(),
+        };
+        if self.opts.keyed {
+            return quote_spanned! {span=>
+                #[allow(unused_imports)]
+                use #blockz::singleton::MutableKeyedSingleton;
+                fn assert_mutable_singleton<T: #blockz::singleton::MutableKeyedSingleton + ?Sized>() {}
+                assert_mutable_singleton::<Self>();
+            };
+        }
+        // the key of the call tells whether the singleton is sharded
+        let key = Self::key_ident();
+        quote_spanned! {span=>
+            #[allow(unused_imports)]
+            use #blockz::singleton::MutableKeyedSingleton as _;
+            fn assert_mutable_singleton<T, K>(_: &K)
+            where
+                T: ?Sized,
+                K: #blockz::singleton::route::MutableKey<T> + ?Sized,
+            {
+            }
+            assert_mutable_singleton::<Self, _>(#key);
         }
     }
 
-    /// Builds a Singleton::use_mut_singleton.
-    fn build_use_mut_singleton_stmt(fn_ident: &Ident) -> TokenStream {
-        quote! {
-            Self::use_mut_singleton(Self::#fn_ident).await
+    /// Builds the check that the singleton can be borrowed with guards, for guard singleton fns.
+    ///
+    /// Singletons with a sync lock don't implement the guard traits, so this fails to compile
+    /// with the diagnostic of GuardSingleton (or MutableGuardSingleton), pointing at the fn. The
+    /// check also brings the guard trait into scope for the guard statement.
+    fn build_guard_check(&self) -> TokenStream {
+        if !self.opts.guard {
+            return TokenStream::new();
+        }
+        let blockz = paths::blockz_path();
+        let mutable = matches!(
+            self.fn_type,
+            SingletonFnType::Mut | SingletonFnType::MutWithArg(_)
+        );
+        let span = self.base.sig.ident.span();
+        if self.opts.keyed {
+            let guard_trait = if mutable {
+                format_ident!("MutableGuardKeyedSingleton")
+            } else {
+                format_ident!("GuardKeyedSingleton")
+            };
+            return quote_spanned! {span=>
+                #[allow(unused_imports)]
+                use #blockz::singleton::#guard_trait;
+                fn assert_guard_singleton<T: #blockz::singleton::#guard_trait + ?Sized>() {}
+                assert_guard_singleton::<Self>();
+            };
+        }
+        // the key of the call tells whether the singleton is sharded
+        let (guard_key, guard_trait) = if mutable {
+            (
+                format_ident!("MutableGuardKey"),
+                format_ident!("MutableGuardKeyedSingleton"),
+            )
+        } else {
+            (
+                format_ident!("GuardKey"),
+                format_ident!("GuardKeyedSingleton"),
+            )
+        };
+        let key = Self::key_ident();
+        quote_spanned! {span=>
+            #[allow(unused_imports)]
+            use #blockz::singleton::#guard_trait as _;
+            fn assert_guard_singleton<T, K>(_: &K)
+            where
+                T: ?Sized,
+                K: #blockz::singleton::route::#guard_key<T> + ?Sized,
+            {
+            }
+            assert_guard_singleton::<Self, _>(#key);
         }
     }
 
-    /// Builds a Singleton::use_mut_singleton_with_arg.
-    fn build_use_mut_singleton_with_arg_stmt(
-        fn_ident: &Ident,
-        arg: &SingletonFnArgs,
-    ) -> TokenStream {
-        let arg = arg.build_impl_fn_call_arg();
-        quote! {
-            Self::use_mut_singleton_with_arg(Self::#fn_ident, #arg).await
+    /// Builds the check that the singleton can be used by async functions, for async singleton
+    /// fns.
+    ///
+    /// Singletons with a sync lock don't implement AsyncSingleton, so this fails to compile with
+    /// its diagnostic, pointing at the fn (guard singleton fns are checked by the guard check).
+    fn build_async_check(&self) -> TokenStream {
+        if self.base.sig.asyncness.is_none() {
+            return TokenStream::new();
+        }
+        let blockz = paths::blockz_path();
+        let span = self.base.sig.ident.span();
+        if self.opts.keyed {
+            return quote_spanned! {span=>
+                fn assert_async_singleton<T: #blockz::singleton::AsyncSingleton + ?Sized>() {}
+                assert_async_singleton::<Self>();
+            };
         }
+        // the key of the call tells whether the singleton is sharded
+        let key = Self::key_ident();
+        quote_spanned! {span=>
+            fn assert_async_singleton<T, K>(_: &K)
+            where
+                T: ?Sized,
+                K: #blockz::singleton::route::AsyncKey<T> + ?Sized,
+            {
+            }
+            assert_async_singleton::<Self, _>(#key);
+        }
+    }
+
+    /// Replace the output of a guard singleton fn with a guard that dereferences to the
+    /// referenced type, and make the function async.
+    ///
+    /// The output must be a reference that borrows from the singleton: a shared one for `&self`,
+    /// a mutable one for `&mut self`.
+    fn make_guard_fn(&self, target: &mut ItemFn) -> Result<()> {
+        let std = paths::std_path();
+        let mutable = matches!(
+            self.fn_type,
+            SingletonFnType::Mut | SingletonFnType::MutWithArg(_)
+        );
+        let target_type = match &self.base.sig.output {
+            ReturnType::Type(_, ty) => match &**ty {
+                Type::Reference(reference) if reference.mutability.is_some() == mutable => {
+                    &reference.elem
+                }
+                _ => return Err(Self::guard_output_error(ty.span(), mutable)),
+            },
+            ReturnType::Default => {
+                return Err(Self::guard_output_error(self.base.sig.span(), mutable))
+            }
+        };
+        // the facade fn moves its arguments into a Send future
+        singleton_fns::add_thread_safety_bounds(&mut target.sig);
+        target.sig.output = if mutable {
+            syn::parse2(quote! { -> impl #std::ops::DerefMut<Target = #target_type> })?
+        } else {
+            syn::parse2(quote! { -> impl #std::ops::Deref<Target = #target_type> })?
+        };
+        target.sig.asyncness = Some(Default::default());
+        Ok(())
+    }
+
+    /// Create the error emitted when a guard singleton fn does not return a reference.
+    fn guard_output_error(span: Span, mutable: bool) -> Error {
+        Error::new(
+            span,
+            if mutable {
+                "guard singleton fns with a &mut self receiver must return a mutable reference (&mut T)"
+            } else {
+                "guard singleton fns with a &self receiver must return a reference (&T)"
+            },
+        )
+    }
+
+    /// Wrap the output of a fallible singleton fn in a Result.
+    fn wrap_fn_output(target: &mut ItemFn) -> Result<()> {
+        let blockz = paths::blockz_path();
+        let output = match &target.sig.output {
+            ReturnType::Default => quote! { () },
+            ReturnType::Type(_, ty) => quote! { #ty },
+        };
+        target.sig.output = syn::parse2(quote! {
+            -> Result<#output, #blockz::singleton::SingletonError>
+        })?;
+        Ok(())
     }
 
     /// Build the facade fn implementation.
@@ -164,22 +561,49 @@ impl<'f> FacadeFnFactory<'f> {
         // get the ident of the impl fn
         let impl_fn_ident = &self.impl_fn.sig.ident;
         // build the singleton use statement
-        let stmt = match self.fn_type {
-            SingletonFnType::NonMut => Self::build_use_singleton_stmt(impl_fn_ident),
-            SingletonFnType::NonMutWithArg(arg) => {
-                Self::build_use_singleton_with_arg_stmt(impl_fn_ident, arg)
+        let stmt = self.build_use_singleton_stmt(impl_fn_ident);
+        // build the checks that the singleton can be used mutably, asynchronously and borrowed
+        // with guards
+        let mutable_check = self.build_mutable_check();
+        let async_check = self.build_async_check();
+        let guard_check = self.build_guard_check();
+        // build the statement that gets the key of the call
+        let key_stmt = self.build_key_stmt()?;
+        // bring the functions of the key into scope: routed calls use the functions of either
+        // the singleton, the actor singleton or the sharded singleton
+        let imports = if self.is_routed() {
+            let actor_route = if cfg!(feature = "tokio-runtime") {
+                quote! {
+                    #[allow(unused_imports)]
+                    use #blockz::singleton::route::ActorRoute as _;
+                }
+            } else {
+                TokenStream::new()
+            };
+            quote! {
+                #[allow(unused_imports)]
+                use #blockz::singleton::route::UnshardedRoute as _;
+                #actor_route
+                #[allow(unused_imports)]
+                use #blockz::singleton::ShardedSingleton as _;
+                #[allow(unused_imports)]
+                use #blockz::singleton::KeyedSingleton as _;
             }
-            SingletonFnType::Mut => Self::build_use_mut_singleton_stmt(impl_fn_ident),
-            SingletonFnType::MutWithArg(arg) => {
-                Self::build_use_mut_singleton_with_arg_stmt(impl_fn_ident, arg)
+        } else {
+            quote! {
+                #[allow(unused_imports)]
+                use #blockz::singleton::KeyedSingleton;
             }
         };
         // replace the block with the new impl
         Self::replace_fn_block(
             target,
             quote! {
-                #[allow(unused_imports)]
-                use #blockz::singleton::Singleton;
+                #imports
+                #key_stmt
+                #mutable_check
+                #async_check
+                #guard_check
                 #stmt
             },
         )
@@ -191,12 +615,56 @@ impl<'f> Factory for FacadeFnFactory<'f> {
 
     /// Build the facade fn.
     fn build(self) -> Self::Product {
+        // guard singleton fns project the singleton guard, which is acquired asynchronously
+        if self.opts.guard && self.base.sig.asyncness.is_some() {
+            return Err(Error::new(
+                self.base.sig.fn_token.span,
+                "guard requires a sync singleton fn (the facade fn is async)",
+            ));
+        }
+        if self.opts.guard && self.opts.timeout.is_some() {
+            return Err(Error::new(
+                self.base.sig.fn_token.span,
+                "timeout can't be used with guard (use the lock_timeout of the singleton)",
+            ));
+        }
+        if self.opts.keyed && self.opts.shard_by.is_some() {
+            return Err(Error::new(
+                self.base.sig.fn_token.span,
+                "shard_by can't be used with keyed",
+            ));
+        }
+        // sync singleton fns block instead of timing out
+        if self.opts.timeout.is_some() && self.base.sig.asyncness.is_none() {
+            return Err(Error::new(
+                self.base.sig.fn_token.span,
+                "timeout requires an async singleton fn",
+            ));
+        }
         // create the working copy
         let mut facade_fn = self.base.clone();
         // remove the receiver
         Self::remove_fn_receiver(&mut facade_fn)?;
+        // bind the args to identifiers
+        self.bind_fn_args(&mut facade_fn);
         // add #[inline(always)] to the function
         Self::add_inline_always_attr(&mut facade_fn)?;
+        // async facades move their arguments into a Send future
+        if facade_fn.sig.asyncness.is_some() {
+            singleton_fns::add_thread_safety_bounds(&mut facade_fn.sig);
+        }
+        // guard facades return the guard instead of the reference
+        if self.opts.guard {
+            self.make_guard_fn(&mut facade_fn)?;
+        }
+        // keyed facades take the key and are named <name>_on
+        if self.opts.keyed {
+            Self::make_keyed_fn(&mut facade_fn)?;
+        }
+        // fallible facades return the singleton errors
+        if self.opts.fallible || self.opts.timeout.is_some() {
+            Self::wrap_fn_output(&mut facade_fn)?;
+        }
         // build the facade implementation
         self.build_facade_impl(&mut facade_fn)?;
         // return the function
@@ -1,12 +1,10 @@
 //! Impl fn factory.
 
-use std::collections::HashMap;
 use std::ops::DerefMut;
 
-use proc_macro2::Delimiter;
-use proc_macro2::Group;
+use proc_macro2::Ident;
+use proc_macro2::Span;
 use proc_macro2::TokenStream;
-use proc_macro2::TokenTree;
 
 use quote::format_ident;
 use quote::quote;
@@ -14,9 +12,11 @@ use quote::quote;
 use syn::parse::Parser;
 use syn::Attribute;
 use syn::ItemFn;
+use syn::Path;
 
 use crate::factory::Factory;
 
+use super::singleton_fns;
 use super::singleton_fns::SingletonFnType;
 
 /// Prefix for an impl fn used by a singleton fn.
@@ -32,12 +32,13 @@ const SINGLETON_FN_MUT_WITH_ARG_PREFIX: &str = "blockz_singleton_fn_mut_with_arg
 pub(super) struct ImplFnFactory<'f> {
     base: &'f ItemFn,
     fn_type: &'f SingletonFnType<'f>,
+    api: Option<&'f Path>,
 }
 
 impl<'f> ImplFnFactory<'f> {
     /// Create a new ImplFnFactory.
-    pub fn new(base: &'f ItemFn, fn_type: &'f SingletonFnType) -> Self {
-        Self { base, fn_type }
+    pub fn new(base: &'f ItemFn, fn_type: &'f SingletonFnType, api: Option<&'f Path>) -> Self {
+        Self { base, fn_type, api }
     }
 
     /// Renames a function according to the appropriate singleton fn type.
@@ -60,84 +61,89 @@ impl<'f> ImplFnFactory<'f> {
 
     /// Fixes the args of a function, if necessary.
     fn fix_fn_args(&self, target: &mut ItemFn) -> syn::Result<()> {
-        let impl_fn_arg: Option<_>;
-        match self.fn_type {
-            SingletonFnType::NonMut => impl_fn_arg = None,
-            SingletonFnType::NonMutWithArg(arg) => impl_fn_arg = Some(arg),
-            SingletonFnType::Mut => impl_fn_arg = None,
-            SingletonFnType::MutWithArg(arg) => impl_fn_arg = Some(arg),
-        }
+        let impl_fn_arg = match self.fn_type {
+            SingletonFnType::NonMut => None,
+            SingletonFnType::NonMutWithArg(arg) => Some(arg),
+            SingletonFnType::Mut => None,
+            SingletonFnType::MutWithArg(arg) => Some(arg),
+        };
         if let Some(value) = impl_fn_arg {
             target.sig.inputs = target
                 .sig
                 .inputs
                 .iter()
-                .cloned()
                 .take(1)
+                .cloned()
                 .chain(vec![value.build_impl_fn_sig_arg()?])
                 .collect();
         }
         Ok(())
     }
 
-    /// Recursevly apply a replace legend to a token stream.
-    fn apply_replace_legend(
-        stream: TokenStream,
-        legend: &HashMap<String, TokenStream>,
-    ) -> TokenStream {
-        stream
-            .into_iter()
-            .map(|tt| match tt {
-                TokenTree::Ident(ident) => {
-                    if let Some(value) = legend.get(ident.to_string().as_str()) {
-                        TokenTree::Group(Group::new(Delimiter::None, value.clone()))
-                    } else {
-                        TokenTree::Ident(ident)
-                    }
-                }
-                TokenTree::Group(group) => {
-                    let delim = group.delimiter();
-                    let tokens = Self::apply_replace_legend(group.stream(), legend);
-                    TokenTree::Group(Group::new(delim, tokens))
-                }
-                other => other,
-            })
-            .collect::<TokenStream>()
-    }
-
     /// Fixes the block of a function, if necessary.
+    ///
+    /// The arguments packed in the tuple argument are bound to their original patterns at the
+    /// start of the block, so the body is used as-is.
     fn fix_fn_block(&self, target: &mut ItemFn) -> syn::Result<()> {
-        // create the replacement legend for fixing the impl fn block
-        let replace_legend: Option<_>;
-        match self.fn_type {
-            SingletonFnType::NonMut => replace_legend = None,
-            SingletonFnType::NonMutWithArg(arg) => {
-                replace_legend = arg.build_impl_fn_replacement_legend()
+        let pattern_stmts = match self.fn_type {
+            SingletonFnType::NonMutWithArg(arg) | SingletonFnType::MutWithArg(arg) => {
+                arg.build_impl_fn_pattern_stmts()
             }
-            SingletonFnType::Mut => replace_legend = None,
-            SingletonFnType::MutWithArg(arg) => {
-                replace_legend = arg.build_impl_fn_replacement_legend()
-            }
-        }
+            SingletonFnType::NonMut | SingletonFnType::Mut => TokenStream::new(),
+        };
 
         // if fixing the impl block is not required, return
-        if replace_legend.is_none() {
+        if pattern_stmts.is_empty() {
             return Ok(());
         }
 
-        // unwrap the replace legend
-        let replace_legend = replace_legend.unwrap();
-
         // get new the block
         let block = target.block.deref_mut();
-        *block = syn::parse2(Self::apply_replace_legend(
-            quote! { #block },
-            &replace_legend,
-        ))?;
+        let stmts = &block.stmts;
+        *block = syn::parse2(quote! {
+            {
+                #pattern_stmts
+                #(#stmts)*
+            }
+        })?;
 
         Ok(())
     }
 
+    /// Replaces the receiver and the body of a function with a call to the trait of a trait
+    /// singleton.
+    ///
+    /// The inner value of trait singletons is a boxed trait object instead of the type itself, so
+    /// the impl fn takes the inner value and forwards the call to it.
+    fn make_trait_fn(&self, target: &mut ItemFn, api: &Path) -> syn::Result<()> {
+        let inner = Ident::new("inner", Span::mixed_site());
+        let (mutability, args) = match self.fn_type {
+            SingletonFnType::NonMut => (None, None),
+            SingletonFnType::NonMutWithArg(arg) => (None, Some(arg)),
+            SingletonFnType::Mut => (Some(quote! { mut }), None),
+            SingletonFnType::MutWithArg(arg) => (Some(quote! { mut }), Some(arg)),
+        };
+        let (sig_arg, call_args) = match args {
+            Some(arg) => (
+                Some(arg.build_trait_impl_fn_sig_arg()?),
+                arg.build_trait_impl_fn_call_args(),
+            ),
+            None => (None, TokenStream::new()),
+        };
+        target.sig.inputs = syn::parse_quote! {
+            #inner: &#mutability Box<dyn #api + Send + Sync>, #sig_arg
+        };
+
+        let fn_ident = &self.base.sig.ident;
+        let dot_await = self.base.sig.asyncness.map(|_| quote! { .await });
+        *target.block = syn::parse2(quote! {
+            {
+                #api::#fn_ident(&#mutability **#inner, #call_args)#dot_await
+            }
+        })?;
+        Ok(())
+    }
+
     /// Make a function private.
     fn make_fn_private(target: &mut ItemFn) {
         target.vis = syn::Visibility::Inherited;
@@ -174,10 +180,19 @@ impl<'f> Factory for ImplFnFactory<'f> {
         Self::add_automatically_derived_attr(&mut impl_fn)?;
         // rename the function
         self.rename_fn(&mut impl_fn);
-        // fix the fn args
-        self.fix_fn_args(&mut impl_fn)?;
-        // fix the fn block
-        self.fix_fn_block(&mut impl_fn)?;
+        if let Some(api) = self.api {
+            // trait singletons forward the call to their inner value
+            self.make_trait_fn(&mut impl_fn, api)?;
+        } else {
+            // fix the fn args
+            self.fix_fn_args(&mut impl_fn)?;
+            // fix the fn block
+            self.fix_fn_block(&mut impl_fn)?;
+        }
+        // async impl fns are called from a Send future
+        if impl_fn.sig.asyncness.is_some() {
+            singleton_fns::add_thread_safety_bounds(&mut impl_fn.sig);
+        }
         // return the function
         Ok(impl_fn)
     }
@@ -0,0 +1,73 @@
+//! Singleton keys.
+
+use darling::FromMeta;
+
+use proc_macro2::TokenStream;
+
+use quote::quote;
+
+use crate::paths;
+
+use syn::Type;
+
+/// The keys of a keyed singleton, which holds a value for each key.
+pub(super) enum SingletonKey {
+    /// String keys: `#[singleton(keyed)]`.
+    Str,
+    /// Typed keys, such as an enum: `#[singleton(keyed = "Type")]`.
+    Typed(Box<Type>),
+    /// Shard indices, for a singleton split into shards: `#[singleton(shards = N)]`.
+    Shards(usize),
+}
+
+impl SingletonKey {
+    /// Get the type of the keys, as borrowed by the keyed singleton functions.
+    pub fn to_key_type(&self) -> TokenStream {
+        match self {
+            Self::Str => quote! { str },
+            Self::Typed(ty) => quote! { #ty },
+            Self::Shards(_) => quote! { usize },
+        }
+    }
+
+    /// Get the type of the keys, as owned by the registry.
+    pub fn to_owned_key_type(&self) -> TokenStream {
+        match self {
+            Self::Str => quote! { String },
+            Self::Typed(ty) => quote! { #ty },
+            Self::Shards(_) => quote! { usize },
+        }
+    }
+
+    /// Get the kind of singleton that uses the keys, as displayed in messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Str | Self::Typed(_) => "keyed",
+            Self::Shards(_) => "sharded",
+        }
+    }
+}
+
+impl FromMeta for SingletonKey {
+    fn from_word() -> darling::Result<Self> {
+        Ok(Self::Str)
+    }
+
+    fn from_string(value: &str) -> darling::Result<Self> {
+        syn::parse_str(value)
+            .map(|ty| Self::Typed(Box::new(ty)))
+            .map_err(|err| darling::Error::custom(format!("invalid key type '{}': {}", value, err)))
+    }
+}
+
+/// Build the type that names an argument of a singleton fn, as a list of characters.
+///
+/// Sharded singletons name their `shard_by` argument with this type and singleton fns name their
+/// arguments with it, so the call is routed by the argument with the same name at compile time.
+pub(super) fn arg_name_type(name: &str) -> TokenStream {
+    let blockz = paths::blockz_path();
+    let name = name.strip_prefix("r#").unwrap_or(name);
+    name.chars().rev().fold(quote! { () }, |tail, c| {
+        quote! { (#blockz::singleton::route::C<#c>, #tail) }
+    })
+}
@@ -6,58 +6,247 @@ use proc_macro2::Ident;
 use proc_macro2::TokenStream;
 
 use quote::quote;
+use quote::ToTokens;
 
 use crate::paths;
 
+use super::actor::SingletonActor;
+use super::runtime::SingletonRuntime;
+
 /// The lock behing a singleton.
-#[derive(FromMeta)]
+#[derive(Default, FromMeta)]
 pub(super) enum SingletonLock {
-    /// tokio::sync::Mutex
+    /// An async mutex: tokio::sync::Mutex or async_lock::Mutex
     #[darling(rename = "mutex")]
+    #[default]
     Mutex,
-    /// tokio::sync::RwLock
+    /// An async rwlock: tokio::sync::RwLock or async_lock::RwLock
     #[darling(rename = "rwlock")]
     RwLock,
+    /// blockz::singleton::SwapLock
+    #[darling(rename = "swap")]
+    Swap,
+    /// No lock, the singleton can't be mutated.
+    #[darling(rename = "none")]
+    None,
+    /// std::sync::Mutex
+    #[darling(rename = "std_mutex")]
+    StdMutex,
+    /// parking_lot::Mutex
+    #[darling(rename = "parking_lot")]
+    ParkingLot,
+    /// blockz::singleton::actor::Actor, set by `#[singleton(actor)]`.
+    #[darling(skip)]
+    Actor(SingletonActor),
 }
 
-impl Default for SingletonLock {
-    fn default() -> Self {
-        Self::Mutex
+impl SingletonLock {
+    /// Check whether the singleton is behind a lock (and can be mutated).
+    pub fn has_lock(&self) -> bool {
+        !matches!(self, Self::None)
+    }
+
+    /// Check whether the lock can be held by async functions.
+    pub fn is_async(&self) -> bool {
+        !matches!(self, Self::StdMutex | Self::ParkingLot)
     }
-}
 
-impl SingletonLock {
     /// Create a new lock type with the inner type.
-    pub fn to_type(&self, inner: &Ident) -> TokenStream {
-        let tokio = paths::tokio_path();
+    pub fn to_type(&self, runtime: &SingletonRuntime, inner: impl ToTokens) -> TokenStream {
+        let blockz = paths::blockz_path();
+        let parking_lot = paths::parking_lot_path();
+        let std = paths::std_path();
+        let sync = runtime.sync_path();
         match self {
-            Self::Mutex => quote! { #tokio::sync::Mutex<#inner> },
-            Self::RwLock => quote! { #tokio::sync::RwLock<#inner> },
+            Self::Mutex => quote! { #sync::Mutex<#inner> },
+            Self::RwLock => quote! { #sync::RwLock<#inner> },
+            Self::Swap => quote! { #blockz::singleton::SwapLock<#inner> },
+            Self::None => quote! { #inner },
+            Self::StdMutex => quote! { #std::sync::Mutex<#inner> },
+            Self::ParkingLot => quote! { #parking_lot::Mutex<#inner> },
+            Self::Actor(_) => quote! { #blockz::singleton::actor::Actor<#inner> },
         }
     }
 
-    /// Create a lock initialization expression that sets the inner value to the ident.
-    pub fn to_new_lock_expr(&self, inner: &Ident) -> TokenStream {
-        let tokio = paths::tokio_path();
+    /// Check whether acquiring the lock can fail, in which case the guard expressions are
+    /// results (see to_checked_guard).
+    pub fn is_fallible(&self) -> bool {
+        matches!(self, Self::Actor(_))
+    }
+
+    /// Create the type of the lock guard returned by to_guard, for a lock with the inner type.
+    ///
+    /// Singletons without a lock are guarded by a static reference to the inner value.
+    pub fn to_guard_type(&self, runtime: &SingletonRuntime, inner: impl ToTokens) -> TokenStream {
+        let blockz = paths::blockz_path();
+        let parking_lot = paths::parking_lot_path();
+        let std = paths::std_path();
+        let sync = runtime.sync_path();
         match self {
-            Self::Mutex => quote! { #tokio::sync::Mutex::new(#inner) },
-            Self::RwLock => quote! { #tokio::sync::RwLock::new(#inner) },
+            Self::Mutex => quote! { #sync::MutexGuard<'static, #inner> },
+            Self::RwLock => quote! { #sync::RwLockReadGuard<'static, #inner> },
+            Self::Swap => quote! { #std::sync::Arc<#inner> },
+            Self::None => quote! { &'static #inner },
+            Self::StdMutex => quote! { #std::sync::MutexGuard<'static, #inner> },
+            Self::ParkingLot => quote! { #parking_lot::MutexGuard<'static, #inner> },
+            Self::Actor(_) => quote! { #blockz::singleton::actor::ActorGuard<#inner> },
+        }
+    }
+
+    /// Create the type of the lock guard returned by to_mut_guard, for a lock with the inner type.
+    pub fn to_mut_guard_type(
+        &self,
+        runtime: &SingletonRuntime,
+        inner: impl ToTokens,
+    ) -> TokenStream {
+        let blockz = paths::blockz_path();
+        let sync = runtime.sync_path();
+        match self {
+            Self::RwLock => quote! { #sync::RwLockWriteGuard<'static, #inner> },
+            Self::Swap => quote! { #blockz::singleton::swap::SwapWriteGuard<'static, #inner> },
+            Self::None => Self::immutable_error(),
+            _ => self.to_guard_type(runtime, inner),
+        }
+    }
+
+    /// Create a lock initialization expression that sets the inner value to the expression.
+    pub fn to_new_lock_expr(
+        &self,
+        runtime: &SingletonRuntime,
+        inner: impl ToTokens,
+    ) -> TokenStream {
+        let blockz = paths::blockz_path();
+        let parking_lot = paths::parking_lot_path();
+        let std = paths::std_path();
+        let sync = runtime.sync_path();
+        match self {
+            Self::Mutex => quote! { #sync::Mutex::new(#inner) },
+            Self::RwLock => quote! { #sync::RwLock::new(#inner) },
+            Self::Swap => quote! { #blockz::singleton::SwapLock::new(#inner) },
+            Self::None => quote! { #inner },
+            Self::StdMutex => quote! { #std::sync::Mutex::new(#inner) },
+            Self::ParkingLot => quote! { #parking_lot::Mutex::new(#inner) },
+            Self::Actor(actor) => {
+                let config = actor.to_config_expr();
+                quote! { #blockz::singleton::actor::Actor::new(#inner, #config) }
+            }
         }
     }
 
     /// Create a new lock guard from a lock identifier.
-    pub fn to_guard(&self, lock: &Ident) -> TokenStream {
+    ///
+    /// The guards of sync locks must not be held across await points.
+    pub fn to_guard(&self, runtime: &SingletonRuntime, lock: &Ident) -> TokenStream {
         match self {
             Self::Mutex => quote! { #lock.lock().await },
             Self::RwLock => quote! { #lock.read().await },
+            Self::Swap => quote! { #lock.load() },
+            Self::None => quote! { #lock },
+            Self::StdMutex | Self::ParkingLot => self.to_blocking_guard(runtime, lock),
+            Self::Actor(_) => quote! { #lock.lock().await },
         }
     }
 
     /// Create a new mutable lock guard from a lock identifier.
-    pub fn to_mut_guard(&self, lock: &Ident) -> TokenStream {
+    ///
+    /// The guards of sync locks must not be held across await points.
+    pub fn to_mut_guard(&self, runtime: &SingletonRuntime, lock: &Ident) -> TokenStream {
         match self {
             Self::Mutex => quote! { #lock.lock().await },
             Self::RwLock => quote! { #lock.write().await },
+            Self::Swap => quote! { #lock.write().await },
+            Self::None => Self::immutable_error(),
+            Self::StdMutex | Self::ParkingLot => self.to_blocking_mut_guard(runtime, lock),
+            Self::Actor(_) => quote! { #lock.lock().await },
+        }
+    }
+
+    /// Create a new lock guard from a lock identifier, blocking the current thread.
+    pub fn to_blocking_guard(&self, runtime: &SingletonRuntime, lock: &Ident) -> TokenStream {
+        let std = paths::std_path();
+        match self {
+            Self::Mutex if runtime.is_tokio() => quote! { #lock.blocking_lock() },
+            Self::Mutex => quote! { #lock.lock_blocking() },
+            Self::RwLock if runtime.is_tokio() => quote! { #lock.blocking_read() },
+            Self::RwLock => quote! { #lock.read_blocking() },
+            Self::Swap => quote! { #lock.load() },
+            Self::None => quote! { #lock },
+            Self::StdMutex => {
+                quote! { #lock.lock().unwrap_or_else(#std::sync::PoisonError::into_inner) }
+            }
+            Self::ParkingLot => quote! { #lock.lock() },
+            Self::Actor(_) => quote! { #lock.blocking_lock() },
+        }
+    }
+
+    /// Create a new mutable lock guard from a lock identifier, blocking the current thread.
+    pub fn to_blocking_mut_guard(&self, runtime: &SingletonRuntime, lock: &Ident) -> TokenStream {
+        match self {
+            Self::Mutex => self.to_blocking_guard(runtime, lock),
+            Self::RwLock if runtime.is_tokio() => quote! { #lock.blocking_write() },
+            Self::RwLock => quote! { #lock.write_blocking() },
+            Self::Swap => quote! { #lock.blocking_write() },
+            Self::None => Self::immutable_error(),
+            Self::StdMutex | Self::ParkingLot | Self::Actor(_) => {
+                self.to_blocking_guard(runtime, lock)
+            }
         }
     }
+
+    /// Create a new mutable lock guard from a lock identifier, without waiting for the lock.
+    ///
+    /// The expression is a result that fails if the lock is in use.
+    pub fn to_try_mut_guard(&self, runtime: &SingletonRuntime, lock: &Ident) -> TokenStream {
+        match self {
+            Self::Mutex if runtime.is_tokio() => quote! { #lock.try_lock() },
+            Self::Mutex => quote! { #lock.try_lock().ok_or(()) },
+            Self::RwLock if runtime.is_tokio() => quote! { #lock.try_write() },
+            Self::RwLock => quote! { #lock.try_write().ok_or(()) },
+            Self::Swap => quote! { #lock.try_write().ok_or(()) },
+            Self::None => Self::immutable_error(),
+            Self::StdMutex => quote! { #lock.try_lock() },
+            Self::ParkingLot => quote! { #lock.try_lock().ok_or(()) },
+            Self::Actor(_) => quote! { #lock.try_lock() },
+        }
+    }
+
+    /// Create the expression that takes the value out of a lock identifier, if the lock has its
+    /// own way to do it.
+    ///
+    /// Actors are stopped (see blockz::singleton::actor::Actor::stop).
+    pub fn to_take(&self, lock: &Ident) -> Option<TokenStream> {
+        match self {
+            Self::Actor(_) => Some(quote! { #lock.stop().await }),
+            _ => None,
+        }
+    }
+
+    /// Handle the errors of a guard expression, for locks whose acquisition can fail.
+    ///
+    /// The error is converted to a SingletonError of the singleton with the name, available as
+    /// `err` to the on_err expression.
+    pub fn to_checked_guard(
+        &self,
+        guard: TokenStream,
+        name: &str,
+        on_err: TokenStream,
+    ) -> TokenStream {
+        if !self.is_fallible() {
+            return guard;
+        }
+        quote! {
+            match #guard {
+                Ok(guard) => guard,
+                Err(err) => {
+                    let err = err.into_singleton_error(#name);
+                    #on_err
+                }
+            }
+        }
+    }
+
+    /// Create the error emitted when a singleton without a lock is mutated.
+    fn immutable_error() -> TokenStream {
+        quote! { compile_error!("singletons without a lock can't be mutated") }
+    }
 }
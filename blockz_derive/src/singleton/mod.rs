@@ -1,16 +1,22 @@
 //! Singleton macro.
 
+mod actor;
 mod derive_static;
 mod derive_trait;
 mod facade_fn;
 mod impl_fn;
+mod keyed;
 mod lock;
+mod runtime;
 mod singleton_fns;
+mod singleton_impl;
+mod watch;
 
 use convert_case::Case;
 use convert_case::Casing;
 
 use darling::FromDeriveInput;
+use darling::FromMeta;
 
 use proc_macro2::Ident;
 use proc_macro2::TokenStream;
@@ -18,23 +24,50 @@ use proc_macro2::TokenStream;
 use quote::format_ident;
 use quote::quote;
 
+use syn::AttributeArgs;
 use syn::DeriveInput;
 use syn::ItemFn;
+use syn::Path;
+use syn::Type;
 
 use std::convert::TryFrom;
 
+use crate::common::DurationMeta;
 use crate::factory::Factory;
+use crate::paths;
 
+use self::actor::SingletonActor;
 use self::derive_static::SingletonStaticFactory;
 use self::derive_trait::SingletonTraitFactory;
 use self::facade_fn::FacadeFnFactory;
 use self::impl_fn::ImplFnFactory;
+use self::keyed::SingletonKey;
 use self::lock::SingletonLock;
+use self::runtime::SingletonRuntime;
 use self::singleton_fns::SingletonFnType;
+use self::watch::SingletonWatch;
+
+pub(crate) use self::singleton_impl::SingletonImplFactory;
 
 /// Prefix for the generated singleton static.
 const SINGLETON_STATIC_PREFIX: &str = "BLOCKZ_SINGLETON_STATIC_";
 
+/// Prefix for the generated scope local that overrides a singleton in a scope.
+const SINGLETON_SCOPE_STATIC_PREFIX: &str = "BLOCKZ_SINGLETON_SCOPE_";
+
+/// Prefix for the generated static that holds the last failed lazy initialization of a singleton.
+const SINGLETON_INIT_STATIC_PREFIX: &str = "BLOCKZ_SINGLETON_INIT_";
+
+/// Prefix for the generated static that publishes the values of a watched singleton.
+const SINGLETON_WATCH_STATIC_PREFIX: &str = "BLOCKZ_SINGLETON_WATCH_";
+
+/// Prefix for the generated function that gets the entry of a singleton (or of a key in a keyed
+/// singleton).
+const SINGLETON_ENTRY_FN_PREFIX: &str = "blockz_singleton_entry_";
+
+/// Prefix for the generated function that gets a lazily initialized singleton.
+const SINGLETON_GET_FN_PREFIX: &str = "blockz_singleton_get_";
+
 /// A factory that builds singletons.
 pub(crate) struct SingletonFactory<'i> {
     input: &'i DeriveInput,
@@ -46,6 +79,111 @@ pub(crate) struct SingletonFactory<'i> {
 pub(crate) struct SingletonOpts {
     #[darling(default)]
     lock: SingletonLock,
+    #[darling(default)]
+    runtime: SingletonRuntime,
+    #[darling(default, multiple, rename = "instance")]
+    instances: Vec<String>,
+    #[darling(default)]
+    init: Option<Path>,
+    #[darling(default)]
+    init_backoff: Option<DurationMeta>,
+    #[darling(default)]
+    lock_timeout: Option<DurationMeta>,
+    #[darling(default)]
+    watch: Option<SingletonWatch>,
+    #[darling(default)]
+    keyed: Option<SingletonKey>,
+    #[darling(default)]
+    shards: Option<usize>,
+    #[darling(default)]
+    shard_by: Option<String>,
+    #[darling(default)]
+    actor: Option<SingletonActor>,
+    #[darling(default, rename = "trait")]
+    api: Option<Path>,
+}
+
+/// A concrete type that gets its own singleton.
+pub(super) struct SingletonInstance {
+    /// The type that implements Singleton.
+    ty: Type,
+    /// The inner value of the singleton: the type itself, or the boxed trait object of a trait
+    /// singleton.
+    inner: Type,
+    /// The name of the type, as displayed in docs and messages.
+    name: String,
+    /// The static that holds the entry of the singleton (or, for keyed singletons, the registry
+    /// of the keys).
+    static_ident: Ident,
+    /// The scope local that overrides the entry of the singleton in a scope.
+    scope_static_ident: Ident,
+    /// The static that holds the last failed lazy initialization of the singleton.
+    init_static_ident: Ident,
+    /// The function that gets the lazily initialized singleton.
+    get_fn_ident: Ident,
+    /// The static that publishes the values of the watched singleton.
+    watch_static_ident: Ident,
+    /// The function that gets the entry of the singleton (or of a key in the keyed singleton).
+    entry_fn_ident: Ident,
+}
+
+impl SingletonInstance {
+    /// Create a new singleton instance.
+    fn new(ty: Type, name: String, api: Option<&Path>) -> Self {
+        // convert the identifiers in the type name to upper snake case
+        let suffix = name
+            .split(|ch: char| !ch.is_alphanumeric() && ch != '_')
+            .filter(|part| !part.is_empty())
+            .map(|part| part.to_case(Case::UpperSnake))
+            .collect::<Vec<String>>()
+            .join("_");
+        let inner = match api {
+            Some(api) => syn::parse_quote! { Box<dyn #api + Send + Sync> },
+            None => ty.clone(),
+        };
+        Self {
+            ty,
+            inner,
+            name,
+            static_ident: format_ident!("{}{}", SINGLETON_STATIC_PREFIX, suffix),
+            scope_static_ident: format_ident!("{}{}", SINGLETON_SCOPE_STATIC_PREFIX, suffix),
+            init_static_ident: format_ident!("{}{}", SINGLETON_INIT_STATIC_PREFIX, suffix),
+            get_fn_ident: format_ident!("{}{}", SINGLETON_GET_FN_PREFIX, suffix.to_lowercase()),
+            watch_static_ident: format_ident!("{}{}", SINGLETON_WATCH_STATIC_PREFIX, suffix),
+            entry_fn_ident: format_ident!("{}{}", SINGLETON_ENTRY_FN_PREFIX, suffix.to_lowercase()),
+        }
+    }
+
+    /// Create the type of the entry of the singleton (or of a key in a keyed singleton): the
+    /// static that holds the singleton (or its lock) and the flag that tracks whether it holds a
+    /// value.
+    fn to_entry_type(&self, opts: &SingletonOpts) -> TokenStream {
+        // get paths to deps
+        let once_cell = paths::once_cell_path();
+        let std = paths::std_path();
+
+        // get lock type, the value is optional so that it can be taken out of the singleton
+        let inner = &self.inner;
+        let lock_type = if opts.lock.has_lock() {
+            opts.lock.to_type(&opts.runtime, quote! { Option<#inner> })
+        } else {
+            quote! { #inner }
+        };
+        quote! {
+            (#once_cell::sync::OnceCell<#lock_type>, #std::sync::atomic::AtomicBool)
+        }
+    }
+
+    /// Create the statement that runs the statements only outside of the scopes of the singleton
+    /// (see Singleton::scope), such as the publishing of watched singletons.
+    fn to_unscoped(&self, stmts: TokenStream) -> TokenStream {
+        let scope_static_ident = &self.scope_static_ident;
+        quote! {
+            if #scope_static_ident.try_with(|_| ()).is_err() {
+                #stmts
+            }
+        }
+    }
 }
 
 /// A factory that builds singleton fns.
@@ -54,22 +192,150 @@ pub(crate) struct SingletonFnFactory<'f> {
     base: &'f ItemFn,
     // the function type that will be built by the factory
     fn_type: SingletonFnType<'f>,
+    // the options of the singleton fn
+    opts: SingletonFnOpts,
+    // the trait of the singleton, for the singleton fns of trait singletons
+    api: Option<&'f Path>,
+}
+
+/// Options used by the singleton fn factory.
+#[derive(Clone, Default, FromMeta)]
+pub(super) struct SingletonFnOpts {
+    /// Return a SingletonError instead of panicking if the singleton is not initialized.
+    #[darling(default)]
+    fallible: bool,
+    /// Return a SingletonError if the call (including the lock acquisition) does not finish in
+    /// time.
+    #[darling(default)]
+    timeout: Option<DurationMeta>,
+    /// Return a guard that keeps the singleton locked and dereferences to the output of the
+    /// function, which borrows from the singleton.
+    #[darling(default)]
+    guard: bool,
+    /// Use a keyed singleton, taking the key as the first argument of the function (which gets
+    /// the `_on` suffix).
+    #[darling(default)]
+    keyed: bool,
+    /// Use a sharded singleton, routing the call to the shard of the argument with this name.
+    #[darling(default)]
+    shard_by: Option<String>,
 }
 
 impl<'i> SingletonFactory<'i> {
     /// Create a new singleton factory.
     pub fn new(input: &'i DeriveInput) -> Result<Self, darling::Error> {
-        Ok(Self {
-            input,
-            opts: SingletonOpts::from_derive_input(input)?,
-        })
+        let mut opts = SingletonOpts::from_derive_input(input)?;
+        // sharded singletons are keyed by the index of the shard
+        if let Some(shards) = opts.shards {
+            if opts.keyed.is_some() {
+                return Err(darling::Error::custom("shards can't be used with keyed")
+                    .with_span(&input.ident));
+            }
+            if shards == 0 {
+                return Err(
+                    darling::Error::custom("shards must be at least 1").with_span(&input.ident)
+                );
+            }
+            opts.keyed = Some(SingletonKey::Shards(shards));
+        }
+        // singleton fns of sharded singletons are routed by the shard_by argument
+        if let Some(shard_by) = &opts.shard_by {
+            if opts.shards.is_none() {
+                return Err(
+                    darling::Error::custom("shard_by requires shards").with_span(&input.ident)
+                );
+            }
+            if syn::parse_str::<Ident>(shard_by).is_err() {
+                return Err(darling::Error::custom(format!(
+                    "shard_by must name an argument, found '{}'",
+                    shard_by
+                ))
+                .with_span(&input.ident));
+            }
+        }
+        // actor singletons keep their value in an actor instead of a lock
+        if let Some(actor) = opts.actor.take() {
+            if !matches!(opts.lock, SingletonLock::Mutex) {
+                return Err(darling::Error::custom(
+                    "actor singletons don't have a lock (remove lock = \"...\")",
+                )
+                .with_span(&input.ident));
+            }
+            opts.lock = SingletonLock::Actor(actor);
+        }
+        // get_singleton of singletons without a lock borrows the type, which trait singletons
+        // don't hold
+        if opts.api.is_some() && matches!(opts.lock, SingletonLock::None) {
+            return Err(darling::Error::custom(
+                "trait singletons need a lock (lock = \"none\" gets the type, not the trait object)",
+            )
+            .with_span(&input.ident));
+        }
+        Ok(Self { input, opts })
     }
 
-    /// Create the name of the static variable that holds the singleton.
-    fn create_static_ident(src: &Ident) -> Ident {
-        // convert the type name to upper snake case and add a prefix
-        let type_name_upper = src.to_string().to_case(Case::UpperSnake);
-        format_ident!("{}{}", SINGLETON_STATIC_PREFIX, type_name_upper)
+    /// Create the instances that get a singleton.
+    ///
+    /// Generic types can't have a single static, so each concrete instantiation declared with
+    /// `#[singleton(instance = "...")]` gets its own.
+    fn create_instances(&self) -> syn::Result<Vec<SingletonInstance>> {
+        let type_name = &self.input.ident;
+        if self.opts.instances.is_empty() {
+            if !self.input.generics.params.is_empty() {
+                return Err(syn::Error::new_spanned(
+                    &self.input.generics,
+                    format!(
+                        "generic singletons must declare their instances, such as #[singleton(instance = \"{}<...>\")]",
+                        type_name
+                    ),
+                ));
+            }
+            return Ok(vec![SingletonInstance::new(
+                syn::parse_quote! { #type_name },
+                type_name.to_string(),
+                self.opts.api.as_ref(),
+            )]);
+        }
+
+        let mut instances: Vec<SingletonInstance> = Vec::with_capacity(self.opts.instances.len());
+        for instance in self.opts.instances.iter() {
+            let ty: Type = syn::parse_str(instance).map_err(|err| {
+                syn::Error::new_spanned(
+                    type_name,
+                    format!("failed to parse singleton instance '{}': {}", instance, err),
+                )
+            })?;
+            let is_instance_of_type = match &ty {
+                Type::Path(path) if path.qself.is_none() => path
+                    .path
+                    .segments
+                    .last()
+                    .is_some_and(|segment| &segment.ident == type_name),
+                _ => false,
+            };
+            if !is_instance_of_type {
+                return Err(syn::Error::new_spanned(
+                    type_name,
+                    format!(
+                        "singleton instance '{}' is not an instance of {}",
+                        instance, type_name
+                    ),
+                ));
+            }
+            let instance =
+                SingletonInstance::new(ty, instance.trim().to_string(), self.opts.api.as_ref());
+            if instances
+                .iter()
+                .any(|other| other.static_ident == instance.static_ident)
+            {
+                return Err(syn::Error::new_spanned(
+                    type_name,
+                    format!("duplicate singleton instance '{}'", instance.name),
+                ));
+            }
+            instances.push(instance);
+        }
+        Ok(instances)
     }
 }
 
@@ -78,25 +344,124 @@ impl<'i> Factory for SingletonFactory<'i> {
 
     /// Build the Singleton implementation.
     fn build(self) -> Self::Product {
-        let static_ident = Self::create_static_ident(&self.input.ident);
-        let singleton_static =
-            SingletonStaticFactory::new(&static_ident, &self.input.ident, &self.opts.lock)
-                .build()?;
-        let singleton_trait =
-            SingletonTraitFactory::new(&static_ident, &self.input.ident, &self.opts.lock).build();
-        Ok(quote! {
-            #singleton_static
-            #singleton_trait
-        })
+        if self.opts.init_backoff.is_some() && self.opts.init.is_none() {
+            return Err(syn::Error::new_spanned(
+                &self.input.ident,
+                "init_backoff requires a lazy initialization function (init = \"...\")",
+            ));
+        }
+        if !cfg!(feature = "tokio-runtime") {
+            let unsupported = if self.opts.runtime.is_tokio() {
+                Some("runtime = \"tokio\" (use runtime = \"agnostic\" instead)")
+            } else if self.opts.watch.is_some() {
+                Some("watch")
+            } else {
+                None
+            };
+            if let Some(unsupported) = unsupported {
+                return Err(syn::Error::new_spanned(
+                    &self.input.ident,
+                    format!(
+                        "{} requires the tokio-runtime feature of blockz",
+                        unsupported
+                    ),
+                ));
+            }
+        }
+        if !cfg!(feature = "swap") && matches!(self.opts.lock, SingletonLock::Swap) {
+            return Err(syn::Error::new_spanned(
+                &self.input.ident,
+                "lock = \"swap\" requires the swap feature of blockz",
+            ));
+        }
+        if let Some(keyed) = &self.opts.keyed {
+            let unsupported = if self.opts.init.is_some() {
+                Some("lazy initialization (init = \"...\")")
+            } else if self.opts.watch.is_some() {
+                Some("watch")
+            } else {
+                None
+            };
+            if let Some(unsupported) = unsupported {
+                return Err(syn::Error::new_spanned(
+                    &self.input.ident,
+                    format!("{} singletons don't support {}", keyed.kind(), unsupported),
+                ));
+            }
+        }
+        if matches!(self.opts.lock, SingletonLock::Actor(_)) {
+            let unsupported = if self.opts.init.is_some() {
+                Some("lazy initialization (init = \"...\")")
+            } else if !self.opts.runtime.is_tokio() {
+                Some("runtime = \"agnostic\" (actors run on tokio)")
+            } else if let Some(keyed) = &self.opts.keyed {
+                Some(match keyed {
+                    SingletonKey::Shards(_) => "shards (an actor holds a single value)",
+                    _ => "keys (an actor holds a single value)",
+                })
+            } else {
+                None
+            };
+            if let Some(unsupported) = unsupported {
+                return Err(syn::Error::new_spanned(
+                    &self.input.ident,
+                    format!("actor singletons don't support {}", unsupported),
+                ));
+            }
+        }
+        if self.opts.watch.is_some() && !self.opts.lock.has_lock() {
+            return Err(syn::Error::new_spanned(
+                &self.input.ident,
+                "watch requires a singleton with a lock (singletons without a lock never change)",
+            ));
+        }
+        if !self.opts.lock.is_async() {
+            let unsupported = if self.opts.init.is_some() {
+                Some("lazy initialization (init = \"...\")")
+            } else if self.opts.lock_timeout.is_some() {
+                Some("lock_timeout")
+            } else {
+                None
+            };
+            if let Some(unsupported) = unsupported {
+                return Err(syn::Error::new_spanned(
+                    &self.input.ident,
+                    format!("{} requires an async lock", unsupported),
+                ));
+            }
+        }
+        let mut tokens = TokenStream::new();
+        for instance in self.create_instances()?.iter() {
+            let singleton_static = SingletonStaticFactory::new(instance, &self.opts).build()?;
+            let singleton_trait = SingletonTraitFactory::new(instance, &self.opts).build();
+            tokens.extend(quote! {
+                #singleton_static
+                #singleton_trait
+            });
+        }
+        Ok(tokens)
     }
 }
 
 impl<'f> SingletonFnFactory<'f> {
     /// Create a new singleton fn factory.
-    pub fn new(base: &'f ItemFn) -> syn::Result<Self> {
+    pub fn new(args: &AttributeArgs, base: &'f ItemFn) -> Result<Self, darling::Error> {
+        Ok(Self {
+            base,
+            fn_type: SingletonFnType::try_from(base)?,
+            opts: SingletonFnOpts::from_list(args)?,
+            api: None,
+        })
+    }
+
+    /// Create a new singleton fn factory for a method of a trait singleton (see
+    /// SingletonImplFactory).
+    fn with_api(base: &'f ItemFn, opts: SingletonFnOpts, api: &'f Path) -> syn::Result<Self> {
         Ok(Self {
             base,
             fn_type: SingletonFnType::try_from(base)?,
+            opts,
+            api: Some(api),
         })
     }
 }
@@ -106,8 +471,12 @@ impl<'f> Factory for SingletonFnFactory<'f> {
 
     /// Build the singleton fn facade and impl.
     fn build(self) -> Self::Product {
-        let impl_fn = ImplFnFactory::new(self.base, &self.fn_type).build()?;
-        let facade_fn = FacadeFnFactory::new(self.base, &self.fn_type, &impl_fn).build()?;
+        if !self.opts.guard {
+            singleton_fns::check_output_not_borrowed(self.base)?;
+        }
+        let impl_fn = ImplFnFactory::new(self.base, &self.fn_type, self.api).build()?;
+        let facade_fn =
+            FacadeFnFactory::new(self.base, &self.fn_type, &self.opts, &impl_fn).build()?;
         Ok(quote! {
             #facade_fn
             #impl_fn
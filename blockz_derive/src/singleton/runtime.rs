@@ -0,0 +1,59 @@
+//! Singleton runtime.
+
+use darling::FromMeta;
+
+use proc_macro2::TokenStream;
+
+use quote::quote;
+use quote::ToTokens;
+
+use crate::paths;
+
+/// The async runtime that a singleton is used with.
+#[derive(Default, FromMeta)]
+pub(super) enum SingletonRuntime {
+    /// tokio
+    #[darling(rename = "tokio")]
+    #[default]
+    Tokio,
+    /// Any executor: blockz::singleton::agnostic
+    #[darling(rename = "agnostic")]
+    Agnostic,
+}
+
+impl SingletonRuntime {
+    /// Check whether the runtime is tokio.
+    pub fn is_tokio(&self) -> bool {
+        matches!(self, Self::Tokio)
+    }
+
+    /// Get the path to the module that holds the async Mutex and RwLock.
+    pub fn sync_path(&self) -> TokenStream {
+        match self {
+            Self::Tokio => {
+                let tokio = paths::tokio_path();
+                quote! { #tokio::sync }
+            }
+            Self::Agnostic => {
+                let blockz = paths::blockz_path();
+                quote! { #blockz::singleton::agnostic }
+            }
+        }
+    }
+
+    /// Create an expression that runs a future with a timeout.
+    ///
+    /// The expression is a result that fails if the timeout elapsed.
+    pub fn to_timeout(&self, duration: impl ToTokens, future: impl ToTokens) -> TokenStream {
+        match self {
+            Self::Tokio => {
+                let tokio = paths::tokio_path();
+                quote! { #tokio::time::timeout(#duration, #future).await }
+            }
+            Self::Agnostic => {
+                let blockz = paths::blockz_path();
+                quote! { #blockz::singleton::agnostic::timeout(#duration, #future).await }
+            }
+        }
+    }
+}
@@ -1,38 +1,49 @@
 //! Singleton fns utilities.
 
-use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
 use std::fmt::Display;
 
+use proc_macro2::Delimiter;
 use proc_macro2::Span;
 use proc_macro2::TokenStream;
+use proc_macro2::TokenTree;
 
+use quote::format_ident;
 use quote::quote;
 
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 use syn::Error;
 use syn::FnArg;
+use syn::GenericArgument;
+use syn::GenericParam;
 use syn::Ident;
 use syn::Index;
 use syn::ItemFn;
 use syn::Pat;
 use syn::PatType;
+use syn::PathArguments;
 use syn::Receiver;
+use syn::ReturnType;
+use syn::Signature;
 use syn::Type;
+use syn::TypeParamBound;
 use syn::TypeTuple;
 
 /// The name of the tuple argument for singleton fns.
 const SINGLETON_FN_TUPLE_ARG_NAME: &str = "args";
 
+/// Prefix for the name that binds an argument pattern in a facade fn.
+const SINGLETON_FN_ARG_PREFIX: &str = "blockz_singleton_arg_";
+
 /// Errors produced when attempting to build singleton fns.
 enum SingletonFnError<'e> {
     FnHasNoInputs(&'e ItemFn),
     FnReceiverNotRef(&'e Receiver),
     FnArgNotReceiver(&'e PatType),
     FnArgNotTyped(&'e FnArg),
-    PatTypeNoIdent(&'e PatType),
+    OutputBorrowsSingleton(&'e ReturnType),
 }
 
 /// SingletonFn type.
@@ -49,18 +60,25 @@ pub(super) enum SingletonFnArgs<'f> {
     ///
     /// This is passed as-is to the singleton function.
     Single {
-        /// The identifier of the function argument.
-        arg_ident: &'f Ident,
+        /// The identifier that binds the function argument in the facade fn.
+        arg_ident: Ident,
+        /// The pattern of the original function argument.
+        arg_pat: &'f Pat,
         /// The type of the function argument.
         arg_type: &'f Type,
     },
     /// Multiple arguments.
     ///
-    /// These need to be converted to a single argument as a tuple.
+    /// These need to be converted to a single argument as a tuple, which the impl fn destructures
+    /// into the original patterns.
     Multiple {
-        /// The idents of the original function arguments.
-        arg_idents: Vec<&'f Ident>,
+        /// The identifiers that bind the function arguments in the facade fn.
+        arg_idents: Vec<Ident>,
+        /// The patterns of the original function arguments.
+        arg_pats: Vec<&'f Pat>,
         /// The ident of the tuple function argument.
+        ///
+        /// This is hygienic, so it can't be used by (or conflict with) the body of the impl fn.
         tuple_ident: Ident,
         /// The type of the tuple function argument.
         ///
@@ -70,6 +88,17 @@ pub(super) enum SingletonFnArgs<'f> {
 }
 
 impl<'f> SingletonFnArgs<'f> {
+    /// Get the identifiers that bind the function arguments in the facade fn.
+    ///
+    /// Arguments that are not bound to an identifier (such as `(a, b): (u32, u32)`) get a
+    /// synthetic one.
+    pub fn facade_fn_arg_idents(&self) -> Vec<&Ident> {
+        match self {
+            SingletonFnArgs::Single { arg_ident, .. } => vec![arg_ident],
+            SingletonFnArgs::Multiple { arg_idents, .. } => arg_idents.iter().collect(),
+        }
+    }
+
     /// Build the argument used for a singleton call by the facade fn.
     ///
     /// This will either just return the ident of the arg, if there is only
@@ -78,7 +107,7 @@ impl<'f> SingletonFnArgs<'f> {
     /// The call arg will be used for:
     /// Singleton::use_singleton_with_arg
     /// or
-    /// Singleton::use_mut_singleton_with_arg
+    /// MutableSingleton::use_mut_singleton_with_arg
     pub fn build_impl_fn_call_arg(&self) -> TokenStream {
         match self {
             SingletonFnArgs::Single { arg_ident, .. } => {
@@ -92,12 +121,31 @@ impl<'f> SingletonFnArgs<'f> {
 
     /// Build the fn input that will be accepted by the impl fn.
     ///
-    /// This builds the `arg: (i64, u64, ...)` in the function signature.
+    /// This builds the `arg: (i64, u64, ...)` in the function signature. A single argument keeps
+    /// its original pattern.
     pub fn build_impl_fn_sig_arg(&self) -> syn::Result<FnArg> {
+        match self {
+            SingletonFnArgs::Single {
+                arg_pat, arg_type, ..
+            } => syn::parse2(quote! { #arg_pat: #arg_type }),
+            SingletonFnArgs::Multiple {
+                tuple_ident,
+                tuple_type,
+                ..
+            } => syn::parse2(quote! { #tuple_ident: #tuple_type }),
+        }
+    }
+
+    /// Build the fn input that will be accepted by the impl fn of a trait singleton.
+    ///
+    /// Unlike [build_impl_fn_sig_arg](Self::build_impl_fn_sig_arg), a single argument is bound to
+    /// its identifier, since the patterns are restored by the implementation of the trait.
+    pub fn build_trait_impl_fn_sig_arg(&self) -> syn::Result<FnArg> {
         match self {
             SingletonFnArgs::Single {
                 arg_ident,
                 arg_type,
+                ..
             } => syn::parse2(quote! { #arg_ident: #arg_type }),
             SingletonFnArgs::Multiple {
                 tuple_ident,
@@ -107,31 +155,43 @@ impl<'f> SingletonFnArgs<'f> {
         }
     }
 
-    pub fn build_impl_fn_replacement_legend(&self) -> Option<HashMap<String, TokenStream>> {
+    /// Build the arguments passed to the trait method by the impl fn of a trait singleton.
+    ///
+    /// This builds `arg` (or `args.0, args.1, ...`).
+    pub fn build_trait_impl_fn_call_args(&self) -> TokenStream {
         match self {
-            SingletonFnArgs::Single { .. } => None,
+            SingletonFnArgs::Single { arg_ident, .. } => quote! { #arg_ident },
             SingletonFnArgs::Multiple {
                 arg_idents,
                 tuple_ident,
                 ..
             } => {
-                let replacement_legend = arg_idents
-                    .iter()
-                    .enumerate()
-                    .map(|(index, arg)| {
-                        let index = Index::from(index);
-                        (
-                            // the name of the argument
-                            format!("{}", quote! {#arg}),
-                            // the tuple element replacement
-                            quote! { #tuple_ident.#index },
-                        )
-                    })
-                    .collect::<HashMap<String, TokenStream>>();
-                Some(replacement_legend)
+                let indices = (0..arg_idents.len()).map(Index::from);
+                quote! { #(#tuple_ident.#indices),* }
             }
         }
     }
+
+    /// Build the statements that restore the argument patterns in the impl fn.
+    ///
+    /// This builds a `let a = args.0;` (or `let (b, c) = args.1;`) for each argument.
+    pub fn build_impl_fn_pattern_stmts(&self) -> TokenStream {
+        match self {
+            SingletonFnArgs::Single { .. } => TokenStream::new(),
+            SingletonFnArgs::Multiple {
+                arg_pats,
+                tuple_ident,
+                ..
+            } => arg_pats
+                .iter()
+                .enumerate()
+                .map(|(index, arg)| {
+                    let index = Index::from(index);
+                    quote! { let #arg = #tuple_ident.#index; }
+                })
+                .collect(),
+        }
+    }
 }
 
 /// Get a SingletonFnType from a function.
@@ -195,6 +255,21 @@ impl<'f> TryFrom<&'f ItemFn> for SingletonFnType<'f> {
     }
 }
 
+/// Check that the output of a singleton fn does not borrow from the singleton.
+///
+/// The singleton is only borrowed while the function runs (unlike guard singleton fns, whose
+/// output is kept alive by the guard).
+pub(super) fn check_output_not_borrowed(base: &ItemFn) -> syn::Result<()> {
+    let receiver = match base.sig.inputs.first() {
+        Some(input) => fn_arg_as_receiver(input)?,
+        None => return Err(SingletonFnError::FnHasNoInputs(base).into()),
+    };
+    if output_borrows_receiver(&base.sig.output, receiver) {
+        return Err(SingletonFnError::OutputBorrowsSingleton(&base.sig.output).into());
+    }
+    Ok(())
+}
+
 /// Get the SingletonFnArgs for a series of typed fn args(which have a PatType inside).
 impl<'f> TryFrom<&[&'f PatType]> for SingletonFnArgs<'f> {
     type Error = syn::Error;
@@ -207,21 +282,21 @@ impl<'f> TryFrom<&[&'f PatType]> for SingletonFnArgs<'f> {
             // the function has a single argument
             let arg = src.first().unwrap();
             Ok(Self::Single {
-                arg_ident: pat_type_as_ident(*arg)?,
-                arg_type: &*arg.ty,
+                arg_ident: facade_fn_arg_ident(0, &arg.pat),
+                arg_pat: &arg.pat,
+                arg_type: &arg.ty,
             })
         } else {
             // the function has multiple arguments
-            let arg_idents = {
-                let mut arg_idents = Vec::with_capacity(src.len());
-                for arg in src {
-                    arg_idents.push(pat_type_as_ident(arg)?);
-                }
-                arg_idents
-            };
+            let arg_idents = src
+                .iter()
+                .enumerate()
+                .map(|(index, arg)| facade_fn_arg_ident(index, &arg.pat))
+                .collect();
+            let arg_pats = src.iter().map(|arg| &*arg.pat).collect();
 
             // create the ident for the args tuple
-            let tuple_ident = Ident::new(SINGLETON_FN_TUPLE_ARG_NAME, Span::call_site());
+            let tuple_ident = Ident::new(SINGLETON_FN_TUPLE_ARG_NAME, Span::mixed_site());
 
             // create the tuple arg type
             let tuple_type = {
@@ -239,6 +314,7 @@ impl<'f> TryFrom<&[&'f PatType]> for SingletonFnArgs<'f> {
 
             Ok(Self::Multiple {
                 arg_idents,
+                arg_pats,
                 tuple_ident,
                 tuple_type,
             })
@@ -268,8 +344,8 @@ impl<'e> Display for SingletonFnError<'e> {
                 )
             }
             FnArgNotTyped(_) => write!(f, "function argument is not typed"),
-            PatTypeNoIdent(_) => {
-                write!(f, "function argument must have an identifier")
+            OutputBorrowsSingleton(_) => {
+                write!(f, "singleton fn output must not borrow from the singleton, which is only locked while the function runs (return an owned value, or name the lifetime of the argument that the output borrows from)")
             }
         }
     }
@@ -283,7 +359,10 @@ impl<'e> Spanned for SingletonFnError<'e> {
             FnReceiverNotRef(recv) => recv.span(),
             FnArgNotReceiver(arg) => arg.span(),
             FnArgNotTyped(arg) => arg.span(),
-            PatTypeNoIdent(pat_ty) => pat_ty.span(),
+            OutputBorrowsSingleton(output) => match output {
+                ReturnType::Default => output.span(),
+                ReturnType::Type(_, ty) => ty.span(),
+            },
         }
     }
 }
@@ -312,11 +391,155 @@ fn fn_arg_as_typed(src: &FnArg) -> syn::Result<&PatType> {
     }
 }
 
-/// Get a pat type as an ident.
-fn pat_type_as_ident(src: &PatType) -> syn::Result<&Ident> {
-    if let Pat::Ident(value) = &*src.pat {
-        Ok(&value.ident)
-    } else {
-        Err(SingletonFnError::PatTypeNoIdent(src).into())
+/// Get the identifier that binds an argument pattern in the facade fn.
+///
+/// Identifier patterns keep their identifier, while other patterns get a synthetic one.
+fn facade_fn_arg_ident(index: usize, src: &Pat) -> Ident {
+    match src {
+        Pat::Ident(value) if value.subpat.is_none() => value.ident.clone(),
+        _ => format_ident!("{}{}", SINGLETON_FN_ARG_PREFIX, index),
+    }
+}
+
+/// Check whether a fn output borrows from the receiver.
+///
+/// With a reference receiver, elided lifetimes (`&T` or `'_`) in the output are the lifetime of
+/// the receiver, as is the lifetime named by the receiver (`&'a self`).
+fn output_borrows_receiver(output: &ReturnType, receiver: &Receiver) -> bool {
+    fn borrows(stream: TokenStream, receiver_lifetime: Option<&str>) -> bool {
+        let mut tokens = stream.into_iter().peekable();
+        while let Some(tt) = tokens.next() {
+            let borrows = match tt {
+                // a reference without a lifetime
+                TokenTree::Punct(punct) if punct.as_char() == '&' => {
+                    !matches!(tokens.peek(), Some(TokenTree::Punct(next)) if next.as_char() == '\'')
+                }
+                // a lifetime: the tick is followed by the name of the lifetime
+                TokenTree::Punct(punct) if punct.as_char() == '\'' => match tokens.next() {
+                    Some(TokenTree::Ident(ident)) => {
+                        ident == "_" || receiver_lifetime.is_some_and(|name| ident == name)
+                    }
+                    _ => false,
+                },
+                // elided lifetimes in the inputs of fn pointers and closures are higher-ranked
+                TokenTree::Ident(ident)
+                    if ident == "fn" || ident == "Fn" || ident == "FnMut" || ident == "FnOnce" =>
+                {
+                    if let Some(TokenTree::Group(group)) = tokens.peek() {
+                        if group.delimiter() == Delimiter::Parenthesis {
+                            tokens.next();
+                        }
+                    }
+                    false
+                }
+                TokenTree::Group(group) => borrows(group.stream(), receiver_lifetime),
+                _ => false,
+            };
+            if borrows {
+                return true;
+            }
+        }
+        false
+    }
+
+    let ty = match output {
+        ReturnType::Default => return false,
+        ReturnType::Type(_, ty) => ty,
+    };
+    let receiver_lifetime = receiver
+        .reference
+        .as_ref()
+        .and_then(|(_, lifetime)| lifetime.as_ref())
+        .map(|lifetime| lifetime.ident.to_string());
+    borrows(quote! { #ty }, receiver_lifetime.as_deref())
+}
+
+/// Add the Send (or Sync) bounds required by async singleton fns to a signature.
+///
+/// The arguments and the output of async singleton fns are moved into a Send future, so generic
+/// type parameters and `impl Trait` arguments must be Send when used by value (or behind a mutable
+/// reference) and Sync when used behind a shared reference.
+pub(super) fn add_thread_safety_bounds(sig: &mut Signature) {
+    /// The bound required by a type.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Bound {
+        Send,
+        Sync,
+    }
+
+    impl Bound {
+        fn to_type_param_bound(self) -> TypeParamBound {
+            match self {
+                Self::Send => syn::parse_quote! { Send },
+                Self::Sync => syn::parse_quote! { Sync },
+            }
+        }
+    }
+
+    /// Walk a type, adding the bounds to `impl Trait` types and collecting the bounds required
+    /// by the generic type parameters.
+    fn walk(ty: &mut Type, shared: bool, params: &[Ident], required: &mut Vec<(Ident, Bound)>) {
+        let bound = if shared { Bound::Sync } else { Bound::Send };
+        match ty {
+            Type::ImplTrait(impl_trait) => impl_trait.bounds.push(bound.to_type_param_bound()),
+            Type::Path(path) if path.qself.is_none() => {
+                if let Some(ident) = path.path.get_ident() {
+                    if params.contains(ident) {
+                        required.push((ident.clone(), bound));
+                        return;
+                    }
+                }
+                for segment in path.path.segments.iter_mut() {
+                    if let PathArguments::AngleBracketed(args) = &mut segment.arguments {
+                        for arg in args.args.iter_mut() {
+                            if let GenericArgument::Type(ty) = arg {
+                                walk(ty, shared, params, required);
+                            }
+                        }
+                    }
+                }
+            }
+            Type::Reference(reference) => walk(
+                &mut reference.elem,
+                shared || reference.mutability.is_none(),
+                params,
+                required,
+            ),
+            Type::Tuple(tuple) => {
+                for elem in tuple.elems.iter_mut() {
+                    walk(elem, shared, params, required);
+                }
+            }
+            Type::Array(array) => walk(&mut array.elem, shared, params, required),
+            Type::Slice(slice) => walk(&mut slice.elem, shared, params, required),
+            Type::Paren(paren) => walk(&mut paren.elem, shared, params, required),
+            Type::Group(group) => walk(&mut group.elem, shared, params, required),
+            _ => {}
+        }
+    }
+
+    let params = sig
+        .generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect::<Vec<Ident>>();
+    let mut required = Vec::new();
+    for input in sig.inputs.iter_mut() {
+        if let FnArg::Typed(arg) = input {
+            walk(&mut arg.ty, false, &params, &mut required);
+        }
+    }
+    if let ReturnType::Type(_, ty) = &mut sig.output {
+        walk(ty, false, &params, &mut required);
+    }
+
+    for param in sig.generics.params.iter_mut() {
+        if let GenericParam::Type(param) = param {
+            for bound in [Bound::Send, Bound::Sync] {
+                if required.contains(&(param.ident.clone(), bound)) {
+                    param.bounds.push(bound.to_type_param_bound());
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,291 @@
+//! Singleton impl factory.
+
+use darling::FromMeta;
+
+use proc_macro2::TokenStream;
+use proc_macro2::TokenTree;
+
+use quote::quote;
+
+use syn::Attribute;
+use syn::AttributeArgs;
+use syn::FnArg;
+use syn::ImplItem;
+use syn::ImplItemMethod;
+use syn::ItemFn;
+use syn::ItemImpl;
+use syn::Meta;
+use syn::Path;
+use syn::Visibility;
+
+use std::convert::TryFrom;
+
+use crate::factory::Factory;
+
+use super::singleton_fns::SingletonFnType;
+use super::SingletonFnFactory;
+use super::SingletonFnOpts;
+
+/// The name of the attribute that marks the singleton fns of an impl block.
+const SINGLETON_FN_ATTR: &str = "singleton_fn";
+
+/// A factory that builds the trait of a trait singleton from the singleton fns of an impl block.
+pub(crate) struct SingletonImplFactory<'i> {
+    input: &'i ItemImpl,
+    opts: SingletonImplOpts,
+    // the singleton fns of the impl block, without their #[singleton_fn] attribute
+    singleton_fns: Vec<SingletonImplFn>,
+}
+
+/// Options used by the singleton impl factory.
+#[derive(FromMeta)]
+struct SingletonImplOpts {
+    /// The trait that is generated for the singleton fns.
+    #[darling(rename = "trait")]
+    api: Path,
+    /// The visibility of the trait (pub by default).
+    #[darling(default)]
+    vis: Option<String>,
+    /// Attributes of the trait, such as `mockall::automock`.
+    #[darling(default, multiple, rename = "trait_attr")]
+    trait_attrs: Vec<String>,
+}
+
+/// A singleton fn of an impl block.
+struct SingletonImplFn {
+    /// The method.
+    method: ImplItemMethod,
+    /// The options of the singleton fn.
+    opts: SingletonFnOpts,
+}
+
+impl<'i> SingletonImplFactory<'i> {
+    /// Create a new singleton impl factory.
+    pub fn new(args: &AttributeArgs, input: &'i ItemImpl) -> Result<Self, darling::Error> {
+        let opts = SingletonImplOpts::from_list(args)?;
+        if opts.api.get_ident().is_none() {
+            return Err(darling::Error::custom(
+                "the trait of a singleton impl must be an identifier",
+            )
+            .with_span(&opts.api));
+        }
+        if let Some((_, path, _)) = &input.trait_ {
+            return Err(darling::Error::custom(
+                "singleton_impl requires an inherent impl block, not a trait impl",
+            )
+            .with_span(path));
+        }
+
+        let mut singleton_fns = Vec::new();
+        for item in input.items.iter() {
+            let method = match item {
+                ImplItem::Method(method) => method,
+                _ => continue,
+            };
+            let attr = match method.attrs.iter().position(is_singleton_fn_attr) {
+                Some(index) => index,
+                None => continue,
+            };
+            let mut method = method.clone();
+            let attr = method.attrs.remove(attr);
+            let opts = SingletonFnOpts::from_list(&parse_singleton_fn_args(&attr)?)?;
+            check_object_safe(&method, &opts)?;
+            singleton_fns.push(SingletonImplFn { method, opts });
+        }
+        if singleton_fns.is_empty() {
+            return Err(darling::Error::custom(
+                "singleton_impl requires at least one #[singleton_fn] method",
+            )
+            .with_span(&input.self_ty));
+        }
+
+        Ok(Self {
+            input,
+            opts,
+            singleton_fns,
+        })
+    }
+
+    /// Build the trait, with the signatures of the singleton fns.
+    ///
+    /// The arguments are bound to identifiers, since methods without a body can't have patterns.
+    fn build_trait(&self) -> syn::Result<TokenStream> {
+        let api = &self.opts.api;
+        let vis: Visibility = match &self.opts.vis {
+            Some(vis) => syn::parse_str(vis)?,
+            None => syn::parse_quote! { pub },
+        };
+        let trait_attrs = self
+            .opts
+            .trait_attrs
+            .iter()
+            .map(|attr| syn::parse_str::<TokenStream>(attr))
+            .collect::<syn::Result<Vec<_>>>()?;
+        let mut sigs = Vec::with_capacity(self.singleton_fns.len());
+        for singleton_fn in self.singleton_fns.iter() {
+            let base = singleton_fn.to_item_fn();
+            let fn_type = SingletonFnType::try_from(&base)?;
+            let arg_idents = match &fn_type {
+                SingletonFnType::NonMutWithArg(arg) | SingletonFnType::MutWithArg(arg) => {
+                    arg.facade_fn_arg_idents()
+                }
+                SingletonFnType::NonMut | SingletonFnType::Mut => Vec::new(),
+            };
+            let mut sig = base.sig.clone();
+            let args = sig.inputs.iter_mut().filter_map(|arg| match arg {
+                FnArg::Typed(arg) => Some(arg),
+                FnArg::Receiver(_) => None,
+            });
+            for (arg, ident) in args.zip(arg_idents) {
+                arg.pat = syn::parse_quote! { #ident };
+            }
+            let docs = base.attrs.iter().filter(|attr| attr.path.is_ident("doc"));
+            sigs.push(quote! {
+                #(#docs)*
+                #sig;
+            });
+        }
+        // the attributes come before async_trait, which mocking attributes expect
+        Ok(quote! {
+            #(#[#trait_attrs])*
+            #[async_trait::async_trait]
+            #vis trait #api: Send + Sync {
+                #(#sigs)*
+            }
+        })
+    }
+
+    /// Build the implementation of the trait for the type, with the methods of the singleton
+    /// fns.
+    fn build_trait_impl(&self) -> TokenStream {
+        let api = &self.opts.api;
+        let self_ty = &self.input.self_ty;
+        let (impl_generics, _, where_clause) = self.input.generics.split_for_impl();
+        let methods = self.singleton_fns.iter().map(|singleton_fn| {
+            let mut method = singleton_fn.method.clone();
+            method.vis = Visibility::Inherited;
+            method
+        });
+        quote! {
+            #[async_trait::async_trait]
+            impl #impl_generics #api for #self_ty #where_clause {
+                #(#methods)*
+            }
+        }
+    }
+
+    /// Build the inherent impl block, with the other items and the facade and impl fns of the
+    /// singleton fns.
+    fn build_inherent_impl(&self) -> syn::Result<TokenStream> {
+        let api = &self.opts.api;
+        let attrs = &self.input.attrs;
+        let self_ty = &self.input.self_ty;
+        let (impl_generics, _, where_clause) = self.input.generics.split_for_impl();
+        let items = self.input.items.iter().filter(|item| match item {
+            ImplItem::Method(method) => !method.attrs.iter().any(is_singleton_fn_attr),
+            _ => true,
+        });
+        let mut fns = Vec::with_capacity(self.singleton_fns.len());
+        for singleton_fn in self.singleton_fns.iter() {
+            let base = singleton_fn.to_item_fn();
+            let opts = singleton_fn.opts.clone();
+            fns.push(SingletonFnFactory::with_api(&base, opts, api)?.build()?);
+        }
+        Ok(quote! {
+            #(#attrs)*
+            impl #impl_generics #self_ty #where_clause {
+                #(#items)*
+                #(#fns)*
+            }
+        })
+    }
+}
+
+impl<'i> Factory for SingletonImplFactory<'i> {
+    type Product = syn::Result<TokenStream>;
+
+    /// Build the trait, its implementation and the singleton fns.
+    fn build(self) -> Self::Product {
+        let api_trait = self.build_trait()?;
+        let trait_impl = self.build_trait_impl();
+        let inherent_impl = self.build_inherent_impl()?;
+        Ok(quote! {
+            #api_trait
+            #trait_impl
+            #inherent_impl
+        })
+    }
+}
+
+impl SingletonImplFn {
+    /// Convert the method to a fn, as expected by the singleton fn factory.
+    fn to_item_fn(&self) -> ItemFn {
+        ItemFn {
+            attrs: self.method.attrs.clone(),
+            vis: self.method.vis.clone(),
+            sig: self.method.sig.clone(),
+            block: Box::new(self.method.block.clone()),
+        }
+    }
+}
+
+/// Check whether an attribute is #[singleton_fn] (with any path, such as
+/// #[blockz::singleton_fn]).
+fn is_singleton_fn_attr(attr: &Attribute) -> bool {
+    attr.path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == SINGLETON_FN_ATTR)
+}
+
+/// Parse the args of a #[singleton_fn] attribute.
+fn parse_singleton_fn_args(attr: &Attribute) -> syn::Result<AttributeArgs> {
+    match attr.parse_meta()? {
+        Meta::Path(_) => Ok(AttributeArgs::new()),
+        Meta::List(list) => Ok(list.nested.into_iter().collect()),
+        Meta::NameValue(value) => Err(syn::Error::new_spanned(
+            value,
+            "expected #[singleton_fn] or #[singleton_fn(...)]",
+        )),
+    }
+}
+
+/// Check that a singleton fn can be a method of the trait, which is used as a trait object.
+fn check_object_safe(method: &ImplItemMethod, opts: &SingletonFnOpts) -> darling::Result<()> {
+    /// Check whether a type contains an `impl Trait`.
+    fn has_impl_trait(stream: TokenStream) -> bool {
+        stream.into_iter().any(|tt| match tt {
+            TokenTree::Ident(ident) => ident == "impl",
+            TokenTree::Group(group) => has_impl_trait(group.stream()),
+            _ => false,
+        })
+    }
+
+    let sig = &method.sig;
+    if opts.guard {
+        return Err(darling::Error::custom(
+            "guard singleton fns can't be used with a singleton impl",
+        )
+        .with_span(&sig.ident));
+    }
+    if let Some(param) = sig.generics.type_params().next() {
+        return Err(darling::Error::custom(
+            "singleton fns of a singleton impl can't have generic type parameters (the trait is used as a trait object)",
+        )
+        .with_span(param));
+    }
+    let impl_trait_arg = sig.inputs.iter().find(|arg| match arg {
+        FnArg::Typed(arg) => {
+            let ty = &arg.ty;
+            has_impl_trait(quote! { #ty })
+        }
+        FnArg::Receiver(_) => false,
+    });
+    if let Some(arg) = impl_trait_arg {
+        return Err(darling::Error::custom(
+            "singleton fns of a singleton impl can't have impl Trait arguments (the trait is used as a trait object)",
+        )
+        .with_span(arg));
+    }
+    Ok(())
+}
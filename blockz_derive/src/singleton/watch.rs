@@ -0,0 +1,73 @@
+//! Singleton watch.
+
+use darling::FromMeta;
+
+use proc_macro2::Ident;
+use proc_macro2::TokenStream;
+
+use quote::quote;
+use quote::ToTokens;
+
+use syn::Type;
+
+use crate::paths;
+
+/// The value published to the subscribers of a singleton.
+pub(super) enum SingletonWatch {
+    /// A clone of the singleton: `#[singleton(watch)]`.
+    Clone,
+    /// A projection of the singleton, created with `From<&Singleton>`:
+    /// `#[singleton(watch = "Type")]`.
+    Projection(Box<Type>),
+}
+
+impl SingletonWatch {
+    /// Get the type of the value published to subscribers.
+    pub fn to_watched_type(&self, inner: impl ToTokens) -> TokenStream {
+        match self {
+            Self::Clone => quote! { #inner },
+            Self::Projection(ty) => quote! { #ty },
+        }
+    }
+
+    /// Create the type of the sender that publishes the value.
+    pub fn to_sender_type(&self, inner: impl ToTokens) -> TokenStream {
+        let blockz = paths::blockz_path();
+        let watched = self.to_watched_type(inner);
+        quote! { #blockz::singleton::watch::Sender<Option<#watched>> }
+    }
+
+    /// Create the statement that publishes a new value of the singleton, from an expression that
+    /// is a reference to it.
+    pub fn to_publish(
+        &self,
+        sender: &Ident,
+        inner: impl ToTokens,
+        value: TokenStream,
+    ) -> TokenStream {
+        let watched = match self {
+            Self::Clone => quote! { <#inner as Clone>::clone(#value) },
+            Self::Projection(ty) => quote! { <#ty as From<&#inner>>::from(#value) },
+        };
+        quote! { #sender.send_replace(Some(#watched)); }
+    }
+
+    /// Create the statement that publishes that the singleton holds no value.
+    pub fn to_publish_none(&self, sender: &Ident) -> TokenStream {
+        quote! { #sender.send_replace(None); }
+    }
+}
+
+impl FromMeta for SingletonWatch {
+    fn from_word() -> darling::Result<Self> {
+        Ok(Self::Clone)
+    }
+
+    fn from_string(value: &str) -> darling::Result<Self> {
+        syn::parse_str(value)
+            .map(|ty| Self::Projection(Box::new(ty)))
+            .map_err(|err| {
+                darling::Error::custom(format!("invalid watch type '{}': {}", value, err))
+            })
+    }
+}
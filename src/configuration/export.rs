@@ -0,0 +1,228 @@
+//! Serialize loaded configurations back to environment variables or configuration files.
+//!
+//! This is useful for passing the configuration of a process to subprocesses and sidecars, or for
+//! dumping the effective configuration (for example in support bundles).
+//!
+//! Fields of type [Secret] are serialized according to a [SecretPolicy]. Omitted secrets are left
+//! out of env vars and TOML files, but JSON files keep them as null fields unless they are
+//! annotated with `#[serde(skip_serializing_if = "Secret::is_omitted")]`.
+//!
+//! # Example
+//!
+//! ```
+//! # #[cfg(all(feature = "configuration_export", feature = "env_configuration"))]
+//! # {
+//! # use blockz::prelude::*;
+//! # use serde::Deserialize;
+//! # use serde::Serialize;
+//! use blockz::configuration::export;
+//! use blockz::configuration::export::FileFormat;
+//! use blockz::configuration::types::Secret;
+//! use blockz::configuration::types::SecretPolicy;
+//!
+//! #[derive(Configuration, Deserialize, Serialize)]
+//! #[configuration(env(prefix = "EXPORT_APP_"))]
+//! struct DbConfig {
+//!     db_host: String,
+//!     db_port: u16,
+//!     db_pass: Secret<String>,
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! # std::env::set_var("EXPORT_APP_DB_HOST", "localhost");
+//! # std::env::set_var("EXPORT_APP_DB_PORT", "5432");
+//! # std::env::set_var("EXPORT_APP_DB_PASS", "NotASafePassword42");
+//! let config = <DbConfig as EasyConfiguration>::load()
+//!     .await
+//!     .expect("Failed to load configuration from the environment!");
+//!
+//! // the variables needed by a worker subprocess
+//! let vars = export::to_env_vars(&config, Some("EXPORT_APP_"), SecretPolicy::Include).unwrap();
+//! # assert_eq!(vars[0], ("EXPORT_APP_DB_HOST".to_string(), "localhost".to_string()));
+//! # assert_eq!(vars[1], ("EXPORT_APP_DB_PORT".to_string(), "5432".to_string()));
+//! # assert_eq!(vars[2], ("EXPORT_APP_DB_PASS".to_string(), "NotASafePassword42".to_string()));
+//! let mut worker = std::process::Command::new("worker");
+//! worker.envs(vars);
+//!
+//! // the effective configuration, for a support bundle
+//! let dump = export::to_string(&config, FileFormat::Toml, SecretPolicy::Redact).unwrap();
+//! # assert_eq!(dump, "db_host = \"localhost\"\ndb_port = 5432\ndb_pass = \"[REDACTED]\"\n");
+//! println!("{}", dump);
+//! // db_host = "localhost"
+//! // db_port = 5432
+//! // db_pass = "[REDACTED]"
+//! # }
+//! # main();
+//! # }
+//! # #[cfg(not(all(feature = "configuration_export", feature = "env_configuration")))]
+//! # fn main() {}
+//! ```
+//!
+//! [Secret]: super::types::Secret
+
+use serde::Serialize;
+
+use serde_json::Value;
+
+use std::error::Error;
+use std::fmt;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::types::SecretPolicy;
+
+/// Configuration file formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FileFormat {
+    /// TOML.
+    Toml,
+    /// JSON (pretty-printed).
+    Json,
+}
+
+/// Errors produced when exporting a configuration.
+#[derive(Debug)]
+pub enum ExportError {
+    /// The configuration could not be serialized.
+    Serialize(String),
+    /// The configuration is not a struct (or a map), so it can't be exported as variables.
+    NotAStruct,
+    /// A field contains a value that can't be represented by an environment variable (such as a
+    /// nested struct, or a sequence with an item that contains a comma).
+    UnsupportedValue(String),
+    /// The configuration file could not be written.
+    Io(io::Error),
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Serialize(err) => write!(f, "failed to serialize the configuration: {}", err),
+            Self::NotAStruct => write!(f, "only structs can be exported as env vars"),
+            Self::UnsupportedValue(field) => write!(
+                f,
+                "field {} contains a value that can't be exported as an env var",
+                field
+            ),
+            Self::Io(err) => write!(f, "failed to write the configuration file: {}", err),
+        }
+    }
+}
+
+impl Error for ExportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Serialize the configuration to the environment variables that an env configuration with the
+/// same prefix would read.
+///
+/// Sequences are joined with commas and fields set to None are left out. Sequence items can't
+/// contain commas, since there is no way to escape them.
+pub fn to_env_vars<T>(
+    config: &T,
+    prefix: Option<&str>,
+    policy: SecretPolicy,
+) -> Result<Vec<(String, String)>, ExportError>
+where
+    T: Serialize,
+{
+    let fields = match to_value(config, policy)? {
+        Value::Object(fields) => fields,
+        _ => return Err(ExportError::NotAStruct),
+    };
+    let prefix = prefix.unwrap_or_default();
+    let mut vars = Vec::with_capacity(fields.len());
+    for (name, value) in fields {
+        let value = match value {
+            Value::Null => continue,
+            Value::Array(items) => items
+                .into_iter()
+                .map(scalar_to_string)
+                .map(|item| item.filter(|item| !item.contains(',')))
+                .collect::<Option<Vec<String>>>()
+                .map(|items| items.join(",")),
+            other => scalar_to_string(other),
+        };
+        match value {
+            Some(value) => vars.push((format!("{}{}", prefix, name.to_uppercase()), value)),
+            None => return Err(ExportError::UnsupportedValue(name)),
+        }
+    }
+    Ok(vars)
+}
+
+/// Serialize the configuration to a string in the given file format.
+pub fn to_string<T>(
+    config: &T,
+    format: FileFormat,
+    policy: SecretPolicy,
+) -> Result<String, ExportError>
+where
+    T: Serialize,
+{
+    let value = to_value(config, policy)?;
+    match format {
+        FileFormat::Toml => toml::to_string(&without_nulls(value))
+            .map_err(|err| ExportError::Serialize(err.to_string())),
+        FileFormat::Json => serde_json::to_string_pretty(&value)
+            .map_err(|err| ExportError::Serialize(err.to_string())),
+    }
+}
+
+/// Serialize the configuration to a file in the given file format.
+pub fn to_file<T, P>(
+    config: &T,
+    path: P,
+    format: FileFormat,
+    policy: SecretPolicy,
+) -> Result<(), ExportError>
+where
+    T: Serialize,
+    P: AsRef<Path>,
+{
+    let contents = to_string(config, format, policy)?;
+    fs::write(path, contents).map_err(ExportError::Io)
+}
+
+/// Serialize the configuration to a json value, applying the secret policy.
+fn to_value<T>(config: &T, policy: SecretPolicy) -> Result<Value, ExportError>
+where
+    T: Serialize,
+{
+    policy
+        .apply(|| serde_json::to_value(config))
+        .map_err(|err| ExportError::Serialize(err.to_string()))
+}
+
+/// Remove the null values from the objects contained by a json value (TOML has no nulls).
+fn without_nulls(value: Value) -> Value {
+    match value {
+        Value::Array(items) => Value::Array(items.into_iter().map(without_nulls).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(name, value)| (name, without_nulls(value)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// Convert a scalar json value to the string an env var would contain.
+fn scalar_to_string(value: Value) -> Option<String> {
+    match value {
+        Value::String(string) => Some(string),
+        Value::Bool(boolean) => Some(boolean.to_string()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
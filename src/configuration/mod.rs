@@ -0,0 +1,603 @@
+//! Support for common configuration behaviour and loading them from various sources.
+//!
+//! # Direct configuration example
+//!
+//! This example shows how to use the direct configuration. Please note that the direct
+//! configuration is usually useful in testing scenarios. Production usage is discouraged.
+//!
+//! ```
+//! # #[cfg(feature = "configuration")]
+//! # {
+//! # use blockz::prelude::*;
+//! #[derive(Configuration)]
+//! #[configuration(direct)]
+//! struct ServerConfig {
+//!     address: String,
+//!     port: u16,
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let config = <ServerConfig as Configuration>::load(ServerConfig {
+//!     address: "127.0.0.1".to_string(),
+//!     port: 58732,
+//! }).await;
+//! # assert_eq!(config.address.as_str(), "127.0.0.1");
+//! # assert_eq!(config.port, 58732);
+//! println!("Server binding to {}:{}.", config.address, config.port);
+//! // Server binding to 127.0.0.1:58732.
+//! # }
+//! # }
+//! # #[cfg(not(feature = "configuration"))]
+//! # fn main() {}
+//! ```
+//!
+//! If the configuration type implements Default, you can use EasyConfiguration to load the default
+//! value, like so:
+//!
+//! ```
+//! # use blockz::prelude::*;
+//! #[derive(Configuration)]
+//! #[configuration(direct)]
+//! struct ServerConfig {
+//!     address: String,
+//!     port: u16,
+//! }
+//!
+//! impl Default for ServerConfig {
+//!     fn default() -> ServerConfig {
+//!         ServerConfig {
+//!             address: "0.0.0.0".to_string(),
+//!             port: 9999,
+//!         }
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let config = <ServerConfig as EasyConfiguration>::load().await;
+//! # assert_eq!(config.address.as_str(), "0.0.0.0");
+//! # assert_eq!(config.port, 9999);
+//! println!("Server binding to {}:{}.", config.address, config.port);
+//! // Server binding to 0.0.0.0:9999.
+//! # }
+//! ```
+//!
+//! # Env configuration examples
+//!
+//! **NOTE**: These examples require the __env_configuration__ feature.
+//!
+//! The first example shows how to use the env configuration with a prefix.
+//!
+//! ```
+//! # #[cfg(feature = "env_configuration")]
+//! # {
+//! # use blockz::prelude::*;
+//! # use serde::Deserialize;
+//! #[derive(Configuration, Deserialize)]
+//! #[configuration(env(prefix = "COOL_APP_"))]
+//! struct ServerConfig {
+//!     #[serde(rename = "bind_addr")]
+//!     address: String,
+//!     #[serde(rename = "bind_port")]
+//!     port: u16,
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! # std::env::set_var("COOL_APP_BIND_ADDR", "0.0.0.0");
+//! # std::env::set_var("COOL_APP_BIND_PORT", "58732");
+//! let config = <ServerConfig as EasyConfiguration>::load()
+//!     .await
+//!     .expect("Failed to load configuration from the environment!");
+//! # assert_eq!(config.address.as_str(), "0.0.0.0");
+//! # assert_eq!(config.port, 58732);
+//! println!("Server binding to {}:{}.", config.address, config.port);
+//! // Server binding to aaa.bbb.ccc.ddd:ppppp.
+//! # }
+//! # }
+//! # #[cfg(not(feature = "env_configuration"))]
+//! # fn main() {}
+//! ```
+//!
+//! The second example shows how to use the env configuration with a prefix source in the form of a
+//! constant.
+//!
+//! ```
+//! # #[cfg(feature = "env_configuration")]
+//! # {
+//! # use blockz::prelude::*;
+//! # use serde::Deserialize;
+//! /// This is our prefix.
+//! const PREFIX: &str = "COOL_APP_";
+//!
+//! #[derive(Configuration, Deserialize)]
+//! #[configuration(env(prefix_source = "PREFIX.to_string()"))]
+//! struct ServerConfig {
+//!     #[serde(rename = "bind_addr")]
+//!     address: String,
+//!     #[serde(rename = "bind_port")]
+//!     port: u16,
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! # std::env::set_var("COOL_APP_BIND_ADDR", "0.0.0.0");
+//! # std::env::set_var("COOL_APP_BIND_PORT", "58732");
+//! let config = <ServerConfig as EasyConfiguration>::load()
+//!     .await
+//!     .expect("Failed to load configuration from the environment!");
+//! # assert_eq!(config.address.as_str(), "0.0.0.0");
+//! # assert_eq!(config.port, 58732);
+//! println!("Server binding to {}:{}.", config.address, config.port);
+//! // Server binding to aaa.bbb.ccc.ddd:ppppp.
+//! # }
+//! # }
+//! # #[cfg(not(feature = "env_configuration"))]
+//! # fn main() {}
+//! ```
+//!
+//! The third example shows how to use the env configuration with a prefix source in the form of a
+//! function that can return an error. The error must be mapped to an `envy::Error`.
+//!
+//! ```
+//! # #[cfg(feature = "env_configuration")]
+//! # {
+//! # use blockz::prelude::*;
+//! # use serde::Deserialize;
+//! /// This function sources our prefix.
+//! fn source_prefix() -> Result<String, envy::Error> {
+//!     std::env::var("COOL_APP_PREFIX")
+//!         .map_err(|err| envy::Error::Custom(format!("failed to source the prefix: {}", err)))
+//! }
+//!
+//! #[derive(Configuration, Deserialize)]
+//! #[configuration(env(prefix_source = "source_prefix()?"))]
+//! struct ServerConfig {
+//!     #[serde(rename = "bind_addr")]
+//!     address: String,
+//!     #[serde(rename = "bind_port")]
+//!     port: u16,
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! # std::env::set_var("COOL_APP_PREFIX", "COOL_APP_");
+//! # std::env::set_var("COOL_APP_BIND_ADDR", "0.0.0.0");
+//! # std::env::set_var("COOL_APP_BIND_PORT", "58732");
+//! let config = <ServerConfig as EasyConfiguration>::load()
+//!     .await
+//!     .expect("Failed to load configuration from the environment!");
+//! # assert_eq!(config.address.as_str(), "0.0.0.0");
+//! # assert_eq!(config.port, 58732);
+//! println!("Server binding to {}:{}.", config.address, config.port);
+//! // Server binding to aaa.bbb.ccc.ddd:ppppp.
+//! # }
+//! # }
+//! # #[cfg(not(feature = "env_configuration"))]
+//! # fn main() {}
+//! ```
+//!
+//! # Enum configurations
+//!
+//! Env configurations can select between variants of an enum, which is useful for pluggable
+//! backends. The `{prefix}KIND` variable selects the variant and only the fields of that variant
+//! are read, from the variables prefixed with `{prefix}{VARIANT}_`.
+//!
+//! ```
+//! # #[cfg(feature = "env_configuration")]
+//! # {
+//! # use blockz::prelude::*;
+//! # use serde::Deserialize;
+//! #[derive(Deserialize)]
+//! struct LocalStorage {
+//!     path: String,
+//! }
+//!
+//! #[derive(Configuration)]
+//! #[configuration(env(prefix = "STORAGE_"))]
+//! enum StorageConfig {
+//!     S3 {
+//!         bucket: String,
+//!         #[serde(default)]
+//!         region: Option<String>,
+//!     },
+//!     Local(LocalStorage),
+//!     #[configuration(rename = "mem")]
+//!     InMemory,
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! # std::env::set_var("STORAGE_KIND", "s3");
+//! # std::env::set_var("STORAGE_S3_BUCKET", "my-bucket");
+//! // STORAGE_KIND=s3 STORAGE_S3_BUCKET=my-bucket
+//! let config = <StorageConfig as EasyConfiguration>::load()
+//!     .await
+//!     .expect("Failed to load configuration from the environment!");
+//! # assert!(matches!(&config, StorageConfig::S3 { bucket, region: None } if bucket == "my-bucket"));
+//! match config {
+//!     StorageConfig::S3 { bucket, .. } => println!("Storing in s3://{}.", bucket),
+//!     StorageConfig::Local(local) => println!("Storing in {}.", local.path),
+//!     StorageConfig::InMemory => println!("Storing in memory."),
+//! }
+//! // Storing in s3://my-bucket.
+//! # }
+//! # main();
+//! # }
+//! # #[cfg(not(feature = "env_configuration"))]
+//! # fn main() {}
+//! ```
+//!
+//! # Global configurations
+//!
+//! Configurations are usually loaded once and then read everywhere. Deriving a configuration with
+//! the `global` option generates `init_global()`, which loads the configuration and stores it in a
+//! static, and `global()`, which returns a `&'static` reference to it without any locking. The
+//! configuration is loaded once, even if `init_global()` is called concurrently, and a failed load
+//! can be retried (see [GlobalConfiguration]).
+//!
+//! ```
+//! # use blockz::prelude::*;
+//! #[derive(Configuration, Default)]
+//! #[configuration(direct, global)]
+//! struct ServerConfig {
+//!     port: u16,
+//! }
+//!
+//! fn port() -> u16 {
+//!     ServerConfig::global().port
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! ServerConfig::init_global().await.unwrap();
+//! # assert_eq!(port(), 0);
+//! println!("Server binding to port {}.", port());
+//! // Server binding to port 0.
+//! # }
+//! # main();
+//! ```
+//!
+//! # Blocking examples
+//!
+//! Derived configurations can also be loaded without an async runtime, for example to size the
+//! runtime from the configuration.
+//!
+//! ```
+//! # use blockz::prelude::*;
+//! #[derive(Configuration, Default)]
+//! #[configuration(direct)]
+//! struct RuntimeConfig {
+//!     worker_threads: usize,
+//! }
+//!
+//! fn main() {
+//!     let config = <RuntimeConfig as EasyBlockingConfiguration>::load_blocking();
+//! #   assert_eq!(config.worker_threads, 0);
+//!     let runtime = tokio::runtime::Builder::new_multi_thread()
+//!         .worker_threads(config.worker_threads.max(1))
+//!         .build()
+//!         .unwrap();
+//!     // runtime.block_on(async_main(config));
+//! }
+//! ```
+//!
+//! Env configurations whose `prefix_source` awaits something only implement [Configuration]. These,
+//! like other async-only configuration sources, can be loaded with [block_on], which does not
+//! require a runtime either:
+//!
+//! ```
+//! # use blockz::prelude::*;
+//! # #[derive(Configuration, Default)]
+//! # #[configuration(direct)]
+//! # struct RuntimeConfig {
+//! #     worker_threads: usize,
+//! # }
+//! let config = blockz::configuration::block_on(<RuntimeConfig as EasyConfiguration>::load());
+//! # assert_eq!(config.worker_threads, 0);
+//! ```
+//!
+//! # Typed values
+//!
+//! **NOTE**: These require the __configuration_types__ feature.
+//!
+//! The [types] module contains types for common configuration values, such as durations, byte
+//! sizes and socket addresses.
+//!
+//! # Exporting configurations
+//!
+//! **NOTE**: This requires the __configuration_export__ feature.
+//!
+//! The [export] module serializes loaded configurations back to environment variables, TOML or
+//! JSON.
+
+#[cfg(feature = "configuration_export")]
+#[cfg_attr(docsrs, doc(cfg(feature = "configuration_export")))]
+pub mod export;
+
+#[cfg(feature = "configuration_types")]
+#[cfg_attr(docsrs, doc(cfg(feature = "configuration_types")))]
+pub mod types;
+
+#[cfg(feature = "env_configuration")]
+use serde::Deserialize;
+
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Wake;
+use std::task::Waker;
+use std::thread;
+use std::thread::Thread;
+
+/// Marker that precedes the offending value in value parse error messages.
+///
+/// This matches the format used by envy for primitive values.
+#[cfg(any(feature = "env_configuration", feature = "configuration_types"))]
+const VALUE_MARKER: &str = " while parsing value '";
+
+/// Common behaviour of configurations.
+#[async_trait::async_trait]
+pub trait Configuration {
+    /// The type of options container this Configuration accepts for the purpose of loading the
+    /// configuration.
+    type Opts: Send;
+
+    /// The result type that can be produced by loading the configuration.
+    type Result: Send;
+
+    /// Load the configuration.
+    async fn load(opts: Self::Opts) -> Self::Result;
+}
+
+/// An easy configuration is a configuration that can be loaded without other parameters.
+#[async_trait::async_trait]
+pub trait EasyConfiguration {
+    /// The result type that can be produced by loading the configuration.
+    type Result: Send;
+
+    /// Load the configuration.
+    async fn load() -> Self::Result;
+}
+
+/// Automatically implement EasyConfiguration on Configuration implementations whose Opts type is
+/// Default.
+#[async_trait::async_trait]
+impl<C, O, R> EasyConfiguration for C
+where
+    C: Configuration<Opts = O, Result = R>,
+    O: Default + Send + 'static,
+    R: Send + 'static,
+{
+    type Result = R;
+
+    async fn load() -> Self::Result {
+        C::load(O::default()).await
+    }
+}
+
+/// Configurations that can be loaded synchronously, without an async runtime.
+pub trait BlockingConfiguration: Configuration {
+    /// Load the configuration, blocking the current thread.
+    fn load_blocking(opts: Self::Opts) -> Self::Result;
+}
+
+/// An easy blocking configuration is a blocking configuration that can be loaded without other
+/// parameters.
+pub trait EasyBlockingConfiguration {
+    /// The result type that can be produced by loading the configuration.
+    type Result;
+
+    /// Load the configuration, blocking the current thread.
+    fn load_blocking() -> Self::Result;
+}
+
+/// Automatically implement EasyBlockingConfiguration on BlockingConfiguration implementations
+/// whose Opts type is Default.
+impl<C, O, R> EasyBlockingConfiguration for C
+where
+    C: BlockingConfiguration<Opts = O, Result = R>,
+    O: Default + Send,
+    R: Send,
+{
+    type Result = R;
+
+    fn load_blocking() -> Self::Result {
+        C::load_blocking(O::default())
+    }
+}
+
+/// Run a future to completion on the current thread.
+///
+/// This does not require (nor start) an async runtime, so it can be used for loading
+/// configurations before building one. Futures that depend on a specific runtime (such as those
+/// doing tokio I/O) will not work.
+pub fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    /// Waker that unparks the blocked thread.
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// The static that holds a global configuration (`#[configuration(global)]`).
+///
+/// The first caller of [init](GlobalConfiguration::init) loads the configuration, while the
+/// concurrent callers wait for that load instead of running their own. A load that fails leaves
+/// the global configuration uninitialized, so the next caller loads it again.
+pub struct GlobalConfiguration<T> {
+    cell: async_lock::OnceCell<T>,
+}
+
+impl<T> GlobalConfiguration<T> {
+    /// Create an uninitialized global configuration.
+    pub const fn new() -> Self {
+        Self {
+            cell: async_lock::OnceCell::new(),
+        }
+    }
+
+    /// Get the global configuration, loading it if it was not initialized.
+    pub async fn init<E, F>(&self, load: impl FnOnce() -> F) -> Result<&T, E>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        self.cell.get_or_try_init(load).await
+    }
+
+    /// Get the global configuration, if it was initialized.
+    pub fn get(&self) -> Option<&T> {
+        self.cell.get()
+    }
+}
+
+impl<T> Default for GlobalConfiguration<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Direct configuration that just returns the passed value.
+pub struct DirectConfiguration<T>
+where
+    T: Send,
+{
+    _phantom: PhantomData<T>,
+}
+
+#[async_trait::async_trait]
+impl<T> Configuration for DirectConfiguration<T>
+where
+    T: Send,
+{
+    type Opts = T;
+    type Result = T;
+
+    async fn load(opts: Self::Opts) -> Self::Result {
+        opts
+    }
+}
+
+impl<T> BlockingConfiguration for DirectConfiguration<T>
+where
+    T: Send,
+{
+    fn load_blocking(opts: Self::Opts) -> Self::Result {
+        opts
+    }
+}
+
+/// Configuration that can be sourced from environment variables.
+#[cfg(feature = "env_configuration")]
+#[cfg_attr(docsrs, doc(cfg(feature = "env_configuration")))]
+pub struct EnvConfiguration<T>
+where
+    T: for<'de> Deserialize<'de> + Send,
+{
+    _phantom_t: PhantomData<T>,
+}
+
+#[cfg(feature = "env_configuration")]
+#[cfg_attr(docsrs, doc(cfg(feature = "env_configuration")))]
+#[async_trait::async_trait]
+impl<T> Configuration for EnvConfiguration<T>
+where
+    T: for<'de> Deserialize<'de> + Send,
+{
+    type Opts = Option<String>;
+    type Result = Result<T, envy::Error>;
+
+    async fn load(opts: Self::Opts) -> Self::Result {
+        <Self as BlockingConfiguration>::load_blocking(opts)
+    }
+}
+
+#[cfg(feature = "env_configuration")]
+#[cfg_attr(docsrs, doc(cfg(feature = "env_configuration")))]
+impl<T> BlockingConfiguration for EnvConfiguration<T>
+where
+    T: for<'de> Deserialize<'de> + Send,
+{
+    fn load_blocking(opts: Self::Opts) -> Self::Result {
+        // variables that are not valid unicode can not be configuration values, skip them instead
+        // of panicking like std::env::vars() does
+        let vars: Vec<(String, String)> = std::env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .collect();
+        let prefix = opts.as_deref().unwrap_or_default();
+        let tracked: TrackedValue<T> = envy::prefixed(prefix).from_iter(vars.iter().cloned())?;
+        tracked.0.map_err(|(key, msg)| {
+            // envy strips the prefix and lowercases the variable names to get the keys
+            let var = vars.iter().map(|(name, _)| name).find(|name| {
+                name.starts_with(prefix) && name[prefix.len()..].to_lowercase() == key
+            });
+            match var {
+                Some(name) => envy::Error::Custom(format!("{} provided by {}", msg, name)),
+                None => envy::Error::Custom(msg),
+            }
+        })
+    }
+}
+
+/// Value deserialized from environment variables that keeps track of which key provided an
+/// invalid value.
+///
+/// envy adds the name of the variable to error messages by itself for primitive values, but
+/// custom deserializers (such as the ones in [types]) have no access to it. Value parse errors are
+/// returned as the key and the error message so that the variable name can be added later.
+#[cfg(feature = "env_configuration")]
+struct TrackedValue<T>(Result<T, (String, String)>);
+
+#[cfg(feature = "env_configuration")]
+impl<'de, T> Deserialize<'de> for TrackedValue<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mut track = serde_path_to_error::Track::new();
+        let err = match T::deserialize(serde_path_to_error::Deserializer::new(
+            deserializer,
+            &mut track,
+        )) {
+            Ok(value) => return Ok(Self(Ok(value))),
+            Err(err) => err,
+        };
+        let msg = err.to_string();
+        let key = track
+            .path()
+            .iter()
+            .next_back()
+            .and_then(|segment| match segment {
+                serde_path_to_error::Segment::Map { key } => Some(key.clone()),
+                _ => None,
+            });
+        match key {
+            Some(key) if msg.ends_with('\'') && msg.contains(VALUE_MARKER) => {
+                Ok(Self(Err((key, msg))))
+            }
+            _ => Err(err),
+        }
+    }
+}
@@ -0,0 +1,929 @@
+//! Typed configuration values.
+//!
+//! These types can be used as fields of configurations and they deserialize both from strings (as
+//! provided by environment variables) and from the native values found in configuration files.
+//!
+//! | Type               | Example values                                 |
+//! |--------------------|------------------------------------------------|
+//! | [Duration]         | `"30s"`, `"1m30s"`, `"250ms"`, `"2h"`, `30`      |
+//! | [ByteSize]         | `"512MiB"`, `"1GB"`, `"1.5KiB"`, `1024`          |
+//! | [SocketAddrList]   | `"0.0.0.0:8080,[::]:8080"`                     |
+//! | [Url]              | `"postgres://user@localhost:5432/db"`          |
+//! | [LogLevel]         | `"info"`, `"WARN"`, `"off"`                    |
+//! | [Percent]          | `"75%"`, `"12.5"`, `50`                          |
+//! | [Cidr]             | `"10.0.0.0/8"`, `"fd00::/8"`, `"127.0.0.1"`      |
+//! | [Secret]           | any value that must not be leaked              |
+//!
+//! Parse errors mention the offending value. When loading an env configuration, the name of the
+//! environment variable that provided the value is added to the error message as well.
+//!
+//! # Example
+//!
+//! ```
+//! # #[cfg(all(feature = "configuration_types", feature = "env_configuration"))]
+//! # {
+//! # use blockz::prelude::*;
+//! # use serde::Deserialize;
+//! use blockz::configuration::types::ByteSize;
+//! use blockz::configuration::types::Duration;
+//! use blockz::configuration::types::SocketAddrList;
+//!
+//! #[derive(Configuration, Deserialize)]
+//! #[configuration(env(prefix = "TYPES_APP_"))]
+//! struct ServerConfig {
+//!     bind: SocketAddrList,
+//!     request_timeout: Duration,
+//!     max_body_size: ByteSize,
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! # std::env::set_var("TYPES_APP_BIND", "0.0.0.0:8080,[::]:8080");
+//! # std::env::set_var("TYPES_APP_REQUEST_TIMEOUT", "1m30s");
+//! # std::env::set_var("TYPES_APP_MAX_BODY_SIZE", "512MiB");
+//! let config = <ServerConfig as EasyConfiguration>::load()
+//!     .await
+//!     .expect("Failed to load configuration from the environment!");
+//! # assert_eq!(config.bind.len(), 2);
+//! # assert_eq!(config.request_timeout.as_secs(), 90);
+//! # assert_eq!(config.max_body_size.as_u64(), 512 * 1024 * 1024);
+//! println!("Request timeout: {}.", config.request_timeout); // 1m30s.
+//! println!("Max body size: {}.", config.max_body_size); // 512MiB.
+//! # std::env::set_var("TYPES_APP_REQUEST_TIMEOUT", "30x");
+//! # let err = <ServerConfig as EasyConfiguration>::load().await.err().unwrap();
+//! # assert!(err.to_string().ends_with("provided by TYPES_APP_REQUEST_TIMEOUT"), "{}", err);
+//! # }
+//! # main();
+//! # }
+//! # #[cfg(not(all(feature = "configuration_types", feature = "env_configuration")))]
+//! # fn main() {}
+//! ```
+
+use serde::de;
+use serde::de::Visitor;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use super::VALUE_MARKER;
+
+/// Error produced when a configuration value could not be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    kind: &'static str,
+    value: String,
+    reason: String,
+}
+
+impl ParseError {
+    /// Create a new parse error.
+    fn new(kind: &'static str, value: &str, reason: impl Display) -> Self {
+        Self {
+            kind,
+            value: value.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// The value that could not be parsed.
+    pub fn value(&self) -> &str {
+        self.value.as_str()
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid {}: {}{}{}'",
+            self.kind, self.reason, VALUE_MARKER, self.value
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+/// Common behaviour of the typed configuration values.
+trait ConfigValue: FromStr<Err = ParseError> {
+    /// What the value is expected to be (used by serde error messages).
+    const EXPECTING: &'static str;
+}
+
+/// Visitor that parses a configuration value from strings and numbers alike.
+struct ConfigValueVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for ConfigValueVisitor<T>
+where
+    T: ConfigValue,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(T::EXPECTING)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<T, E> {
+        self.visit_str(v.to_string().as_str())
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<T, E> {
+        self.visit_str(v.to_string().as_str())
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<T, E> {
+        self.visit_str(v.to_string().as_str())
+    }
+}
+
+/// Implement Serialize (through Display) and Deserialize (through ConfigValue) for a type.
+macro_rules! impl_serde {
+    ($type: ty) => {
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_any(ConfigValueVisitor(PhantomData))
+            }
+        }
+    };
+}
+
+/// Split a string into a decimal number and the suffix that follows it.
+fn split_number(src: &str) -> (&str, &str) {
+    let end = src
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(src.len());
+    src.split_at(end)
+}
+
+/// A duration, such as `"30s"`, `"1m30s"` or `"250ms"`.
+///
+/// Supported units: `ns`, `us`, `ms`, `s`, `m`, `h` and `d`. Multiple components may be combined
+/// (`"1h30m"`) and a number without a unit is interpreted as seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration(std::time::Duration);
+
+impl Duration {
+    /// Get the inner std duration.
+    pub fn into_inner(self) -> std::time::Duration {
+        self.0
+    }
+
+    /// Parse a single duration component into nanoseconds.
+    fn parse_component(number: &str, unit: &str) -> Result<f64, String> {
+        let number: f64 = number
+            .parse()
+            .map_err(|_| format!("'{}' is not a number", number))?;
+        let multiplier = match unit {
+            "ns" => 1.0,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "" | "s" | "sec" | "secs" => 1e9,
+            "m" | "min" | "mins" => 60e9,
+            "h" | "hr" | "hrs" => 3_600e9,
+            "d" | "day" | "days" => 86_400e9,
+            other => {
+                return Err(format!(
+                    "unknown unit '{}' (expected one of ns, us, ms, s, m, h, d)",
+                    other
+                ))
+            }
+        };
+        Ok(number * multiplier)
+    }
+}
+
+impl FromStr for Duration {
+    type Err = ParseError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let err = |reason| ParseError::new("duration", src, reason);
+        let mut rest = src.trim();
+        if rest.is_empty() {
+            return Err(err("empty duration".to_string()));
+        }
+        let mut nanos = 0f64;
+        while !rest.is_empty() {
+            let (number, tail) = split_number(rest);
+            if number.is_empty() {
+                return Err(err(format!("expected a number, found '{}'", tail)));
+            }
+            let unit_len = tail
+                .find(|c: char| c.is_ascii_digit() || c.is_whitespace())
+                .unwrap_or(tail.len());
+            let (unit, tail) = tail.split_at(unit_len);
+            nanos += Self::parse_component(number, unit).map_err(err)?;
+            rest = tail.trim_start();
+        }
+        if nanos > u64::MAX as f64 {
+            return Err(err("duration is too large".to_string()));
+        }
+        Ok(Self(std::time::Duration::from_nanos(nanos.round() as u64)))
+    }
+}
+
+impl Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [(&str, u128); 7] = [
+            ("d", 86_400_000_000_000),
+            ("h", 3_600_000_000_000),
+            ("m", 60_000_000_000),
+            ("s", 1_000_000_000),
+            ("ms", 1_000_000),
+            ("us", 1_000),
+            ("ns", 1),
+        ];
+        let mut nanos = self.0.as_nanos();
+        if nanos == 0 {
+            return f.write_str("0s");
+        }
+        for (unit, size) in UNITS.iter() {
+            if nanos >= *size {
+                write!(f, "{}{}", nanos / size, unit)?;
+                nanos %= size;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Deref for Duration {
+    type Target = std::time::Duration;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<std::time::Duration> for Duration {
+    fn from(src: std::time::Duration) -> Self {
+        Self(src)
+    }
+}
+
+impl From<Duration> for std::time::Duration {
+    fn from(src: Duration) -> Self {
+        src.0
+    }
+}
+
+impl ConfigValue for Duration {
+    const EXPECTING: &'static str = "a duration such as \"30s\" or a number of seconds";
+}
+
+impl_serde!(Duration);
+
+/// A size in bytes, such as `"512MiB"` or `"1GB"`.
+///
+/// Both decimal (`KB`, `MB`, `GB`, `TB`, `PB`) and binary (`KiB`, `MiB`, `GiB`, `TiB`, `PiB`) units
+/// are supported, case-insensitively. A number without a unit is interpreted as bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    /// Decimal units.
+    const DECIMAL_UNITS: [(&'static str, u64); 5] = [
+        ("PB", 1_000_000_000_000_000),
+        ("TB", 1_000_000_000_000),
+        ("GB", 1_000_000_000),
+        ("MB", 1_000_000),
+        ("KB", 1_000),
+    ];
+
+    /// Binary units.
+    const BINARY_UNITS: [(&'static str, u64); 5] = [
+        ("PiB", 1 << 50),
+        ("TiB", 1 << 40),
+        ("GiB", 1 << 30),
+        ("MiB", 1 << 20),
+        ("KiB", 1 << 10),
+    ];
+
+    /// Create a new byte size.
+    pub const fn new(bytes: u64) -> Self {
+        Self(bytes)
+    }
+
+    /// Get the size in bytes.
+    pub const fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    /// Get the multiplier for a unit.
+    fn unit_multiplier(unit: &str) -> Option<u64> {
+        if unit.is_empty() || unit.eq_ignore_ascii_case("b") {
+            return Some(1);
+        }
+        Self::DECIMAL_UNITS
+            .iter()
+            .chain(Self::BINARY_UNITS.iter())
+            .find(|(name, _)| name.eq_ignore_ascii_case(unit))
+            .map(|(_, multiplier)| *multiplier)
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = ParseError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let err = |reason: String| ParseError::new("byte size", src, reason);
+        let (number, unit) = split_number(src.trim());
+        if number.is_empty() {
+            return Err(err("expected a number".to_string()));
+        }
+        let unit = unit.trim();
+        let multiplier = Self::unit_multiplier(unit).ok_or_else(|| {
+            err(format!(
+                "unknown unit '{}' (expected one of B, KB, MB, GB, TB, PB, KiB, MiB, GiB, TiB, PiB)",
+                unit
+            ))
+        })?;
+        let bytes = if let Ok(integer) = number.parse::<u64>() {
+            integer.checked_mul(multiplier)
+        } else {
+            let float: f64 = number
+                .parse()
+                .map_err(|_| err(format!("'{}' is not a number", number)))?;
+            let bytes = (float * multiplier as f64).round();
+            if bytes > u64::MAX as f64 {
+                None
+            } else {
+                Some(bytes as u64)
+            }
+        };
+        bytes
+            .map(Self)
+            .ok_or_else(|| err("byte size is too large".to_string()))
+    }
+}
+
+impl Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = Self::BINARY_UNITS
+            .iter()
+            .chain(Self::DECIMAL_UNITS.iter())
+            .find(|(_, size)| self.0 != 0 && self.0.is_multiple_of(*size));
+        match unit {
+            Some((name, size)) => write!(f, "{}{}", self.0 / size, name),
+            None => write!(f, "{}B", self.0),
+        }
+    }
+}
+
+impl From<u64> for ByteSize {
+    fn from(src: u64) -> Self {
+        Self(src)
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(src: ByteSize) -> Self {
+        src.0
+    }
+}
+
+impl ConfigValue for ByteSize {
+    const EXPECTING: &'static str = "a byte size such as \"512MiB\" or a number of bytes";
+}
+
+impl_serde!(ByteSize);
+
+/// A non-empty list of socket addresses, such as `"0.0.0.0:8080,[::]:8080"`.
+///
+/// From configuration files, this can also be deserialized from a list of strings.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SocketAddrList(Vec<SocketAddr>);
+
+impl SocketAddrList {
+    /// Get the inner list of socket addresses.
+    pub fn into_inner(self) -> Vec<SocketAddr> {
+        self.0
+    }
+
+    /// Parse the socket addresses from an iterator of strings.
+    fn parse_all<'s>(src: &str, addrs: impl Iterator<Item = &'s str>) -> Result<Self, ParseError> {
+        let addrs = addrs
+            .map(str::trim)
+            .map(|addr| {
+                addr.parse::<SocketAddr>().map_err(|err| {
+                    ParseError::new("socket address list", src, format!("'{}': {}", addr, err))
+                })
+            })
+            .collect::<Result<Vec<SocketAddr>, ParseError>>()?;
+        if addrs.is_empty() {
+            Err(ParseError::new(
+                "socket address list",
+                src,
+                "expected at least one address",
+            ))
+        } else {
+            Ok(Self(addrs))
+        }
+    }
+}
+
+impl FromStr for SocketAddrList {
+    type Err = ParseError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        Self::parse_all(src, src.split(',').filter(|addr| !addr.trim().is_empty()))
+    }
+}
+
+impl Display for SocketAddrList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, addr) in self.0.iter().enumerate() {
+            if index != 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", addr)?;
+        }
+        Ok(())
+    }
+}
+
+impl Deref for SocketAddrList {
+    type Target = [SocketAddr];
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_slice()
+    }
+}
+
+impl Serialize for SocketAddrList {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SocketAddrList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// Visitor that accepts both a comma separated string and a sequence of strings.
+        struct SocketAddrListVisitor;
+
+        impl<'de> Visitor<'de> for SocketAddrListVisitor {
+            type Value = SocketAddrList;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a comma separated list of socket addresses or a list of strings")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut addrs = Vec::new();
+                while let Some(addr) = seq.next_element::<String>()? {
+                    addrs.push(addr);
+                }
+                let joined = addrs.join(",");
+                SocketAddrList::parse_all(&joined, addrs.iter().map(String::as_str))
+                    .map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_any(SocketAddrListVisitor)
+    }
+}
+
+/// A URL, such as `"postgres://user@localhost:5432/db"`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Url(url::Url);
+
+impl Url {
+    /// Get the inner url.
+    pub fn into_inner(self) -> url::Url {
+        self.0
+    }
+}
+
+impl FromStr for Url {
+    type Err = ParseError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        url::Url::parse(src.trim())
+            .map(Self)
+            .map_err(|err| ParseError::new("url", src, err))
+    }
+}
+
+impl Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl Deref for Url {
+    type Target = url::Url;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<url::Url> for Url {
+    fn from(src: url::Url) -> Self {
+        Self(src)
+    }
+}
+
+impl From<Url> for url::Url {
+    fn from(src: Url) -> Self {
+        src.0
+    }
+}
+
+impl ConfigValue for Url {
+    const EXPECTING: &'static str = "a url";
+}
+
+impl_serde!(Url);
+
+/// A log level, parsed case-insensitively.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// Logging is disabled.
+    Off,
+    /// `error`.
+    Error,
+    /// `warn` (or `warning`).
+    Warn,
+    /// `info`.
+    #[default]
+    Info,
+    /// `debug`.
+    Debug,
+    /// `trace`.
+    Trace,
+}
+
+impl LogLevel {
+    /// Get the name of the log level.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = ParseError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src.trim().to_ascii_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "error" => Ok(Self::Error),
+            "warn" | "warning" => Ok(Self::Warn),
+            "info" => Ok(Self::Info),
+            "debug" => Ok(Self::Debug),
+            "trace" => Ok(Self::Trace),
+            _ => Err(ParseError::new(
+                "log level",
+                src,
+                "expected one of off, error, warn, info, debug, trace",
+            )),
+        }
+    }
+}
+
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ConfigValue for LogLevel {
+    const EXPECTING: &'static str = "a log level";
+}
+
+impl_serde!(LogLevel);
+
+/// A percentage between 0 and 100, such as `"75%"` or `"12.5"`.
+///
+/// The percent sign is optional.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Percent(f64);
+
+impl Percent {
+    /// Create a new percentage, if the value is between 0 and 100.
+    pub fn new(value: f64) -> Option<Self> {
+        if (0.0..=100.0).contains(&value) {
+            Some(Self(value))
+        } else {
+            None
+        }
+    }
+
+    /// Get the percentage (between 0 and 100).
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+
+    /// Get the percentage as a fraction (between 0 and 1).
+    pub fn as_fraction(&self) -> f64 {
+        self.0 / 100.0
+    }
+}
+
+impl FromStr for Percent {
+    type Err = ParseError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let trimmed = src.trim();
+        let number = trimmed.strip_suffix('%').unwrap_or(trimmed).trim_end();
+        let value: f64 = number
+            .parse()
+            .map_err(|_| ParseError::new("percentage", src, "expected a number"))?;
+        Self::new(value)
+            .ok_or_else(|| ParseError::new("percentage", src, "expected a value between 0 and 100"))
+    }
+}
+
+impl Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+impl ConfigValue for Percent {
+    const EXPECTING: &'static str = "a percentage such as \"75%\"";
+}
+
+impl_serde!(Percent);
+
+/// An IP network in CIDR notation, such as `"10.0.0.0/8"` or `"fd00::/8"`.
+///
+/// An address without a prefix length is interpreted as a network containing only that address.
+/// The address must not have any bits set outside of the prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Create a new CIDR network.
+    ///
+    /// Returns None if the prefix length is too large or if the address has bits set outside of
+    /// the prefix.
+    pub fn new(network: IpAddr, prefix_len: u8) -> Option<Self> {
+        let cidr = Self {
+            network,
+            prefix_len,
+        };
+        if prefix_len > Self::max_prefix_len(&network) || cidr.mask(&network) != network {
+            None
+        } else {
+            Some(cidr)
+        }
+    }
+
+    /// The network address.
+    pub fn network(&self) -> IpAddr {
+        self.network
+    }
+
+    /// The length of the prefix.
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Check whether the network contains an address.
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        self.network.is_ipv4() == addr.is_ipv4() && self.mask(addr) == self.network
+    }
+
+    /// The maximum prefix length for an address.
+    fn max_prefix_len(addr: &IpAddr) -> u8 {
+        if addr.is_ipv4() {
+            32
+        } else {
+            128
+        }
+    }
+
+    /// Apply the network mask to an address.
+    fn mask(&self, addr: &IpAddr) -> IpAddr {
+        match addr {
+            IpAddr::V4(v4) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix_len.min(32)))
+                    .unwrap_or(0);
+                IpAddr::from((u32::from(*v4) & mask).to_be_bytes())
+            }
+            IpAddr::V6(v6) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix_len.min(128)))
+                    .unwrap_or(0);
+                IpAddr::from((u128::from(*v6) & mask).to_be_bytes())
+            }
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = ParseError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let err = |reason: String| ParseError::new("cidr", src, reason);
+        let trimmed = src.trim();
+        let (addr, prefix_len) = match trimmed.find('/') {
+            Some(index) => (&trimmed[..index], Some(&trimmed[index + 1..])),
+            None => (trimmed, None),
+        };
+        let network: IpAddr = addr
+            .parse()
+            .map_err(|e| err(format!("'{}': {}", addr, e)))?;
+        let max_prefix_len = Self::max_prefix_len(&network);
+        let prefix_len = match prefix_len {
+            Some(value) => value
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_prefix_len)
+                .ok_or_else(|| {
+                    err(format!(
+                        "prefix length must be a number between 0 and {}",
+                        max_prefix_len
+                    ))
+                })?,
+            None => max_prefix_len,
+        };
+        Self::new(network, prefix_len)
+            .ok_or_else(|| err("address has bits set outside of the prefix".to_string()))
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+impl ConfigValue for Cidr {
+    const EXPECTING: &'static str = "a network in CIDR notation such as \"10.0.0.0/8\"";
+}
+
+impl_serde!(Cidr);
+
+/// Placeholder that replaces redacted secrets.
+pub const REDACTED: &str = "[REDACTED]";
+
+thread_local! {
+    /// The policy used for serializing secrets on the current thread.
+    static SECRET_POLICY: Cell<SecretPolicy> = Cell::new(SecretPolicy::default());
+}
+
+/// How secrets are serialized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SecretPolicy {
+    /// Secrets are left out of the output.
+    ///
+    /// Omitted secrets serialize as none (like `Option::None`). Fields annotated with
+    /// `#[serde(skip_serializing_if = "Secret::is_omitted")]` are left out entirely.
+    Omit,
+    /// Secrets are replaced by a placeholder. _(default)_
+    #[default]
+    Redact,
+    /// Secrets are serialized as-is.
+    Include,
+}
+
+impl SecretPolicy {
+    /// Run a function with this policy applied to all secrets serialized on the current thread.
+    pub fn apply<F, R>(self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        /// Restores the previous policy, even if the function panics.
+        struct Restore(SecretPolicy);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                SECRET_POLICY.with(|policy| policy.set(self.0));
+            }
+        }
+
+        let _restore = Restore(SECRET_POLICY.with(|policy| policy.replace(self)));
+        f()
+    }
+
+    /// Get the policy that applies on the current thread.
+    pub fn current() -> Self {
+        SECRET_POLICY.with(Cell::get)
+    }
+}
+
+/// A secret value, such as a password or an API key.
+///
+/// Secrets deserialize just like the value they contain, but they are never printed by [Debug]
+/// and they are serialized according to the current [SecretPolicy] (redacted by default).
+///
+/// Omitted secrets serialize as none, which formats that support it (such as JSON) still print as
+/// a null field. Use [Secret::is_omitted] to leave the field out:
+///
+/// ```
+/// # #[cfg(feature = "configuration_export")]
+/// # {
+/// # use blockz::configuration::types::Secret;
+/// # use blockz::configuration::types::SecretPolicy;
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct DbConfig {
+///     db_host: String,
+///     #[serde(skip_serializing_if = "Secret::is_omitted")]
+///     db_pass: Secret<String>,
+/// }
+///
+/// let config = DbConfig {
+///     db_host: "localhost".to_string(),
+///     db_pass: Secret::new("NotASafePassword42".to_string()),
+/// };
+/// let json = SecretPolicy::Omit.apply(|| serde_json::to_string(&config)).unwrap();
+/// assert_eq!(json, r#"{"db_host":"localhost"}"#);
+/// # }
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    /// Create a new secret.
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Get a reference to the secret value.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    /// Get the secret value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+
+    /// Check whether the secret is omitted by the current [SecretPolicy].
+    ///
+    /// This is meant to be used with `#[serde(skip_serializing_if = "Secret::is_omitted")]`.
+    pub fn is_omitted(&self) -> bool {
+        SecretPolicy::current() == SecretPolicy::Omit
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({})", REDACTED)
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(src: T) -> Self {
+        Self(src)
+    }
+}
+
+impl<T> Serialize for Secret<T>
+where
+    T: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match SecretPolicy::current() {
+            SecretPolicy::Omit => serializer.serialize_none(),
+            SecretPolicy::Redact => serializer.serialize_str(REDACTED),
+            SecretPolicy::Include => self.0.serialize(serializer),
+        }
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Secret<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self)
+    }
+}
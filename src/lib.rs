@@ -25,16 +25,67 @@ pub mod prelude {
     #[cfg_attr(docsrs, doc(cfg(feature = "configuration")))]
     pub use crate::configuration::EasyConfiguration;
 
+    #[cfg(feature = "configuration")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "configuration")))]
+    pub use crate::configuration::BlockingConfiguration;
+
+    #[cfg(feature = "configuration")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "configuration")))]
+    pub use crate::configuration::EasyBlockingConfiguration;
+
     pub use blockz_derive::*;
 
     #[cfg(feature = "singleton")]
     #[cfg_attr(docsrs, doc(cfg(feature = "singleton")))]
     pub use crate::singleton::Singleton;
+
+    #[cfg(feature = "singleton")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "singleton")))]
+    pub use crate::singleton::MutableSingleton;
+
+    #[cfg(feature = "singleton")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "singleton")))]
+    pub use crate::singleton::GuardSingleton;
+
+    #[cfg(feature = "singleton")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "singleton")))]
+    pub use crate::singleton::MutableGuardSingleton;
+
+    #[cfg(feature = "tokio-runtime")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio-runtime")))]
+    pub use crate::singleton::WatchSingleton;
+
+    #[cfg(feature = "singleton")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "singleton")))]
+    pub use crate::singleton::KeyedSingleton;
+
+    #[cfg(feature = "singleton")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "singleton")))]
+    pub use crate::singleton::MutableKeyedSingleton;
+
+    #[cfg(feature = "singleton")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "singleton")))]
+    pub use crate::singleton::GuardKeyedSingleton;
+
+    #[cfg(feature = "singleton")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "singleton")))]
+    pub use crate::singleton::MutableGuardKeyedSingleton;
+
+    #[cfg(feature = "singleton")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "singleton")))]
+    pub use crate::singleton::ShardedSingleton;
 }
 
 /// Tests for the derive crate.
 #[cfg(test)]
 mod test {
+    #[cfg(any(
+        all(
+            feature = "swap",
+            any(feature = "tokio-runtime", feature = "singleton_agnostic")
+        ),
+        feature = "configuration"
+    ))]
     macro_rules! ui_tests {
         ($t: ident, pass, $feat: literal, [$( $index:literal ),*]) => {
             $(
@@ -48,14 +99,36 @@ mod test {
         };
     }
 
-    /// Test the `singleton` feature.
+    /// Test the `singleton` feature, with the `tokio-runtime` and `swap` features.
     #[test]
-    #[cfg(feature = "singleton")]
+    #[cfg(all(feature = "tokio-runtime", feature = "swap"))]
     fn test_singleton() {
         let t = trybuild::TestCases::new();
 
-        ui_tests!(t, pass, "singleton", [0, 1, 2]);
-        ui_tests!(t, fail, "singleton", [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        ui_tests!(
+            t,
+            pass,
+            "singleton",
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22]
+        );
+        ui_tests!(
+            t,
+            fail,
+            "singleton",
+            [
+                0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22,
+                23, 24, 25, 26, 27
+            ]
+        );
+    }
+
+    /// Test the `singleton_agnostic` feature, with the `swap` feature.
+    #[test]
+    #[cfg(all(feature = "singleton_agnostic", feature = "swap"))]
+    fn test_singleton_agnostic() {
+        let t = trybuild::TestCases::new();
+
+        ui_tests!(t, pass, "singleton_agnostic", [0]);
     }
 
     /// Test the direct configuration.
@@ -64,7 +137,7 @@ mod test {
     fn test_direct_configuration() {
         let t = trybuild::TestCases::new();
 
-        ui_tests!(t, pass, "direct_configuration", [0, 1, 2]);
+        ui_tests!(t, pass, "direct_configuration", [0, 1, 2, 3, 4, 5]);
         ui_tests!(t, fail, "direct_configuration", [0, 1]);
     }
 
@@ -74,7 +147,30 @@ mod test {
     fn test_envy_configuration() {
         let t = trybuild::TestCases::new();
 
-        ui_tests!(t, pass, "env_configuration", [0, 1, 2, 3, 4, 5]);
-        ui_tests!(t, fail, "env_configuration", [0, 1, 2, 3]);
+        ui_tests!(
+            t,
+            pass,
+            "env_configuration",
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10]
+        );
+        ui_tests!(t, fail, "env_configuration", [0, 1, 2, 3, 4, 5]);
+    }
+
+    /// Test the `configuration_types` feature.
+    #[test]
+    #[cfg(all(feature = "configuration_types", feature = "env_configuration"))]
+    fn test_configuration_types() {
+        let t = trybuild::TestCases::new();
+
+        ui_tests!(t, pass, "configuration_types", [0]);
+    }
+
+    /// Test the `configuration_export` feature.
+    #[test]
+    #[cfg(all(feature = "configuration_export", feature = "env_configuration"))]
+    fn test_configuration_export() {
+        let t = trybuild::TestCases::new();
+
+        ui_tests!(t, pass, "configuration_export", [0]);
     }
 }
@@ -0,0 +1,633 @@
+//! Actor for singletons driven by a message channel.
+//!
+//! An [Actor] keeps the value of a singleton in a dedicated task, which receives the uses of the
+//! singleton as messages over a bounded channel (its mailbox) and serves them one at a time.
+//!
+//! Calls ([Actor::call]) carry the work to the task: the task runs the call against the value it
+//! owns and sends the result back through a oneshot reply. Since the call outlives the caller's
+//! borrows, it must be `'static`, and a call that panics resumes the panic in the caller. Leases
+//! ([Actor::lock]) lend the value to the caller instead, through a guard that hands it back to the
+//! task when it's dropped; they serve the uses that need a reference to the value, such as guards.
+//! Both go through the same mailbox, so they never share the value: the mailbox orders them.
+//!
+//! The task is started by the first use of the actor and it parks the value in the actor (and
+//! exits) when the value is taken out of it, so an empty actor doesn't keep a task running. When
+//! the actor is dropped, such as at the end of a scope, its task serves the uses left in the
+//! mailbox and exits with the value.
+//!
+//! This is the value holder behind `#[singleton(actor)]`, whose singleton fns are sent as calls.
+
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::mem;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::task::Context;
+use std::task::Poll;
+
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+
+use super::SingletonError;
+
+/// What a use does when the mailbox of the actor is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ActorOverflow {
+    /// Wait until the mailbox has room.
+    #[default]
+    Wait,
+    /// Fail with [ActorError::MailboxFull].
+    Fail,
+}
+
+/// What happens to the uses in the mailbox when the actor is stopped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ActorStop {
+    /// The uses in the mailbox run before the actor stops.
+    #[default]
+    Drain,
+    /// The uses in the mailbox fail with [ActorError::Stopped].
+    Abort,
+}
+
+/// Configuration of an [Actor].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ActorConfig {
+    /// Capacity of the mailbox.
+    pub mailbox: usize,
+    /// What a use does when the mailbox is full.
+    pub overflow: ActorOverflow,
+    /// What happens to the uses in the mailbox when the actor is stopped.
+    pub stop: ActorStop,
+}
+
+impl Default for ActorConfig {
+    fn default() -> Self {
+        Self {
+            mailbox: 32,
+            overflow: ActorOverflow::default(),
+            stop: ActorStop::default(),
+        }
+    }
+}
+
+/// Error returned when an [Actor] can't be used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActorError {
+    /// The mailbox is full (with [ActorOverflow::Fail]).
+    MailboxFull,
+    /// The actor was stopped before it served the use (with [ActorStop::Abort]).
+    Stopped,
+    /// The actor holds no value, so it can't serve a call.
+    Empty,
+}
+
+impl ActorError {
+    /// Convert the error to the error of the singleton with the name.
+    pub fn into_singleton_error(self, name: &'static str) -> SingletonError {
+        match self {
+            Self::MailboxFull => SingletonError::MailboxFull(name),
+            Self::Stopped => SingletonError::Stopped(name),
+            Self::Empty => SingletonError::NotInitialized(name),
+        }
+    }
+}
+
+impl fmt::Display for ActorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MailboxFull => write!(f, "actor: mailbox full"),
+            Self::Stopped => write!(f, "actor: stopped"),
+            Self::Empty => write!(f, "actor: empty"),
+        }
+    }
+}
+
+impl Error for ActorError {}
+
+/// A call that the task of an [Actor] runs against its value.
+pub trait ActorCall<T>: Send + 'static {
+    /// Output of the call, sent back to the caller.
+    type Output: Send + 'static;
+
+    /// Whether the call changes the value.
+    const MUTABLE: bool;
+
+    /// Run the call.
+    fn call(self, value: &mut T) -> Pin<Box<dyn Future<Output = Self::Output> + Send + '_>>;
+}
+
+/// An actor that holds a value in a dedicated task.
+///
+/// The value is optional (`V` is `Option<T>`), like the values behind the locks of singletons.
+pub struct Actor<V> {
+    shared: Arc<Shared<V>>,
+}
+
+/// The state of an actor, shared with its task.
+struct Shared<V> {
+    config: ActorConfig,
+    state: Mutex<State<V>>,
+}
+
+/// Whether the task of an actor is running.
+enum State<V> {
+    /// The task is not running, the value is parked in the actor.
+    Parked(V),
+    /// The task is running and holds the value.
+    Running(Mailbox<V>),
+    /// The actor was dropped, its task exits once it has served the mailbox.
+    Closed,
+}
+
+/// The sending side of the mailbox of a running task.
+struct Mailbox<V> {
+    sender: mpsc::Sender<Message<V>>,
+    aborting: Arc<AtomicBool>,
+}
+
+/// A message sent to the task of an actor.
+enum Message<V> {
+    /// Run a call against the value.
+    Call(Box<dyn Dispatch<V>>),
+    /// Lend the value to a use.
+    Lease(oneshot::Sender<ActorGuard<V>>),
+    /// Take the value out of the actor.
+    Stop(oneshot::Sender<V>),
+}
+
+/// What a use hands back to the task of an actor.
+enum Returned<V> {
+    /// The value, for the task to serve the next uses.
+    Value(V),
+    /// Nothing: the value was empty and the mailbox too, so the use parked the value itself.
+    Parked,
+}
+
+/// The hand-back of a use, called with the value when its guard is dropped.
+type Back<V> = Box<dyn FnOnce(V) + Send + Sync>;
+
+/// The reply to a call: its result, or the payload of its panic.
+type Reply<R> = Result<Result<R, ActorError>, Box<dyn Any + Send>>;
+
+/// A call in the mailbox, which replies to its caller when it runs.
+trait Dispatch<V>: Send {
+    /// Run the call against the value and send the reply.
+    fn run(self: Box<Self>, value: &mut V) -> Pin<Box<dyn Future<Output = ()> + Send + '_>>;
+
+    /// Fail the call without running it.
+    fn fail(self: Box<Self>, err: ActorError);
+}
+
+/// A call and its reply.
+struct Dispatched<C, T>
+where
+    C: ActorCall<T>,
+{
+    call: C,
+    after: Option<fn(&T)>,
+    reply: oneshot::Sender<Reply<C::Output>>,
+}
+
+impl<C, T> Dispatch<Option<T>> for Dispatched<C, T>
+where
+    C: ActorCall<T>,
+    T: Send + 'static,
+{
+    fn run(
+        self: Box<Self>,
+        value: &mut Option<T>,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        let Self { call, after, reply } = *self;
+        Box::pin(async move {
+            let result = match value {
+                Some(value) => {
+                    // the panic of a call is resumed by its caller, the task keeps serving
+                    let result = CatchUnwind(call.call(value)).await;
+                    if let (Ok(_), true, Some(after)) = (&result, C::MUTABLE, after) {
+                        after(value);
+                    }
+                    result.map(Ok)
+                }
+                None => Ok(Err(ActorError::Empty)),
+            };
+            let _ = reply.send(result);
+        })
+    }
+
+    fn fail(self: Box<Self>, err: ActorError) {
+        let _ = self.reply.send(Ok(Err(err)));
+    }
+}
+
+/// A future that catches the panics of the future it polls.
+struct CatchUnwind<F>(F);
+
+impl<F> Future for CatchUnwind<F>
+where
+    F: Future + Unpin,
+{
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = &mut self.0;
+        match panic::catch_unwind(AssertUnwindSafe(|| Pin::new(inner).poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+impl<V> Clone for Mailbox<V> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            aborting: self.aborting.clone(),
+        }
+    }
+}
+
+impl<T> Actor<Option<T>>
+where
+    T: Send + 'static,
+{
+    /// Create a new actor, whose task starts with its first use.
+    pub fn new(value: Option<T>, config: ActorConfig) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                config,
+                state: Mutex::new(State::Parked(value)),
+            }),
+        }
+    }
+
+    /// Send a call to the mailbox and wait for its result.
+    ///
+    /// The task runs the call against its value, then runs `after` with the value if the call
+    /// changed it. The call fails with [ActorError::Empty] if the actor holds no value, and if it
+    /// panics, the returned future resumes the panic. The call runs even if the returned future is
+    /// dropped after sending it.
+    ///
+    /// The returned future panics if polled outside of a tokio runtime.
+    pub fn call<C>(
+        &self,
+        call: C,
+        after: Option<fn(&T)>,
+    ) -> impl Future<Output = Result<C::Output, ActorError>> + Send + 'static
+    where
+        C: ActorCall<T>,
+    {
+        let shared = self.shared.clone();
+        async move {
+            let (reply, result) = oneshot::channel();
+            let call = Box::new(Dispatched { call, after, reply });
+            shared
+                .dispatch(Message::Call(call), shared.config.overflow)
+                .await?;
+            match result.await {
+                Ok(Ok(result)) => result,
+                Ok(Err(payload)) => panic::resume_unwind(payload),
+                Err(_) => Err(ActorError::Stopped),
+            }
+        }
+    }
+
+    /// Send a call to the mailbox and block the current thread until its result is sent back.
+    ///
+    /// This panics if called within an asynchronous execution context or outside of a tokio
+    /// runtime. It can be called from the blocking threads of tokio, such as in `spawn_blocking`
+    /// closures.
+    pub fn blocking_call<C>(&self, call: C, after: Option<fn(&T)>) -> Result<C::Output, ActorError>
+    where
+        C: ActorCall<T>,
+    {
+        Shared::<Option<T>>::runtime().block_on(self.call(call, after))
+    }
+
+    /// Send a use to the mailbox and wait until the task lends the value to it.
+    ///
+    /// This panics if called outside of a tokio runtime.
+    pub async fn lock(&self) -> Result<ActorGuard<Option<T>>, ActorError> {
+        let (reply, lease) = oneshot::channel();
+        self.shared
+            .dispatch(Message::Lease(reply), self.shared.config.overflow)
+            .await?;
+        lease.await.map_err(|_| ActorError::Stopped)
+    }
+
+    /// Send a use to the mailbox and block the current thread until the task lends the value to
+    /// it.
+    ///
+    /// This panics if called within an asynchronous execution context or outside of a tokio
+    /// runtime. It can be called from the blocking threads of tokio, such as in `spawn_blocking`
+    /// closures.
+    pub fn blocking_lock(&self) -> Result<ActorGuard<Option<T>>, ActorError> {
+        Shared::<Option<T>>::runtime().block_on(self.lock())
+    }
+
+    /// Get the value parked in the actor, if its task is not running.
+    pub fn try_lock(&self) -> Result<ActorParkedGuard<'_, Option<T>>, ActorError> {
+        let state = self.shared.state();
+        match &*state {
+            State::Parked(_) => Ok(ActorParkedGuard { state }),
+            State::Running(_) | State::Closed => Err(ActorError::MailboxFull),
+        }
+    }
+
+    /// Stop the actor and take its value out.
+    ///
+    /// The uses in the mailbox either run first or fail, depending on the stop configuration.
+    pub async fn stop(&self) -> Option<T> {
+        {
+            let mut state = self.shared.state();
+            match &mut *state {
+                State::Parked(value) => return value.take(),
+                State::Running(mailbox) => {
+                    if self.shared.config.stop == ActorStop::Abort {
+                        mailbox.aborting.store(true, Ordering::SeqCst);
+                    }
+                }
+                State::Closed => return None,
+            }
+        }
+        let (reply, value) = oneshot::channel();
+        // stops wait for room in the mailbox, so they can't fail
+        if self
+            .shared
+            .dispatch(Message::Stop(reply), ActorOverflow::Wait)
+            .await
+            .is_err()
+        {
+            return None;
+        }
+        value.await.unwrap_or(None)
+    }
+}
+
+impl<T> Shared<Option<T>>
+where
+    T: Send + 'static,
+{
+    /// Lock the state of the actor.
+    fn state(&self) -> MutexGuard<'_, State<Option<T>>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Get the handle of the current tokio runtime.
+    fn runtime() -> Handle {
+        Handle::try_current().expect("actor: must be used within a tokio runtime")
+    }
+
+    /// Send a message to the task, starting it if it's not running.
+    async fn dispatch(
+        self: &Arc<Self>,
+        mut message: Message<Option<T>>,
+        overflow: ActorOverflow,
+    ) -> Result<(), ActorError> {
+        loop {
+            let mailbox = self.mailbox();
+            // a closed mailbox belongs to a task that parked the value, so try again
+            message = match overflow {
+                ActorOverflow::Wait => match mailbox.sender.send(message).await {
+                    Ok(()) => return Ok(()),
+                    Err(mpsc::error::SendError(message)) => message,
+                },
+                ActorOverflow::Fail => match mailbox.sender.try_send(message) {
+                    Ok(()) => return Ok(()),
+                    Err(mpsc::error::TrySendError::Full(_)) => return Err(ActorError::MailboxFull),
+                    Err(mpsc::error::TrySendError::Closed(message)) => message,
+                },
+            };
+        }
+    }
+
+    /// Get the mailbox of the task, starting it with the parked value if it's not running.
+    fn mailbox(self: &Arc<Self>) -> Mailbox<Option<T>> {
+        let mut state = self.state();
+        if let State::Running(mailbox) = &*state {
+            return mailbox.clone();
+        }
+        let (sender, receiver) = mpsc::channel(self.config.mailbox);
+        let mailbox = Mailbox {
+            sender,
+            aborting: Arc::new(AtomicBool::new(false)),
+        };
+        let value = match mem::replace(&mut *state, State::Running(mailbox.clone())) {
+            State::Parked(value) => value,
+            State::Running(_) | State::Closed => unreachable!(),
+        };
+        // the task doesn't keep its mailbox open, so it exits when the actor is dropped
+        let aborting = mailbox.aborting.clone();
+        let sender = mailbox.sender.downgrade();
+        Self::runtime().spawn(self.clone().run(value, receiver, aborting, sender));
+        mailbox
+    }
+
+    /// Serve the messages of the mailbox until the value is taken out of the actor.
+    async fn run(
+        self: Arc<Self>,
+        mut value: Option<T>,
+        mut receiver: mpsc::Receiver<Message<Option<T>>>,
+        aborting: Arc<AtomicBool>,
+        sender: mpsc::WeakSender<Message<Option<T>>>,
+    ) {
+        let mut next = receiver.recv().await;
+        while let Some(message) = next {
+            // calls to an empty actor fail after it parks, so it can be initialized right after
+            let mut empty = None;
+            match message {
+                Message::Call(call) => {
+                    // aborted calls are dropped, so they fail with ActorError::Stopped
+                    let aborting = aborting.load(Ordering::SeqCst);
+                    if !aborting && value.is_some() {
+                        call.run(&mut value).await;
+                    } else if !aborting {
+                        empty = Some(call);
+                    }
+                }
+                Message::Lease(reply) => {
+                    // aborted uses are dropped, so they fail with ActorError::Stopped
+                    if !aborting.load(Ordering::SeqCst) {
+                        match self.lend(value, reply, sender.clone()).await {
+                            Some(returned) => value = returned,
+                            None => break,
+                        }
+                    }
+                }
+                Message::Stop(reply) => {
+                    // park before replying, so the actor can be initialized again right away
+                    *self.state() = State::Parked(None);
+                    let _ = reply.send(value.take());
+                    break;
+                }
+            }
+            // park the empty value when there is nothing left to serve
+            next = match receiver.try_recv() {
+                Ok(message) => Some(message),
+                Err(_) if value.is_some() => receiver.recv().await,
+                Err(_) => {
+                    let mut state = self.state();
+                    match receiver.try_recv() {
+                        Ok(message) => Some(message),
+                        Err(_) => {
+                            *state = State::Parked(None);
+                            None
+                        }
+                    }
+                }
+            };
+            if let Some(call) = empty {
+                call.fail(ActorError::Empty);
+            }
+        }
+        // the messages sent while parking go to the next task
+        receiver.close();
+        while let Some(message) = receiver.recv().await {
+            let _ = self.dispatch(message, ActorOverflow::Wait).await;
+        }
+    }
+
+    /// Lend the value to a use and wait until it's handed back.
+    ///
+    /// This returns None if the use parked the value, which stops the task.
+    async fn lend(
+        self: &Arc<Self>,
+        value: Option<T>,
+        reply: oneshot::Sender<ActorGuard<Option<T>>>,
+        sender: mpsc::WeakSender<Message<Option<T>>>,
+    ) -> Option<Option<T>> {
+        let (back, returned) = oneshot::channel();
+        let shared = self.clone();
+        let back: Back<Option<T>> = Box::new(move |value| {
+            // an empty value is parked by the use, so the actor can be initialized right after it
+            if value.is_none() {
+                let mut state = shared.state();
+                if let State::Running(mailbox) = &*state {
+                    let empty = mailbox.sender.capacity() == mailbox.sender.max_capacity();
+                    let same = sender
+                        .upgrade()
+                        .is_some_and(|sender| mailbox.sender.same_channel(&sender));
+                    if empty && same {
+                        *state = State::Parked(None);
+                        let _ = back.send(Returned::Parked);
+                        return;
+                    }
+                }
+            }
+            let _ = back.send(Returned::Value(value));
+        });
+        let guard = ActorGuard {
+            value: Some(value),
+            back: Some(back),
+        };
+        match reply.send(guard) {
+            // a guard that is forgotten loses the value
+            Ok(()) => match returned.await {
+                Ok(Returned::Value(value)) => Some(value),
+                Ok(Returned::Parked) => None,
+                Err(_) => Some(None),
+            },
+            Err(mut guard) => {
+                guard.back = None;
+                Some(guard.value.take().unwrap_or(None))
+            }
+        }
+    }
+}
+
+impl<V> Drop for Actor<V> {
+    fn drop(&mut self) {
+        // closing the mailbox ends the task once it has served the uses in it
+        let mut state = self
+            .shared
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let State::Running(_) = &*state {
+            *state = State::Closed;
+        }
+    }
+}
+
+impl<V> fmt::Debug for Actor<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Actor")
+            .field("config", &self.shared.config)
+            .finish()
+    }
+}
+
+/// Guard of a use of an [Actor], which holds the value lent by its task.
+///
+/// The value is handed back to the task when the guard is dropped.
+pub struct ActorGuard<V> {
+    value: Option<V>,
+    back: Option<Back<V>>,
+}
+
+impl<V> Deref for ActorGuard<V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.value
+            .as_ref()
+            .expect("actor guard: value already handed back")
+    }
+}
+
+impl<V> DerefMut for ActorGuard<V> {
+    fn deref_mut(&mut self) -> &mut V {
+        self.value
+            .as_mut()
+            .expect("actor guard: value already handed back")
+    }
+}
+
+impl<V> Drop for ActorGuard<V> {
+    fn drop(&mut self) {
+        if let (Some(value), Some(back)) = (self.value.take(), self.back.take()) {
+            back(value);
+        }
+    }
+}
+
+/// Guard of the value parked in an [Actor] whose task is not running.
+///
+/// The actor can't start its task until the guard is dropped, so the guard must not be held across
+/// await points.
+pub struct ActorParkedGuard<'a, V> {
+    state: MutexGuard<'a, State<V>>,
+}
+
+impl<'a, V> Deref for ActorParkedGuard<'a, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        match &*self.state {
+            State::Parked(value) => value,
+            State::Running(_) | State::Closed => unreachable!(),
+        }
+    }
+}
+
+impl<'a, V> DerefMut for ActorParkedGuard<'a, V> {
+    fn deref_mut(&mut self) -> &mut V {
+        match &mut *self.state {
+            State::Parked(value) => value,
+            State::Running(_) | State::Closed => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Executor-independent singleton support.
+//!
+//! Singletons declared with `#[singleton(runtime = "agnostic")]` use the locks and the timeout
+//! from this module instead of the ones from tokio, so they can be used with any executor
+//! (async-std, smol, futures or tokio itself).
+
+use futures_lite::future;
+
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+pub use async_lock::Mutex;
+pub use async_lock::MutexGuard;
+pub use async_lock::RwLock;
+pub use async_lock::RwLockReadGuard;
+pub use async_lock::RwLockWriteGuard;
+
+/// Error returned by [timeout] when the future does not finish in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Elapsed(());
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
+}
+
+impl Error for Elapsed {}
+
+/// Run a future, failing if it does not finish within the duration.
+///
+/// The timer is driven by [async_io], so it does not depend on the executor that polls the
+/// future.
+///
+/// [async_io]: https://docs.rs/async-io
+pub async fn timeout<F>(duration: Duration, future: F) -> Result<F::Output, Elapsed>
+where
+    F: Future,
+{
+    let elapsed = async {
+        async_io::Timer::after(duration).await;
+        Err(Elapsed(()))
+    };
+    future::or(async { Ok(future.await) }, elapsed).await
+}
@@ -0,0 +1,267 @@
+//! Guards that give scoped access to singletons.
+//!
+//! A guard holds the lock of a singleton (or a snapshot of it, for swap locks) and dereferences to
+//! its value, so callers can borrow data from the singleton without cloning it. Guards can be
+//! projected to a part of the value with [SingletonReadGuard::map] and [SingletonWriteGuard::map].
+//!
+//! The lock guard is moved to the heap, so the projected value doesn't move with the guard, no
+//! matter where the lock keeps the value. The lock of a scope (see Singleton::scope) is kept alive
+//! by the guard, so it can outlive the scope.
+
+use std::any::Any;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+/// What keeps the lock of a guard alive, such as the entry of a scope.
+type Owner = Option<Arc<dyn Any + Send + Sync>>;
+
+/// A guard that gives shared access to (a part of) the value of a singleton.
+///
+/// This is returned by `GuardSingleton::read_guard`. The singleton stays locked until the guard
+/// is dropped, so the guard must not be held for longer than needed.
+// G: the guard of the lock.
+// T: the value that the guard dereferences to.
+pub struct SingletonReadGuard<G, T: ?Sized> {
+    guard: NonNull<G>,
+    value: NonNull<T>,
+    owner: Owner,
+    _marker: PhantomData<(Box<G>, *const T)>,
+}
+
+/// A guard that gives exclusive access to (a part of) the value of a singleton.
+///
+/// This is returned by `MutableGuardSingleton::write_guard`. The singleton stays locked until the
+/// guard is dropped, so the guard must not be held for longer than needed.
+// G: the guard of the lock.
+// T: the value that the guard dereferences to.
+pub struct SingletonWriteGuard<G, T: ?Sized> {
+    guard: NonNull<G>,
+    value: NonNull<T>,
+    release: Option<fn(&G)>,
+    owner: Owner,
+    _marker: PhantomData<(Box<G>, *mut T)>,
+}
+
+impl<G> SingletonReadGuard<G, G::Target>
+where
+    G: Deref,
+{
+    /// Create a new read guard from the guard of a lock.
+    pub fn new(guard: G) -> Self {
+        let guard = NonNull::from(Box::leak(Box::new(guard)));
+        // the lock guard is never moved out of the heap, so the value stays where it is
+        let value = NonNull::from(unsafe { guard.as_ref() }.deref());
+        Self {
+            guard,
+            value,
+            owner: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<G, T: ?Sized> SingletonReadGuard<G, T> {
+    /// Project the guard to a part of the value, such as a field.
+    ///
+    /// This is an associated function so that it doesn't shadow a method of the value.
+    pub fn map<U, F>(mut this: Self, f: F) -> SingletonReadGuard<G, U>
+    where
+        U: ?Sized,
+        F: FnOnce(&T) -> &U,
+    {
+        let value = NonNull::from(f(unsafe { this.value.as_ref() }));
+        let owner = this.owner.take();
+        SingletonReadGuard {
+            guard: ManuallyDrop::new(this).guard,
+            value,
+            owner,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Project the guard to a part of the value that might be missing, such as an optional field.
+    ///
+    /// The original guard is returned if the projection fails.
+    pub fn try_map<U, F>(mut this: Self, f: F) -> Result<SingletonReadGuard<G, U>, Self>
+    where
+        U: ?Sized,
+        F: FnOnce(&T) -> Option<&U>,
+    {
+        match f(unsafe { this.value.as_ref() }) {
+            Some(value) => {
+                let value = NonNull::from(value);
+                let owner = this.owner.take();
+                Ok(SingletonReadGuard {
+                    guard: ManuallyDrop::new(this).guard,
+                    value,
+                    owner,
+                    _marker: PhantomData,
+                })
+            }
+            None => Err(this),
+        }
+    }
+
+    /// Keep the owner of the lock alive until the guard is dropped.
+    #[doc(hidden)]
+    pub fn with_owner<O>(mut this: Self, owner: Option<Arc<O>>) -> Self
+    where
+        O: Send + Sync + 'static,
+    {
+        this.owner = owner.map(|owner| owner as Arc<dyn Any + Send + Sync>);
+        this
+    }
+}
+
+impl<G> SingletonWriteGuard<G, G::Target>
+where
+    G: DerefMut,
+{
+    /// Create a new write guard from the guard of a lock.
+    pub fn new(guard: G) -> Self {
+        let mut guard = NonNull::from(Box::leak(Box::new(guard)));
+        // the lock guard is never moved out of the heap, so the value stays where it is
+        let value = NonNull::from(unsafe { guard.as_mut() }.deref_mut());
+        Self {
+            guard,
+            value,
+            release: None,
+            owner: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create a new write guard from the guard of a lock, which runs a function with the guard of
+    /// the lock right before releasing it.
+    ///
+    /// This is used by watched singletons to publish the changes made through the guard.
+    pub fn with_release(guard: G, release: fn(&G)) -> Self {
+        let mut this = Self::new(guard);
+        this.release = Some(release);
+        this
+    }
+}
+
+impl<G, T: ?Sized> SingletonWriteGuard<G, T> {
+    /// Project the guard to a part of the value, such as a field.
+    ///
+    /// This is an associated function so that it doesn't shadow a method of the value.
+    pub fn map<U, F>(mut this: Self, f: F) -> SingletonWriteGuard<G, U>
+    where
+        U: ?Sized,
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        let value = NonNull::from(f(unsafe { this.value.as_mut() }));
+        let owner = this.owner.take();
+        let this = ManuallyDrop::new(this);
+        SingletonWriteGuard {
+            guard: this.guard,
+            value,
+            release: this.release,
+            owner,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Project the guard to a part of the value that might be missing, such as an optional field.
+    ///
+    /// The original guard is returned if the projection fails.
+    pub fn try_map<U, F>(mut this: Self, f: F) -> Result<SingletonWriteGuard<G, U>, Self>
+    where
+        U: ?Sized,
+        F: FnOnce(&mut T) -> Option<&mut U>,
+    {
+        match f(unsafe { this.value.as_mut() }) {
+            Some(value) => {
+                let value = NonNull::from(value);
+                let owner = this.owner.take();
+                let this = ManuallyDrop::new(this);
+                Ok(SingletonWriteGuard {
+                    guard: this.guard,
+                    value,
+                    release: this.release,
+                    owner,
+                    _marker: PhantomData,
+                })
+            }
+            None => Err(this),
+        }
+    }
+
+    /// Keep the owner of the lock alive until the guard is dropped.
+    #[doc(hidden)]
+    pub fn with_owner<O>(mut this: Self, owner: Option<Arc<O>>) -> Self
+    where
+        O: Send + Sync + 'static,
+    {
+        this.owner = owner.map(|owner| owner as Arc<dyn Any + Send + Sync>);
+        this
+    }
+}
+
+impl<G, T: ?Sized> Deref for SingletonReadGuard<G, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<G, T: ?Sized> Deref for SingletonWriteGuard<G, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<G, T: ?Sized> DerefMut for SingletonWriteGuard<G, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.value.as_mut() }
+    }
+}
+
+// the owner is dropped after the lock guard, with the other fields
+impl<G, T: ?Sized> Drop for SingletonReadGuard<G, T> {
+    fn drop(&mut self) {
+        drop(unsafe { Box::from_raw(self.guard.as_ptr()) });
+    }
+}
+
+impl<G, T: ?Sized> Drop for SingletonWriteGuard<G, T> {
+    fn drop(&mut self) {
+        let guard = unsafe { Box::from_raw(self.guard.as_ptr()) };
+        if let Some(release) = self.release {
+            release(&guard);
+        }
+    }
+}
+
+// the guards own the lock guard and a (shared or exclusive) reference to the value
+unsafe impl<G: Send, T: ?Sized + Sync> Send for SingletonReadGuard<G, T> {}
+unsafe impl<G: Sync, T: ?Sized + Sync> Sync for SingletonReadGuard<G, T> {}
+unsafe impl<G: Send, T: ?Sized + Send> Send for SingletonWriteGuard<G, T> {}
+unsafe impl<G: Sync, T: ?Sized + Sync> Sync for SingletonWriteGuard<G, T> {}
+
+impl<G, T> fmt::Debug for SingletonReadGuard<G, T>
+where
+    T: ?Sized + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<G, T> fmt::Debug for SingletonWriteGuard<G, T>
+where
+    T: ?Sized + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
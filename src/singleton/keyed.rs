@@ -0,0 +1,343 @@
+//! Keyed singletons.
+//!
+//! A keyed singleton (`#[singleton(keyed)]`) holds a value for each key, such as the database
+//! pools of a service (primary, replica, analytics) which share the same type. The keys are
+//! either strings or a typed key, such as an enum (`#[singleton(keyed = "DbRole")]`).
+//!
+//! Each key behaves like a singleton of its own: it is initialized with
+//! KeyedSingleton::init_singleton_keyed and used by the `*_keyed` functions, which mirror the
+//! functions of [Singleton](super::Singleton). Singleton fns of keyed singletons
+//! (`#[singleton_fn(keyed)]`) take the key as their first argument.
+//!
+//! Keys are registered by their first use and they are never removed: taking the value out of a
+//! key leaves it uninitialized.
+
+use std::borrow::ToOwned;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::time::Duration;
+
+use super::SingletonError;
+use super::SingletonFn;
+use super::SingletonFnMut;
+use super::SingletonFnMutWithArg;
+use super::SingletonFnWithArg;
+
+/// A singleton that holds a value for each key.
+#[async_trait::async_trait]
+pub trait KeyedSingleton {
+    /// Key of the values, as borrowed by the functions (`str` for string keys).
+    type Key: ?Sized + ToOwned + Sync;
+
+    /// Inner value contained by the singleton for each key.
+    type Inner;
+
+    /// Initialize the singleton for a key.
+    ///
+    /// This can fail if the key has already been initialized.
+    fn init_singleton_keyed(key: &Self::Key, inner: Self::Inner) -> anyhow::Result<()>;
+
+    /// Check whether the singleton has been initialized for a key.
+    fn is_initialized_keyed(key: &Self::Key) -> bool;
+
+    /// Get the keys that have been initialized.
+    fn singleton_keys() -> Vec<<Self::Key as ToOwned>::Owned>;
+
+    /// Use the singleton for a key with an immutable reference.
+    ///
+    /// This panics if the key has not been initialized, or if the singleton has a sync
+    /// lock (it can only be used by blocking functions).
+    // F: Function to be run.
+    // R: Function result.
+    async fn use_singleton_keyed<F, R>(key: &Self::Key, clojure: F) -> R
+    where
+        F: for<'c> SingletonFn<'c, Self::Inner, R> + Send,
+        R: Send;
+
+    /// Use the singleton for a key with an immutable reference and an argument.
+    ///
+    /// This panics if the key has not been initialized, or if the singleton has a sync
+    /// lock (it can only be used by blocking functions).
+    // F: Function to be run.
+    // A: Function argument.
+    // R: Function result.
+    async fn use_singleton_with_arg_keyed<F, A, R>(key: &Self::Key, clojure: F, arg: A) -> R
+    where
+        F: for<'c> SingletonFnWithArg<'c, Self::Inner, A, R> + Send,
+        A: Send,
+        R: Send;
+
+    /// Use the singleton for a key with an immutable reference, or fail if the key has not been
+    /// initialized.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // R: Function result.
+    async fn try_use_singleton_keyed<F, R>(
+        key: &Self::Key,
+        clojure: F,
+    ) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFn<'c, Self::Inner, R> + Send,
+        R: Send;
+
+    /// Use the singleton for a key with an immutable reference and an argument, or fail if the
+    /// key has not been initialized.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // A: Function argument.
+    // R: Function result.
+    async fn try_use_singleton_with_arg_keyed<F, A, R>(
+        key: &Self::Key,
+        clojure: F,
+        arg: A,
+    ) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFnWithArg<'c, Self::Inner, A, R> + Send,
+        A: Send,
+        R: Send;
+
+    /// Use the singleton for a key with an immutable reference, or fail if the use (including
+    /// the lock acquisition) does not finish within the timeout.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // R: Function result.
+    async fn use_singleton_timeout_keyed<F, R>(
+        key: &Self::Key,
+        clojure: F,
+        timeout: Duration,
+    ) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFn<'c, Self::Inner, R> + Send,
+        R: Send;
+
+    /// Use the singleton for a key with an immutable reference and an argument, or fail if the
+    /// use (including the lock acquisition) does not finish within the timeout.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // A: Function argument.
+    // R: Function result.
+    async fn use_singleton_with_arg_timeout_keyed<F, A, R>(
+        key: &Self::Key,
+        clojure: F,
+        arg: A,
+        timeout: Duration,
+    ) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFnWithArg<'c, Self::Inner, A, R> + Send,
+        A: Send,
+        R: Send;
+
+    /// Use the singleton for a key with an immutable reference, blocking the current thread
+    /// while waiting for the lock.
+    ///
+    /// This panics if the key has not been initialized.
+    // F: Function to be run.
+    // R: Function result.
+    fn use_singleton_blocking_keyed<F, R>(key: &Self::Key, clojure: F) -> R
+    where
+        F: FnOnce(&Self::Inner) -> R;
+
+    /// Use the singleton for a key with an immutable reference, blocking the current thread
+    /// while waiting for the lock, or fail if the key has not been initialized.
+    // F: Function to be run.
+    // R: Function result.
+    fn try_use_singleton_blocking_keyed<F, R>(
+        key: &Self::Key,
+        clojure: F,
+    ) -> Result<R, SingletonError>
+    where
+        F: FnOnce(&Self::Inner) -> R;
+}
+
+/// A keyed singleton that can be borrowed with guards.
+///
+/// This is implemented by all keyed singletons, except those with a sync lock (`lock =
+/// "std_mutex"` or `lock = "parking_lot"`), which can only be used by blocking functions.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be borrowed with a guard as a keyed singleton",
+    label = "guard singleton fns need a singleton with an async lock",
+    note = "singletons with a sync lock (`std_mutex` or `parking_lot`) can only be used by blocking functions, use an async lock such as `#[singleton(lock = \"rwlock\")]`"
+)]
+#[async_trait::async_trait]
+pub trait GuardKeyedSingleton: KeyedSingleton {
+    /// Guard that gives shared access to the inner value of a key (a SingletonReadGuard).
+    type ReadGuard: Deref<Target = Self::Inner>;
+
+    /// Lock the singleton for a key and get a guard that gives shared access to its inner value.
+    ///
+    /// This panics if the key has not been initialized.
+    async fn read_guard_keyed(key: &Self::Key) -> Self::ReadGuard;
+
+    /// Lock the singleton for a key and get a guard that gives shared access to its inner value,
+    /// or fail if the key has not been initialized.
+    async fn try_read_guard_keyed(key: &Self::Key) -> Result<Self::ReadGuard, SingletonError>;
+}
+
+/// A keyed singleton that can be used with a mutable reference.
+///
+/// This is implemented by all keyed singletons, except those without a lock (`lock = "none"`).
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be used mutably as a keyed singleton",
+    label = "`&mut self` singleton fns need a singleton with a lock",
+    note = "singletons with `#[singleton(lock = \"none\")]` can't be mutated, use another lock such as `#[singleton(lock = \"swap\")]`"
+)]
+#[async_trait::async_trait]
+pub trait MutableKeyedSingleton: KeyedSingleton {
+    /// Use the singleton for a key with a mutable reference.
+    ///
+    /// This panics if the key has not been initialized, or if the singleton has a sync
+    /// lock (it can only be used by blocking functions).
+    // F: Function to be run.
+    // R: Function result.
+    async fn use_mut_singleton_keyed<F, R>(key: &Self::Key, clojure: F) -> R
+    where
+        F: for<'c> SingletonFnMut<'c, Self::Inner, R> + Send,
+        R: Send;
+
+    /// Use the singleton for a key with a mutable reference and an argument.
+    ///
+    /// This panics if the key has not been initialized, or if the singleton has a sync
+    /// lock (it can only be used by blocking functions).
+    // F: Function to be run.
+    // A: Function argument.
+    // R: Function result.
+    async fn use_mut_singleton_with_arg_keyed<F, A, R>(key: &Self::Key, clojure: F, arg: A) -> R
+    where
+        F: for<'c> SingletonFnMutWithArg<'c, Self::Inner, A, R> + Send,
+        A: Send,
+        R: Send;
+
+    /// Use the singleton for a key with a mutable reference, or fail if the key has not been
+    /// initialized.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // R: Function result.
+    async fn try_use_mut_singleton_keyed<F, R>(
+        key: &Self::Key,
+        clojure: F,
+    ) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFnMut<'c, Self::Inner, R> + Send,
+        R: Send;
+
+    /// Use the singleton for a key with a mutable reference and an argument, or fail if the key
+    /// has not been initialized.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // A: Function argument.
+    // R: Function result.
+    async fn try_use_mut_singleton_with_arg_keyed<F, A, R>(
+        key: &Self::Key,
+        clojure: F,
+        arg: A,
+    ) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFnMutWithArg<'c, Self::Inner, A, R> + Send,
+        A: Send,
+        R: Send;
+
+    /// Use the singleton for a key with a mutable reference, or fail if the use (including the
+    /// lock acquisition) does not finish within the timeout.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // R: Function result.
+    async fn use_mut_singleton_timeout_keyed<F, R>(
+        key: &Self::Key,
+        clojure: F,
+        timeout: Duration,
+    ) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFnMut<'c, Self::Inner, R> + Send,
+        R: Send;
+
+    /// Use the singleton for a key with a mutable reference and an argument, or fail if the use
+    /// (including the lock acquisition) does not finish within the timeout.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // A: Function argument.
+    // R: Function result.
+    async fn use_mut_singleton_with_arg_timeout_keyed<F, A, R>(
+        key: &Self::Key,
+        clojure: F,
+        arg: A,
+        timeout: Duration,
+    ) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFnMutWithArg<'c, Self::Inner, A, R> + Send,
+        A: Send,
+        R: Send;
+
+    /// Use the singleton for a key with a mutable reference, blocking the current thread while
+    /// waiting for the lock.
+    ///
+    /// This panics if the key has not been initialized.
+    // F: Function to be run.
+    // R: Function result.
+    fn use_mut_singleton_blocking_keyed<F, R>(key: &Self::Key, clojure: F) -> R
+    where
+        F: FnOnce(&mut Self::Inner) -> R;
+
+    /// Use the singleton for a key with a mutable reference, blocking the current thread while
+    /// waiting for the lock, or fail if the key has not been initialized.
+    // F: Function to be run.
+    // R: Function result.
+    fn try_use_mut_singleton_blocking_keyed<F, R>(
+        key: &Self::Key,
+        clojure: F,
+    ) -> Result<R, SingletonError>
+    where
+        F: FnOnce(&mut Self::Inner) -> R;
+
+    /// Replace the value of a key, returning the old value.
+    async fn replace_singleton_keyed(key: &Self::Key, inner: Self::Inner) -> Option<Self::Inner>;
+
+    /// Take the value out of a key, leaving it uninitialized.
+    async fn take_singleton_keyed(key: &Self::Key) -> Option<Self::Inner>;
+
+    /// Reset a key to its uninitialized state, dropping its value.
+    ///
+    /// This is meant for tests and it is only available with the `test-util` feature.
+    #[cfg(feature = "test-util")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+    async fn reset_singleton_keyed(key: &Self::Key);
+}
+
+/// A keyed singleton that can be borrowed mutably with guards.
+///
+/// This is implemented by all keyed singletons with an async lock.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be borrowed mutably with a guard as a keyed singleton",
+    label = "`&mut self` guard singleton fns need a singleton with an async lock",
+    note = "singletons without a lock can't be mutated and singletons with a sync lock (`std_mutex` or `parking_lot`) can only be used by blocking functions"
+)]
+#[async_trait::async_trait]
+pub trait MutableGuardKeyedSingleton: GuardKeyedSingleton + MutableKeyedSingleton {
+    /// Guard that gives exclusive access to the inner value of a key (a SingletonWriteGuard).
+    type WriteGuard: DerefMut<Target = Self::Inner>;
+
+    /// Lock the singleton for a key and get a guard that gives exclusive access to its inner
+    /// value.
+    ///
+    /// This panics if the key has not been initialized.
+    async fn write_guard_keyed(key: &Self::Key) -> Self::WriteGuard;
+
+    /// Lock the singleton for a key and get a guard that gives exclusive access to its inner
+    /// value, or fail if the key has not been initialized.
+    async fn try_write_guard_keyed(key: &Self::Key) -> Result<Self::WriteGuard, SingletonError>;
+}
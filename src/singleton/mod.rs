@@ -5,7 +5,7 @@
 //! This example showcases how you can use the Singleton trait by leveraging procedural macros.
 //!
 //! ```
-//! # #[cfg(feature = "singleton")]
+//! # #[cfg(feature = "tokio-runtime")]
 //! # {
 //! # use blockz::prelude::*;
 //! # use std::error::Error;
@@ -62,11 +62,89 @@
 //! # Ok(())
 //! # }
 //! # }
-//! # #[cfg(not(feature = "singleton"))]
+//! # #[cfg(not(feature = "tokio-runtime"))]
 //! # fn main() {}
 //! ```
 
+#[cfg(feature = "tokio-runtime")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio-runtime")))]
+pub mod actor;
+#[cfg(feature = "singleton_agnostic")]
+#[cfg_attr(docsrs, doc(cfg(feature = "singleton_agnostic")))]
+pub mod agnostic;
+pub mod guard;
+pub mod keyed;
+#[doc(hidden)]
+pub mod route;
+pub mod scope;
+pub mod sharded;
+#[cfg(feature = "swap")]
+#[cfg_attr(docsrs, doc(cfg(feature = "swap")))]
+pub mod swap;
+
+pub use self::guard::SingletonReadGuard;
+pub use self::guard::SingletonWriteGuard;
+pub use self::keyed::GuardKeyedSingleton;
+pub use self::keyed::KeyedSingleton;
+pub use self::keyed::MutableGuardKeyedSingleton;
+pub use self::keyed::MutableKeyedSingleton;
+pub use self::scope::ScopeLocal;
+pub use self::sharded::ShardedSingleton;
+#[cfg(feature = "swap")]
+#[cfg_attr(docsrs, doc(cfg(feature = "swap")))]
+pub use self::swap::SwapLock;
+
+/// Watch channels, used by watched singletons (see WatchSingleton).
+#[cfg(feature = "tokio-runtime")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio-runtime")))]
+pub use tokio::sync::watch;
+
+/// Scope locals, used by the scopes of singletons (see Singleton::scope).
+#[doc(hidden)]
+pub use crate::scope_local;
+
+use std::error::Error;
+use std::fmt;
+use std::fmt::Display;
 use std::future::Future;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::time::Duration;
+
+/// Errors produced when using a singleton.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SingletonError {
+    /// The singleton (named by the contained type name) has not been initialized.
+    NotInitialized(&'static str),
+    /// The lazy initialization of the singleton (named by the contained type name) failed for
+    /// the contained reason.
+    InitFailed(&'static str, String),
+    /// The singleton (named by the contained type name) could not be used in time: either its
+    /// lock was not acquired within the lock timeout, or the call did not finish within its
+    /// timeout.
+    LockTimeout(&'static str),
+    /// The mailbox of the actor singleton (named by the contained type name) is full.
+    MailboxFull(&'static str),
+    /// The actor singleton (named by the contained type name) was stopped before it served the
+    /// use.
+    Stopped(&'static str),
+}
+
+impl Display for SingletonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotInitialized(name) => write!(f, "{}: singleton: not initialized", name),
+            Self::InitFailed(name, reason) => {
+                write!(f, "{}: singleton: initialization failed: {}", name, reason)
+            }
+            Self::LockTimeout(name) => write!(f, "{}: singleton: timed out", name),
+            Self::MailboxFull(name) => write!(f, "{}: singleton: mailbox full", name),
+            Self::Stopped(name) => write!(f, "{}: singleton: stopped", name),
+        }
+    }
+}
+
+impl Error for SingletonError {}
 
 /// A singleton.
 #[async_trait::async_trait]
@@ -79,7 +157,31 @@ pub trait Singleton {
     /// This can fail if the singleton has already been initialized.
     fn init_singleton(inner: Self::Inner) -> anyhow::Result<()>;
 
+    /// Check whether the singleton has been initialized.
+    fn is_initialized() -> bool;
+
+    /// Run a future with the singleton overridden by a value.
+    ///
+    /// Everything the future runs uses the value instead of the singleton, which is left
+    /// untouched, so tests with different singleton states can run concurrently. The override is
+    /// local to the future (see ScopeLocal), whichever executor polls it: it doesn't apply to the
+    /// tasks spawned by the future (including `spawn_blocking` closures) and changes made within
+    /// it are not published to the subscribers of watched singletons. Nested scopes override the
+    /// outer ones.
+    ///
+    /// The value is dropped when the future finishes or is dropped, even if it panics, unless
+    /// guards borrowed from the scope still hold it: they keep it until they are dropped. Since
+    /// the value doesn't live for `'static`, `get_singleton` panics within a scope of a singleton
+    /// without a lock (`read_guard` borrows the value there).
+    async fn scope<F>(inner: Self::Inner, fut: F) -> F::Output
+    where
+        F: Future + Send,
+        F::Output: Send;
+
     /// Use the singleton with an immutable reference.
+    ///
+    /// This panics if the singleton has not been initialized, or if it has a sync lock (it can
+    /// only be used by blocking functions).
     // F: Function to be run.
     // R: Function result.
     async fn use_singleton<F, R>(clojure: F) -> R
@@ -88,6 +190,9 @@ pub trait Singleton {
         R: Send;
 
     /// Use the singleton with an immutable reference and an argument.
+    ///
+    /// This panics if the singleton has not been initialized, or if it has a sync lock (it can
+    /// only be used by blocking functions).
     // F: Function to be run.
     // A: Function argument.
     // R: Function result.
@@ -97,7 +202,139 @@ pub trait Singleton {
         A: Send,
         R: Send;
 
+    /// Use the singleton with an immutable reference, or fail if it has not been initialized.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // R: Function result.
+    async fn try_use_singleton<F, R>(clojure: F) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFn<'c, Self::Inner, R> + Send,
+        R: Send;
+
+    /// Use the singleton with an immutable reference and an argument, or fail if it has not been
+    /// initialized.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // A: Function argument.
+    // R: Function result.
+    async fn try_use_singleton_with_arg<F, A, R>(clojure: F, arg: A) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFnWithArg<'c, Self::Inner, A, R> + Send,
+        A: Send,
+        R: Send;
+
+    /// Use the singleton with an immutable reference, or fail if the use (including the lock
+    /// acquisition) does not finish within the timeout.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // R: Function result.
+    async fn use_singleton_timeout<F, R>(
+        clojure: F,
+        timeout: Duration,
+    ) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFn<'c, Self::Inner, R> + Send,
+        R: Send;
+
+    /// Use the singleton with an immutable reference and an argument, or fail if the use
+    /// (including the lock acquisition) does not finish within the timeout.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // A: Function argument.
+    // R: Function result.
+    async fn use_singleton_with_arg_timeout<F, A, R>(
+        clojure: F,
+        arg: A,
+        timeout: Duration,
+    ) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFnWithArg<'c, Self::Inner, A, R> + Send,
+        A: Send,
+        R: Send;
+
+    /// Use the singleton with an immutable reference, blocking the current thread while waiting
+    /// for the lock.
+    ///
+    /// This panics if the singleton has not been initialized (lazily initialized singletons are
+    /// not initialized by blocking uses). Singletons with an async lock panic if this is called
+    /// within an asynchronous execution context.
+    // F: Function to be run.
+    // R: Function result.
+    fn use_singleton_blocking<F, R>(clojure: F) -> R
+    where
+        F: FnOnce(&Self::Inner) -> R;
+
+    /// Use the singleton with an immutable reference, blocking the current thread while waiting
+    /// for the lock, or fail if it has not been initialized.
+    // F: Function to be run.
+    // R: Function result.
+    fn try_use_singleton_blocking<F, R>(clojure: F) -> Result<R, SingletonError>
+    where
+        F: FnOnce(&Self::Inner) -> R;
+}
+
+/// A singleton (keyed or not) that can be used by async functions.
+///
+/// This is implemented by all singletons, except those with a sync lock (`lock = "std_mutex"` or
+/// `lock = "parking_lot"`), whose async use functions panic. Async singleton fns check it, so
+/// misusing them is a compile error.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be used by async singleton fns",
+    label = "async singleton fns need a singleton with an async lock",
+    note = "singletons with a sync lock (`std_mutex` or `parking_lot`) can only be used by blocking functions, use an async lock such as `#[singleton(lock = \"rwlock\")]`"
+)]
+pub trait AsyncSingleton {}
+
+/// A singleton that can be borrowed with guards.
+///
+/// This is implemented by all singletons, except those with a sync lock (`lock = "std_mutex"` or
+/// `lock = "parking_lot"`), which can only be used by blocking functions.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be borrowed with a guard",
+    label = "guard singleton fns need a singleton with an async lock",
+    note = "singletons with a sync lock (`std_mutex` or `parking_lot`) can only be used by blocking functions, use an async lock such as `#[singleton(lock = \"rwlock\")]`"
+)]
+#[async_trait::async_trait]
+pub trait GuardSingleton: Singleton {
+    /// Guard that gives shared access to the inner value (a SingletonReadGuard).
+    type ReadGuard: Deref<Target = Self::Inner>;
+
+    /// Lock the singleton and get a guard that gives shared access to its inner value.
+    ///
+    /// The singleton stays locked until the guard is dropped. Use SingletonReadGuard::map to
+    /// borrow a part of the inner value.
+    ///
+    /// This panics if the singleton has not been initialized.
+    async fn read_guard() -> Self::ReadGuard;
+
+    /// Lock the singleton and get a guard that gives shared access to its inner value, or fail if
+    /// it has not been initialized.
+    async fn try_read_guard() -> Result<Self::ReadGuard, SingletonError>;
+}
+
+/// A singleton that can be used with a mutable reference.
+///
+/// This is implemented by all singletons, except those without a lock (`lock = "none"`), which
+/// can't be used mutably, replaced or taken out: misusing them is a compile error.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be used mutably as a singleton",
+    label = "`&mut self` singleton fns need a singleton with a lock",
+    note = "singletons with `#[singleton(lock = \"none\")]` can't be mutated, use another lock such as `#[singleton(lock = \"swap\")]`"
+)]
+#[async_trait::async_trait]
+pub trait MutableSingleton: Singleton {
     /// Use the singleton with a mutable reference.
+    ///
+    /// This panics if the singleton has not been initialized, or if it has a sync lock (it can
+    /// only be used by blocking functions).
     // F: Function to be run.
     // R: Function result.
     async fn use_mut_singleton<F, R>(clojure: F) -> R
@@ -105,7 +342,10 @@ pub trait Singleton {
         F: for<'c> SingletonFnMut<'c, Self::Inner, R> + Send,
         R: Send;
 
-    /// Use the singleton with an immutable reference and an argument.
+    /// Use the singleton with a mutable reference and an argument.
+    ///
+    /// This panics if the singleton has not been initialized, or if it has a sync lock (it can
+    /// only be used by blocking functions).
     // F: Function to be run.
     // A: Function argument.
     // R: Function result.
@@ -114,6 +354,158 @@ pub trait Singleton {
         F: for<'c> SingletonFnMutWithArg<'c, Self::Inner, A, R> + Send,
         A: Send,
         R: Send;
+
+    /// Use the singleton with a mutable reference, or fail if it has not been initialized.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // R: Function result.
+    async fn try_use_mut_singleton<F, R>(clojure: F) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFnMut<'c, Self::Inner, R> + Send,
+        R: Send;
+
+    /// Use the singleton with a mutable reference and an argument, or fail if it has not been
+    /// initialized.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // A: Function argument.
+    // R: Function result.
+    async fn try_use_mut_singleton_with_arg<F, A, R>(
+        clojure: F,
+        arg: A,
+    ) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFnMutWithArg<'c, Self::Inner, A, R> + Send,
+        A: Send,
+        R: Send;
+
+    /// Use the singleton with a mutable reference, or fail if the use (including the lock
+    /// acquisition) does not finish within the timeout.
+    ///
+    /// A function that times out is dropped, which releases the lock.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // R: Function result.
+    async fn use_mut_singleton_timeout<F, R>(
+        clojure: F,
+        timeout: Duration,
+    ) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFnMut<'c, Self::Inner, R> + Send,
+        R: Send;
+
+    /// Use the singleton with a mutable reference and an argument, or fail if the use (including
+    /// the lock acquisition) does not finish within the timeout.
+    ///
+    /// A function that times out is dropped, which releases the lock.
+    ///
+    /// This panics if the singleton has a sync lock, since it can only be used by blocking
+    /// functions.
+    // F: Function to be run.
+    // A: Function argument.
+    // R: Function result.
+    async fn use_mut_singleton_with_arg_timeout<F, A, R>(
+        clojure: F,
+        arg: A,
+        timeout: Duration,
+    ) -> Result<R, SingletonError>
+    where
+        F: for<'c> SingletonFnMutWithArg<'c, Self::Inner, A, R> + Send,
+        A: Send,
+        R: Send;
+
+    /// Use the singleton with a mutable reference, blocking the current thread while waiting for
+    /// the lock.
+    ///
+    /// This panics if the singleton has not been initialized (lazily initialized singletons are
+    /// not initialized by blocking uses). Singletons with an async lock panic if this is called
+    /// within an asynchronous execution context.
+    // F: Function to be run.
+    // R: Function result.
+    fn use_mut_singleton_blocking<F, R>(clojure: F) -> R
+    where
+        F: FnOnce(&mut Self::Inner) -> R;
+
+    /// Use the singleton with a mutable reference, blocking the current thread while waiting for
+    /// the lock, or fail if it has not been initialized.
+    // F: Function to be run.
+    // R: Function result.
+    fn try_use_mut_singleton_blocking<F, R>(clojure: F) -> Result<R, SingletonError>
+    where
+        F: FnOnce(&mut Self::Inner) -> R;
+
+    /// Replace the value of the singleton, returning the old value.
+    ///
+    /// The singleton is initialized if it held no value. This waits for the uses of the singleton
+    /// that are in flight, which finish against the old value.
+    async fn replace_singleton(inner: Self::Inner) -> Option<Self::Inner>;
+
+    /// Take the value out of the singleton, leaving it uninitialized.
+    ///
+    /// This waits for the uses of the singleton that are in flight, which finish against the old
+    /// value. Lazily initialized singletons are initialized again by their next use.
+    async fn take_singleton() -> Option<Self::Inner>;
+
+    /// Reset the singleton to its uninitialized state, dropping its value.
+    ///
+    /// This is meant for tests that need a fresh singleton: unlike take_singleton, it also forgets
+    /// the last failed lazy initialization, so the next use doesn't back off. It is only available
+    /// with the `test-util` feature.
+    #[cfg(feature = "test-util")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+    async fn reset_singleton();
+}
+
+/// A singleton that can be borrowed mutably with guards.
+///
+/// This is implemented by all singletons with an async lock.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be borrowed mutably with a guard",
+    label = "`&mut self` guard singleton fns need a singleton with an async lock",
+    note = "singletons without a lock can't be mutated and singletons with a sync lock (`std_mutex` or `parking_lot`) can only be used by blocking functions"
+)]
+#[async_trait::async_trait]
+pub trait MutableGuardSingleton: GuardSingleton + MutableSingleton {
+    /// Guard that gives exclusive access to the inner value (a SingletonWriteGuard).
+    type WriteGuard: DerefMut<Target = Self::Inner>;
+
+    /// Lock the singleton and get a guard that gives exclusive access to its inner value.
+    ///
+    /// The singleton stays locked until the guard is dropped. Use SingletonWriteGuard::map to
+    /// borrow a part of the inner value.
+    ///
+    /// This panics if the singleton has not been initialized.
+    async fn write_guard() -> Self::WriteGuard;
+
+    /// Lock the singleton and get a guard that gives exclusive access to its inner value, or fail
+    /// if it has not been initialized.
+    async fn try_write_guard() -> Result<Self::WriteGuard, SingletonError>;
+}
+
+/// A singleton whose changes can be watched (`#[singleton(watch)]`).
+///
+/// The subscribers use the watch channels of tokio, so this requires the `tokio-runtime` feature.
+///
+/// The value of the singleton (a clone or a projection of it) is published after each change:
+/// its initialization, its replacement, the uses with a mutable reference and the release of its
+/// write guards. Subscribers get the latest value, which is None while the singleton holds no
+/// value.
+#[cfg(feature = "tokio-runtime")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio-runtime")))]
+pub trait WatchSingleton: Singleton {
+    /// Value published to the subscribers.
+    type Watched: Send + Sync;
+
+    /// Subscribe to the changes of the singleton.
+    ///
+    /// The current value is marked as seen, so `changed().await` waits for the next change.
+    fn subscribe() -> watch::Receiver<Option<Self::Watched>>;
 }
 
 /// Trait that defines the behaviour of a function that uses an immutable singleton.
@@ -139,7 +531,7 @@ impl<'i, I, R, F, FR> SingletonFn<'i, I, R> for F
 where
     I: 'i,
     F: FnOnce(&'i I) -> FR,
-    FR: Future<Output = R> + Send + 'i,
+    FR: Future<Output = R> + Send,
     R: Send,
 {
     /// The result of a singleton function (a Future).
@@ -151,6 +543,9 @@ where
 }
 
 /// Trait that defines the behaviour of a function that uses an immutable singleton and an argument.
+///
+/// The argument may hold references (such as `&str` or `(&str, &[u8])`), which only need to
+/// outlive the use of the singleton.
 // 'i: the lifetime of the inner value of the singleton.
 // I: the inner value of the singleton.
 // A: the argument to be consumed by the function.
@@ -171,12 +566,13 @@ where
 // A: the argument to be consumed by the function.
 // R: the result of the function.
 // F: the function to be executed.
-// FR: the future produced by the function F.
+// FR: the future produced by the function F. It is not required to outlive 'i, since it is
+//     awaited while the singleton is in use, so it can also borrow from the argument.
 impl<'i, A, I, R, F, FR> SingletonFnWithArg<'i, I, A, R> for F
 where
     I: 'i,
     F: FnOnce(&'i I, A) -> FR,
-    FR: Future<Output = R> + Send + 'i,
+    FR: Future<Output = R> + Send,
     A: Send,
     R: Send,
 {
@@ -211,7 +607,7 @@ impl<'i, I, R, F, FR> SingletonFnMut<'i, I, R> for F
 where
     I: 'i,
     F: FnMut(&'i mut I) -> FR,
-    FR: Future<Output = R> + Send + 'i,
+    FR: Future<Output = R> + Send,
     R: Send,
 {
     /// The result of a singleton function (a Future).
@@ -222,7 +618,10 @@ where
     }
 }
 
-/// Trait that defines the behaviour of a function that uses an immutable singleton and an argument.
+/// Trait that defines the behaviour of a function that uses a mutable singleton and an argument.
+///
+/// The argument may hold references (such as `&str` or `(&str, &[u8])`), which only need to
+/// outlive the use of the singleton.
 // 'i: the lifetime of the inner value of the singleton.
 // I: the inner value of the singleton.
 // A: the argument to be consumed by the function.
@@ -243,12 +642,13 @@ where
 // A: the argument to be consumed by the function.
 // R: the result of the function.
 // F: the function to be executed.
-// FR: the future produced by the function F.
+// FR: the future produced by the function F. It is not required to outlive 'i, since it is
+//     awaited while the singleton is in use, so it can also borrow from the argument.
 impl<'i, A, I, R, F, FR> SingletonFnMutWithArg<'i, I, A, R> for F
 where
     I: 'i,
     F: FnOnce(&'i mut I, A) -> FR,
-    FR: Future<Output = R> + Send + 'i,
+    FR: Future<Output = R> + Send,
     A: Send,
     R: Send,
 {
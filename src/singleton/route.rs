@@ -0,0 +1,795 @@
+//! Routing of singleton fns.
+//!
+//! Singleton fns are expanded without seeing the options of their singleton, so they don't know
+//! whether it is sharded. Each singleton fn routes its call instead: it passes its arguments,
+//! named at the type level, to `route_args`, which resolves to either
+//! [UnshardedRoute::route_args] (for singletons) or [ShardedSingleton::route_args] (for sharded
+//! singletons). The call then goes to the `*_keyed` function for the returned key: the shard of
+//! the `shard_by` argument or [Unsharded], whose functions forward to [Singleton].
+//!
+//! Actor singletons implement `ActorRoute` instead of [UnshardedRoute], whose `*_keyed` functions
+//! send the calls to the actor (see the actor module), so their arguments must be `'static`.
+//! `#[singleton]` implements one of them; a singleton that implements [Singleton] by hand needs an
+//! empty `impl UnshardedRoute for T {}` for its singleton fns.
+//!
+//! This module is used by the expansion of `#[singleton_fn]`.
+
+use std::future::Future;
+use std::marker::PhantomData;
+#[cfg(feature = "tokio-runtime")]
+use std::pin::Pin;
+use std::time::Duration;
+
+use super::AsyncSingleton;
+use super::GuardKeyedSingleton;
+use super::GuardSingleton;
+use super::KeyedSingleton;
+use super::MutableGuardKeyedSingleton;
+use super::MutableGuardSingleton;
+use super::MutableKeyedSingleton;
+use super::MutableSingleton;
+use super::Singleton;
+use super::SingletonError;
+use super::SingletonFn;
+use super::SingletonFnMut;
+use super::SingletonFnMutWithArg;
+use super::SingletonFnWithArg;
+
+#[cfg(feature = "tokio-runtime")]
+use super::actor::ActorCall;
+#[cfg(doc)]
+use super::ShardedSingleton;
+
+/// A character of an argument name: names are lists of characters, such as
+/// `(C<'i'>, (C<'d'>, ()))`.
+pub struct C<const C: char>;
+
+/// The name of a singleton that has no `shard_by` argument, which matches no argument.
+pub struct NoShardBy;
+
+/// The position of an argument: the head of the list.
+pub struct Here;
+
+/// The position of an argument: in the tail of the list.
+pub struct There<I>(PhantomData<I>);
+
+/// The arguments of a singleton fn, as a list of `((PhantomData<Name>, &Arg), Tail)` ending with
+/// `()`, in which the argument `Name` is at position `I`.
+#[diagnostic::on_unimplemented(
+    message = "the singleton fn doesn't have the `shard_by` argument of its sharded singleton",
+    label = "calls of sharded singleton fns are routed by the `shard_by` argument",
+    note = "name the argument with `#[singleton(shards = N, shard_by = \"name\")]` or override it with `#[singleton_fn(shard_by = \"name\")]`"
+)]
+pub trait ShardArg<Name, I> {
+    /// Type of the argument.
+    type Arg: ?Sized;
+
+    /// Get the argument.
+    fn shard_arg(&self) -> &Self::Arg;
+}
+
+impl<Name, A, T> ShardArg<Name, Here> for ((PhantomData<Name>, &A), T)
+where
+    A: ?Sized,
+{
+    type Arg = A;
+
+    fn shard_arg(&self) -> &A {
+        (self.0).1
+    }
+}
+
+impl<Name, Other, A, T, I> ShardArg<Name, There<I>> for ((PhantomData<Other>, &A), T)
+where
+    A: ?Sized,
+    T: ShardArg<Name, I>,
+{
+    type Arg = T::Arg;
+
+    fn shard_arg(&self) -> &T::Arg {
+        self.1.shard_arg()
+    }
+}
+
+/// The key of the calls to singletons that are not sharded.
+#[derive(Clone, Copy, Debug)]
+pub struct Unsharded;
+
+/// The routing of singletons that are not sharded, whose `*_keyed` functions forward to the
+/// functions of [Singleton] (or [MutableSingleton], for the mutable uses).
+pub trait UnshardedRoute: Singleton {
+    /// Route a call: singletons have a single value.
+    fn route_args<Args>(_args: &Args) -> Unsharded
+    where
+        Args: ?Sized,
+    {
+        Unsharded
+    }
+
+    /// Forward to [Singleton::use_singleton].
+    fn use_singleton_keyed<'r, F, R>(
+        _key: &'r Unsharded,
+        clojure: F,
+    ) -> impl Future<Output = R> + Send + 'r
+    where
+        F: for<'c> SingletonFn<'c, Self::Inner, R> + Send + 'r,
+        R: Send + 'r,
+        Self: 'r,
+    {
+        Self::use_singleton(clojure)
+    }
+
+    /// Forward to [Singleton::use_singleton_with_arg].
+    fn use_singleton_with_arg_keyed<'r, F, A, R>(
+        _key: &'r Unsharded,
+        clojure: F,
+        arg: A,
+    ) -> impl Future<Output = R> + Send + 'r
+    where
+        F: for<'c> SingletonFnWithArg<'c, Self::Inner, A, R> + Send + 'r,
+        A: Send + 'r,
+        R: Send + 'r,
+        Self: 'r,
+    {
+        Self::use_singleton_with_arg(clojure, arg)
+    }
+
+    /// Forward to [MutableSingleton::use_mut_singleton].
+    fn use_mut_singleton_keyed<'r, F, R>(
+        _key: &'r Unsharded,
+        clojure: F,
+    ) -> impl Future<Output = R> + Send + 'r
+    where
+        F: for<'c> SingletonFnMut<'c, Self::Inner, R> + Send + 'r,
+        R: Send + 'r,
+        Self: MutableSingleton + 'r,
+    {
+        Self::use_mut_singleton(clojure)
+    }
+
+    /// Forward to [MutableSingleton::use_mut_singleton_with_arg].
+    fn use_mut_singleton_with_arg_keyed<'r, F, A, R>(
+        _key: &'r Unsharded,
+        clojure: F,
+        arg: A,
+    ) -> impl Future<Output = R> + Send + 'r
+    where
+        F: for<'c> SingletonFnMutWithArg<'c, Self::Inner, A, R> + Send + 'r,
+        A: Send + 'r,
+        R: Send + 'r,
+        Self: MutableSingleton + 'r,
+    {
+        Self::use_mut_singleton_with_arg(clojure, arg)
+    }
+
+    /// Forward to [Singleton::try_use_singleton].
+    fn try_use_singleton_keyed<'r, F, R>(
+        _key: &'r Unsharded,
+        clojure: F,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send + 'r
+    where
+        F: for<'c> SingletonFn<'c, Self::Inner, R> + Send + 'r,
+        R: Send + 'r,
+        Self: 'r,
+    {
+        Self::try_use_singleton(clojure)
+    }
+
+    /// Forward to [Singleton::try_use_singleton_with_arg].
+    fn try_use_singleton_with_arg_keyed<'r, F, A, R>(
+        _key: &'r Unsharded,
+        clojure: F,
+        arg: A,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send + 'r
+    where
+        F: for<'c> SingletonFnWithArg<'c, Self::Inner, A, R> + Send + 'r,
+        A: Send + 'r,
+        R: Send + 'r,
+        Self: 'r,
+    {
+        Self::try_use_singleton_with_arg(clojure, arg)
+    }
+
+    /// Forward to [MutableSingleton::try_use_mut_singleton].
+    fn try_use_mut_singleton_keyed<'r, F, R>(
+        _key: &'r Unsharded,
+        clojure: F,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send + 'r
+    where
+        F: for<'c> SingletonFnMut<'c, Self::Inner, R> + Send + 'r,
+        R: Send + 'r,
+        Self: MutableSingleton + 'r,
+    {
+        Self::try_use_mut_singleton(clojure)
+    }
+
+    /// Forward to [MutableSingleton::try_use_mut_singleton_with_arg].
+    fn try_use_mut_singleton_with_arg_keyed<'r, F, A, R>(
+        _key: &'r Unsharded,
+        clojure: F,
+        arg: A,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send + 'r
+    where
+        F: for<'c> SingletonFnMutWithArg<'c, Self::Inner, A, R> + Send + 'r,
+        A: Send + 'r,
+        R: Send + 'r,
+        Self: MutableSingleton + 'r,
+    {
+        Self::try_use_mut_singleton_with_arg(clojure, arg)
+    }
+
+    /// Forward to [Singleton::use_singleton_timeout].
+    fn use_singleton_timeout_keyed<'r, F, R>(
+        _key: &'r Unsharded,
+        clojure: F,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send + 'r
+    where
+        F: for<'c> SingletonFn<'c, Self::Inner, R> + Send + 'r,
+        R: Send + 'r,
+        Self: 'r,
+    {
+        Self::use_singleton_timeout(clojure, timeout)
+    }
+
+    /// Forward to [Singleton::use_singleton_with_arg_timeout].
+    fn use_singleton_with_arg_timeout_keyed<'r, F, A, R>(
+        _key: &'r Unsharded,
+        clojure: F,
+        arg: A,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send + 'r
+    where
+        F: for<'c> SingletonFnWithArg<'c, Self::Inner, A, R> + Send + 'r,
+        A: Send + 'r,
+        R: Send + 'r,
+        Self: 'r,
+    {
+        Self::use_singleton_with_arg_timeout(clojure, arg, timeout)
+    }
+
+    /// Forward to [MutableSingleton::use_mut_singleton_timeout].
+    fn use_mut_singleton_timeout_keyed<'r, F, R>(
+        _key: &'r Unsharded,
+        clojure: F,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send + 'r
+    where
+        F: for<'c> SingletonFnMut<'c, Self::Inner, R> + Send + 'r,
+        R: Send + 'r,
+        Self: MutableSingleton + 'r,
+    {
+        Self::use_mut_singleton_timeout(clojure, timeout)
+    }
+
+    /// Forward to [MutableSingleton::use_mut_singleton_with_arg_timeout].
+    fn use_mut_singleton_with_arg_timeout_keyed<'r, F, A, R>(
+        _key: &'r Unsharded,
+        clojure: F,
+        arg: A,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send + 'r
+    where
+        F: for<'c> SingletonFnMutWithArg<'c, Self::Inner, A, R> + Send + 'r,
+        A: Send + 'r,
+        R: Send + 'r,
+        Self: MutableSingleton + 'r,
+    {
+        Self::use_mut_singleton_with_arg_timeout(clojure, arg, timeout)
+    }
+
+    /// Forward to [Singleton::use_singleton_blocking].
+    fn use_singleton_blocking_keyed<F, R>(_key: &Unsharded, clojure: F) -> R
+    where
+        F: FnOnce(&Self::Inner) -> R,
+    {
+        Self::use_singleton_blocking(clojure)
+    }
+
+    /// Forward to [MutableSingleton::use_mut_singleton_blocking].
+    fn use_mut_singleton_blocking_keyed<F, R>(_key: &Unsharded, clojure: F) -> R
+    where
+        F: FnOnce(&mut Self::Inner) -> R,
+        Self: MutableSingleton,
+    {
+        Self::use_mut_singleton_blocking(clojure)
+    }
+
+    /// Forward to [Singleton::try_use_singleton_blocking].
+    fn try_use_singleton_blocking_keyed<F, R>(
+        _key: &Unsharded,
+        clojure: F,
+    ) -> Result<R, SingletonError>
+    where
+        F: FnOnce(&Self::Inner) -> R,
+    {
+        Self::try_use_singleton_blocking(clojure)
+    }
+
+    /// Forward to [MutableSingleton::try_use_mut_singleton_blocking].
+    fn try_use_mut_singleton_blocking_keyed<F, R>(
+        _key: &Unsharded,
+        clojure: F,
+    ) -> Result<R, SingletonError>
+    where
+        F: FnOnce(&mut Self::Inner) -> R,
+        Self: MutableSingleton,
+    {
+        Self::try_use_mut_singleton_blocking(clojure)
+    }
+
+    /// Forward to [GuardSingleton::read_guard].
+    fn read_guard_keyed<'r>(
+        _key: &'r Unsharded,
+    ) -> impl Future<Output = <Self as GuardSingleton>::ReadGuard> + Send + 'r
+    where
+        Self: GuardSingleton + 'r,
+    {
+        Self::read_guard()
+    }
+
+    /// Forward to [GuardSingleton::try_read_guard].
+    fn try_read_guard_keyed<'r>(
+        _key: &'r Unsharded,
+    ) -> impl Future<Output = Result<<Self as GuardSingleton>::ReadGuard, SingletonError>> + Send + 'r
+    where
+        Self: GuardSingleton + 'r,
+    {
+        Self::try_read_guard()
+    }
+
+    /// Forward to [MutableGuardSingleton::write_guard].
+    fn write_guard_keyed<'r>(
+        _key: &'r Unsharded,
+    ) -> impl Future<Output = <Self as MutableGuardSingleton>::WriteGuard> + Send + 'r
+    where
+        Self: MutableGuardSingleton + 'r,
+    {
+        Self::write_guard()
+    }
+
+    /// Forward to [MutableGuardSingleton::try_write_guard].
+    fn try_write_guard_keyed<'r>(
+        _key: &'r Unsharded,
+    ) -> impl Future<Output = Result<<Self as MutableGuardSingleton>::WriteGuard, SingletonError>>
+           + Send
+           + 'r
+    where
+        Self: MutableGuardSingleton + 'r,
+    {
+        Self::try_write_guard()
+    }
+}
+
+/// The routing of actor singletons, whose `*_keyed` functions send the calls to the actor.
+///
+/// Guards can't be sent, so they forward to [GuardSingleton] and lease the value instead.
+#[cfg(feature = "tokio-runtime")]
+pub trait ActorRoute: Singleton<Inner: Send> {
+    /// Send a call to the actor, bounded by the timeout if there is one.
+    fn call_actor<C>(
+        call: C,
+        timeout: Option<Duration>,
+    ) -> impl Future<Output = Result<C::Output, SingletonError>> + Send + 'static
+    where
+        C: ActorCall<Self::Inner>;
+
+    /// Route a call: actor singletons have a single value.
+    fn route_args<Args>(_args: &Args) -> Unsharded
+    where
+        Args: ?Sized,
+    {
+        Unsharded
+    }
+
+    /// Send a call of a [SingletonFn] to the actor.
+    fn use_singleton_keyed<F, R>(_key: &Unsharded, clojure: F) -> impl Future<Output = R> + Send
+    where
+        F: for<'c> SingletonFn<'c, Self::Inner, R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let call = Self::call_actor(ActorFn(clojure, PhantomData), None);
+        async move { call.await.unwrap_or_else(|err| panic!("{}", err)) }
+    }
+
+    /// Send a call of a [SingletonFnWithArg] to the actor.
+    fn use_singleton_with_arg_keyed<F, A, R>(
+        _key: &Unsharded,
+        clojure: F,
+        arg: A,
+    ) -> impl Future<Output = R> + Send
+    where
+        F: for<'c> SingletonFnWithArg<'c, Self::Inner, A, R> + Send + 'static,
+        A: Send + 'static,
+        R: Send + 'static,
+    {
+        let call = Self::call_actor(ActorFnWithArg(clojure, arg, PhantomData), None);
+        async move { call.await.unwrap_or_else(|err| panic!("{}", err)) }
+    }
+
+    /// Send a call of a [SingletonFnMut] to the actor.
+    fn use_mut_singleton_keyed<F, R>(_key: &Unsharded, clojure: F) -> impl Future<Output = R> + Send
+    where
+        F: for<'c> SingletonFnMut<'c, Self::Inner, R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let call = Self::call_actor(ActorFnMut(clojure, PhantomData), None);
+        async move { call.await.unwrap_or_else(|err| panic!("{}", err)) }
+    }
+
+    /// Send a call of a [SingletonFnMutWithArg] to the actor.
+    fn use_mut_singleton_with_arg_keyed<F, A, R>(
+        _key: &Unsharded,
+        clojure: F,
+        arg: A,
+    ) -> impl Future<Output = R> + Send
+    where
+        F: for<'c> SingletonFnMutWithArg<'c, Self::Inner, A, R> + Send + 'static,
+        A: Send + 'static,
+        R: Send + 'static,
+    {
+        let call = Self::call_actor(ActorFnMutWithArg(clojure, arg, PhantomData), None);
+        async move { call.await.unwrap_or_else(|err| panic!("{}", err)) }
+    }
+
+    /// Send a call of a [SingletonFn] to the actor, failing instead of panicking.
+    fn try_use_singleton_keyed<F, R>(
+        _key: &Unsharded,
+        clojure: F,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send
+    where
+        F: for<'c> SingletonFn<'c, Self::Inner, R> + Send + 'static,
+        R: Send + 'static,
+    {
+        Self::call_actor(ActorFn(clojure, PhantomData), None)
+    }
+
+    /// Send a call of a [SingletonFnWithArg] to the actor, failing instead of panicking.
+    fn try_use_singleton_with_arg_keyed<F, A, R>(
+        _key: &Unsharded,
+        clojure: F,
+        arg: A,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send
+    where
+        F: for<'c> SingletonFnWithArg<'c, Self::Inner, A, R> + Send + 'static,
+        A: Send + 'static,
+        R: Send + 'static,
+    {
+        Self::call_actor(ActorFnWithArg(clojure, arg, PhantomData), None)
+    }
+
+    /// Send a call of a [SingletonFnMut] to the actor, failing instead of panicking.
+    fn try_use_mut_singleton_keyed<F, R>(
+        _key: &Unsharded,
+        clojure: F,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send
+    where
+        F: for<'c> SingletonFnMut<'c, Self::Inner, R> + Send + 'static,
+        R: Send + 'static,
+    {
+        Self::call_actor(ActorFnMut(clojure, PhantomData), None)
+    }
+
+    /// Send a call of a [SingletonFnMutWithArg] to the actor, failing instead of panicking.
+    fn try_use_mut_singleton_with_arg_keyed<F, A, R>(
+        _key: &Unsharded,
+        clojure: F,
+        arg: A,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send
+    where
+        F: for<'c> SingletonFnMutWithArg<'c, Self::Inner, A, R> + Send + 'static,
+        A: Send + 'static,
+        R: Send + 'static,
+    {
+        Self::call_actor(ActorFnMutWithArg(clojure, arg, PhantomData), None)
+    }
+
+    /// Send a call of a [SingletonFn] to the actor, failing if it does not finish in time.
+    fn use_singleton_timeout_keyed<F, R>(
+        _key: &Unsharded,
+        clojure: F,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send
+    where
+        F: for<'c> SingletonFn<'c, Self::Inner, R> + Send + 'static,
+        R: Send + 'static,
+    {
+        Self::call_actor(ActorFn(clojure, PhantomData), Some(timeout))
+    }
+
+    /// Send a call of a [SingletonFnWithArg] to the actor, failing if it does not finish in time.
+    fn use_singleton_with_arg_timeout_keyed<F, A, R>(
+        _key: &Unsharded,
+        clojure: F,
+        arg: A,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send
+    where
+        F: for<'c> SingletonFnWithArg<'c, Self::Inner, A, R> + Send + 'static,
+        A: Send + 'static,
+        R: Send + 'static,
+    {
+        Self::call_actor(ActorFnWithArg(clojure, arg, PhantomData), Some(timeout))
+    }
+
+    /// Send a call of a [SingletonFnMut] to the actor, failing if it does not finish in time.
+    fn use_mut_singleton_timeout_keyed<F, R>(
+        _key: &Unsharded,
+        clojure: F,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send
+    where
+        F: for<'c> SingletonFnMut<'c, Self::Inner, R> + Send + 'static,
+        R: Send + 'static,
+    {
+        Self::call_actor(ActorFnMut(clojure, PhantomData), Some(timeout))
+    }
+
+    /// Send a call of a [SingletonFnMutWithArg] to the actor, failing if it does not finish in
+    /// time.
+    fn use_mut_singleton_with_arg_timeout_keyed<F, A, R>(
+        _key: &Unsharded,
+        clojure: F,
+        arg: A,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<R, SingletonError>> + Send
+    where
+        F: for<'c> SingletonFnMutWithArg<'c, Self::Inner, A, R> + Send + 'static,
+        A: Send + 'static,
+        R: Send + 'static,
+    {
+        Self::call_actor(ActorFnMutWithArg(clojure, arg, PhantomData), Some(timeout))
+    }
+
+    /// Send a blocking call to the actor and block the current thread until it finishes.
+    fn use_singleton_blocking_keyed<F, R>(key: &Unsharded, clojure: F) -> R
+    where
+        F: FnOnce(&Self::Inner) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        Self::try_use_singleton_blocking_keyed(key, clojure).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Send a mutable blocking call to the actor and block the current thread until it finishes.
+    fn use_mut_singleton_blocking_keyed<F, R>(key: &Unsharded, clojure: F) -> R
+    where
+        F: FnOnce(&mut Self::Inner) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        Self::try_use_mut_singleton_blocking_keyed(key, clojure)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Send a blocking call to the actor and block the current thread until it finishes, failing
+    /// instead of panicking.
+    fn try_use_singleton_blocking_keyed<F, R>(
+        _key: &Unsharded,
+        clojure: F,
+    ) -> Result<R, SingletonError>
+    where
+        F: FnOnce(&Self::Inner) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        block_on(Self::call_actor(ActorBlockingFn(clojure), None))
+    }
+
+    /// Send a mutable blocking call to the actor and block the current thread until it finishes,
+    /// failing instead of panicking.
+    fn try_use_mut_singleton_blocking_keyed<F, R>(
+        _key: &Unsharded,
+        clojure: F,
+    ) -> Result<R, SingletonError>
+    where
+        F: FnOnce(&mut Self::Inner) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        block_on(Self::call_actor(ActorBlockingFnMut(clojure), None))
+    }
+
+    /// Forward to [GuardSingleton::read_guard].
+    fn read_guard_keyed<'r>(
+        _key: &'r Unsharded,
+    ) -> impl Future<Output = <Self as GuardSingleton>::ReadGuard> + Send + 'r
+    where
+        Self: GuardSingleton + 'r,
+    {
+        Self::read_guard()
+    }
+
+    /// Forward to [GuardSingleton::try_read_guard].
+    fn try_read_guard_keyed<'r>(
+        _key: &'r Unsharded,
+    ) -> impl Future<Output = Result<<Self as GuardSingleton>::ReadGuard, SingletonError>> + Send + 'r
+    where
+        Self: GuardSingleton + 'r,
+    {
+        Self::try_read_guard()
+    }
+
+    /// Forward to [MutableGuardSingleton::write_guard].
+    fn write_guard_keyed<'r>(
+        _key: &'r Unsharded,
+    ) -> impl Future<Output = <Self as MutableGuardSingleton>::WriteGuard> + Send + 'r
+    where
+        Self: MutableGuardSingleton + 'r,
+    {
+        Self::write_guard()
+    }
+
+    /// Forward to [MutableGuardSingleton::try_write_guard].
+    fn try_write_guard_keyed<'r>(
+        _key: &'r Unsharded,
+    ) -> impl Future<Output = Result<<Self as MutableGuardSingleton>::WriteGuard, SingletonError>>
+           + Send
+           + 'r
+    where
+        Self: MutableGuardSingleton + 'r,
+    {
+        Self::try_write_guard()
+    }
+}
+
+/// Block the current thread on a call of an actor, like [Actor::blocking_call].
+///
+/// [Actor::blocking_call]: super::actor::Actor::blocking_call
+#[cfg(feature = "tokio-runtime")]
+fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    tokio::runtime::Handle::try_current()
+        .expect("actor: must be used within a tokio runtime")
+        .block_on(future)
+}
+
+/// The boxed future of an actor call.
+#[cfg(feature = "tokio-runtime")]
+type CallFuture<'v, R> = Pin<Box<dyn Future<Output = R> + Send + 'v>>;
+
+/// A call of a [SingletonFn], sent to an actor.
+#[cfg(feature = "tokio-runtime")]
+pub struct ActorFn<F, R>(F, PhantomData<fn() -> R>);
+
+#[cfg(feature = "tokio-runtime")]
+impl<T, F, R> ActorCall<T> for ActorFn<F, R>
+where
+    T: Send,
+    F: for<'c> SingletonFn<'c, T, R> + Send + 'static,
+    R: Send + 'static,
+{
+    type Output = R;
+
+    const MUTABLE: bool = false;
+
+    fn call(self, value: &mut T) -> CallFuture<'_, R> {
+        Box::pin(async move { self.0.call_once(value).await })
+    }
+}
+
+/// A call of a [SingletonFnWithArg], sent to an actor.
+#[cfg(feature = "tokio-runtime")]
+pub struct ActorFnWithArg<F, A, R>(F, A, PhantomData<fn() -> R>);
+
+#[cfg(feature = "tokio-runtime")]
+impl<T, F, A, R> ActorCall<T> for ActorFnWithArg<F, A, R>
+where
+    T: Send,
+    F: for<'c> SingletonFnWithArg<'c, T, A, R> + Send + 'static,
+    A: Send + 'static,
+    R: Send + 'static,
+{
+    type Output = R;
+
+    const MUTABLE: bool = false;
+
+    fn call(self, value: &mut T) -> CallFuture<'_, R> {
+        Box::pin(async move { self.0.call_once(value, self.1).await })
+    }
+}
+
+/// A call of a [SingletonFnMut], sent to an actor.
+#[cfg(feature = "tokio-runtime")]
+pub struct ActorFnMut<F, R>(F, PhantomData<fn() -> R>);
+
+#[cfg(feature = "tokio-runtime")]
+impl<T, F, R> ActorCall<T> for ActorFnMut<F, R>
+where
+    T: Send,
+    F: for<'c> SingletonFnMut<'c, T, R> + Send + 'static,
+    R: Send + 'static,
+{
+    type Output = R;
+
+    const MUTABLE: bool = true;
+
+    fn call(self, value: &mut T) -> CallFuture<'_, R> {
+        Box::pin(async move { self.0.call_once(value).await })
+    }
+}
+
+/// A call of a [SingletonFnMutWithArg], sent to an actor.
+#[cfg(feature = "tokio-runtime")]
+pub struct ActorFnMutWithArg<F, A, R>(F, A, PhantomData<fn() -> R>);
+
+#[cfg(feature = "tokio-runtime")]
+impl<T, F, A, R> ActorCall<T> for ActorFnMutWithArg<F, A, R>
+where
+    T: Send,
+    F: for<'c> SingletonFnMutWithArg<'c, T, A, R> + Send + 'static,
+    A: Send + 'static,
+    R: Send + 'static,
+{
+    type Output = R;
+
+    const MUTABLE: bool = true;
+
+    fn call(self, value: &mut T) -> CallFuture<'_, R> {
+        Box::pin(async move { self.0.call_once(value, self.1).await })
+    }
+}
+
+/// A blocking call, sent to an actor.
+#[cfg(feature = "tokio-runtime")]
+pub struct ActorBlockingFn<F>(F);
+
+#[cfg(feature = "tokio-runtime")]
+impl<T, F, R> ActorCall<T> for ActorBlockingFn<F>
+where
+    F: FnOnce(&T) -> R + Send + 'static,
+    R: Send + 'static,
+{
+    type Output = R;
+
+    const MUTABLE: bool = false;
+
+    fn call(self, value: &mut T) -> CallFuture<'_, R> {
+        Box::pin(std::future::ready((self.0)(value)))
+    }
+}
+
+/// A mutable blocking call, sent to an actor.
+#[cfg(feature = "tokio-runtime")]
+pub struct ActorBlockingFnMut<F>(F);
+
+#[cfg(feature = "tokio-runtime")]
+impl<T, F, R> ActorCall<T> for ActorBlockingFnMut<F>
+where
+    F: FnOnce(&mut T) -> R + Send + 'static,
+    R: Send + 'static,
+{
+    type Output = R;
+
+    const MUTABLE: bool = true;
+
+    fn call(self, value: &mut T) -> CallFuture<'_, R> {
+        Box::pin(std::future::ready((self.0)(value)))
+    }
+}
+
+/// A key whose calls can use the singleton `T` mutably.
+pub trait MutableKey<T: ?Sized> {}
+
+impl<T> MutableKey<T> for Unsharded where T: MutableSingleton + ?Sized {}
+
+impl<T> MutableKey<T> for usize where T: MutableKeyedSingleton<Key = usize> + ?Sized {}
+
+/// A key whose calls can use the singleton `T` asynchronously.
+pub trait AsyncKey<T: ?Sized> {}
+
+impl<T> AsyncKey<T> for Unsharded where T: AsyncSingleton + ?Sized {}
+
+impl<T> AsyncKey<T> for usize where T: AsyncSingleton + KeyedSingleton<Key = usize> + ?Sized {}
+
+/// A key whose calls can borrow the singleton `T` with guards.
+pub trait GuardKey<T: ?Sized> {}
+
+impl<T> GuardKey<T> for Unsharded where T: GuardSingleton + ?Sized {}
+
+impl<T> GuardKey<T> for usize where T: GuardKeyedSingleton<Key = usize> + ?Sized {}
+
+/// A key whose calls can borrow the singleton `T` mutably with guards.
+pub trait MutableGuardKey<T: ?Sized> {}
+
+impl<T> MutableGuardKey<T> for Unsharded where T: MutableGuardSingleton + ?Sized {}
+
+impl<T> MutableGuardKey<T> for usize where T: MutableGuardKeyedSingleton<Key = usize> + ?Sized {}
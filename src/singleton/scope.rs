@@ -0,0 +1,223 @@
+//! Scopes that override singletons.
+//!
+//! A [ScopeLocal] holds a value while a future runs, like the task-locals of tokio, but it doesn't
+//! depend on the executor: the value is moved into a thread-local each time the future is polled
+//! and moved back out when the poll returns, so everything the future runs sees it, no matter which
+//! executor (or thread) polls the future.
+//!
+//! This is what Singleton::scope uses to override the entry of a singleton: the scope local holds
+//! the entry of the scope, and [Entry] gets the entry in effect.
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::mem;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use std::thread::LocalKey;
+
+/// Declare a [ScopeLocal].
+///
+/// ```
+/// # #[cfg(feature = "singleton")]
+/// # {
+/// blockz::singleton::scope_local! {
+///     static LEVEL: usize;
+/// }
+///
+/// let level = LEVEL.scope(1, async { LEVEL.try_with(|level| *level) });
+/// assert_eq!(futures_lite::future::block_on(level), Ok(1));
+/// assert!(LEVEL.try_with(|level| *level).is_err());
+/// # }
+/// ```
+#[macro_export]
+#[doc(hidden)]
+macro_rules! scope_local {
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty; $($rest:tt)*) => {
+        $(#[$attr])*
+        $vis const $name: $crate::singleton::scope::ScopeLocal<$ty> = {
+            ::std::thread_local! {
+                static SCOPE: ::std::cell::RefCell<Option<$ty>> = ::std::cell::RefCell::new(None);
+            }
+            $crate::singleton::scope::ScopeLocal { inner: SCOPE }
+        };
+        $crate::scope_local!($($rest)*);
+    };
+    () => {};
+}
+
+/// A value that is set while a future runs (see [ScopeLocal::scope]).
+///
+/// Scope locals are declared with the `scope_local!` macro.
+pub struct ScopeLocal<T: 'static> {
+    #[doc(hidden)]
+    pub inner: LocalKey<RefCell<Option<T>>>,
+}
+
+/// Error returned by [ScopeLocal::try_with] outside of a scope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScopeAccessError(());
+
+impl fmt::Display for ScopeAccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("scope local: not within a scope")
+    }
+}
+
+impl Error for ScopeAccessError {}
+
+impl<T: 'static> ScopeLocal<T> {
+    /// Run a future with the scope local set to a value.
+    ///
+    /// Nested scopes override the outer ones. The value is dropped with the returned future.
+    pub fn scope<F>(&'static self, value: T, fut: F) -> Scoped<T, F>
+    where
+        F: Future,
+    {
+        Scoped {
+            local: self,
+            value: Some(value),
+            fut,
+        }
+    }
+
+    /// Run a function with a reference to the value of the current scope, or fail if there is no
+    /// scope.
+    pub fn try_with<F, R>(&'static self, f: F) -> Result<R, ScopeAccessError>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        self.inner
+            .try_with(|cell| cell.borrow().as_ref().map(f))
+            .ok()
+            .flatten()
+            .ok_or(ScopeAccessError(()))
+    }
+
+    /// Run a function with the value of a slot moved into the scope local.
+    ///
+    /// The value is moved back into the slot when the function returns, even if it panics.
+    fn enter<F, R>(&'static self, slot: &mut Option<T>, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        struct Restore<'s, T: 'static> {
+            local: &'static LocalKey<RefCell<Option<T>>>,
+            slot: &'s mut Option<T>,
+        }
+
+        impl<'s, T: 'static> Drop for Restore<'s, T> {
+            fn drop(&mut self) {
+                self.local
+                    .with(|cell| mem::swap(self.slot, &mut *cell.borrow_mut()));
+            }
+        }
+
+        self.inner
+            .with(|cell| mem::swap(slot, &mut *cell.borrow_mut()));
+        let _restore = Restore {
+            local: &self.inner,
+            slot,
+        };
+        f()
+    }
+}
+
+/// The entry of a singleton in effect: its static, or the entry of the scope that overrides it.
+///
+/// The entry of a scope is dropped (with the value of the scope) when the scope and the uses that
+/// hold it are done.
+pub enum Entry<T: 'static> {
+    /// The static of the singleton.
+    Static(&'static T),
+    /// The entry of a scope.
+    Scoped(Arc<T>),
+}
+
+impl<T: 'static> Entry<T> {
+    /// Get the entry in effect: the entry of the current scope, or the static.
+    pub fn current(local: &'static ScopeLocal<Arc<T>>, static_entry: &'static T) -> Self {
+        local
+            .try_with(|entry| Self::Scoped(entry.clone()))
+            .unwrap_or(Self::Static(static_entry))
+    }
+
+    /// Get the static, if the entry is not the entry of a scope.
+    pub fn as_static(&self) -> Option<&'static T> {
+        match self {
+            Self::Static(entry) => Some(entry),
+            Self::Scoped(_) => None,
+        }
+    }
+
+    /// Borrow the entry for the 'static lifetime, along with the owner of the entry of a scope.
+    ///
+    /// # Safety
+    ///
+    /// The reference must not be used after the owner is dropped, such as by keeping both in a
+    /// guard (see `SingletonReadGuard::with_owner`).
+    #[doc(hidden)]
+    pub unsafe fn into_static(self) -> (&'static T, Option<Arc<T>>) {
+        match self {
+            Self::Static(entry) => (entry, None),
+            Self::Scoped(entry) => (unsafe { &*Arc::as_ptr(&entry) }, Some(entry)),
+        }
+    }
+}
+
+impl<T: 'static> Deref for Entry<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            Self::Static(entry) => entry,
+            Self::Scoped(entry) => entry,
+        }
+    }
+}
+
+impl<T: 'static> fmt::Debug for Entry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Static(_) => f.write_str("Entry::Static"),
+            Self::Scoped(_) => f.write_str("Entry::Scoped"),
+        }
+    }
+}
+
+impl<T: 'static> fmt::Debug for ScopeLocal<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScopeLocal").finish_non_exhaustive()
+    }
+}
+
+/// A future that runs with a scope local set to a value (see [ScopeLocal::scope]).
+pub struct Scoped<T: 'static, F> {
+    local: &'static ScopeLocal<T>,
+    value: Option<T>,
+    fut: F,
+}
+
+impl<T: 'static, F> Future for Scoped<T, F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // the future is pinned with the scope, while the value is moved in and out of it
+        let this = unsafe { self.get_unchecked_mut() };
+        let fut = unsafe { Pin::new_unchecked(&mut this.fut) };
+        this.local.enter(&mut this.value, || fut.poll(cx))
+    }
+}
+
+impl<T: 'static, F> fmt::Debug for Scoped<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scoped").finish_non_exhaustive()
+    }
+}
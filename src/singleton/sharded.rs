@@ -0,0 +1,83 @@
+//! Sharded singletons.
+//!
+//! A sharded singleton (`#[singleton(shards = 16)]`) splits its state into a number of shards,
+//! each with its own lock, so that uses of different shards don't contend for the same lock. This
+//! suits singletons such as counters and metrics, whose state can be partitioned and aggregated.
+//!
+//! A sharded singleton is a [KeyedSingleton] whose keys are the indices of the shards. Singleton
+//! fns route their calls to the shard of the argument named by the singleton, which is hashed by
+//! [ShardedSingleton::shard_of]: `#[singleton(shards = 16, shard_by = "user_id")]`. A singleton fn
+//! can route its calls by another argument with `#[singleton_fn(shard_by = "name")]`, and singleton
+//! fns without the argument don't compile. Whole-state operations lock all the shards with
+//! [ShardedSingleton::read_shards].
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+
+use super::route::ShardArg;
+use super::GuardKeyedSingleton;
+use super::KeyedSingleton;
+
+/// A singleton split into shards, each with its own lock.
+#[async_trait::async_trait]
+pub trait ShardedSingleton: KeyedSingleton<Key = usize> {
+    /// Number of shards.
+    const SHARDS: usize;
+
+    /// Name of the `shard_by` argument, as a type (see the route module).
+    #[doc(hidden)]
+    type ShardBy;
+
+    /// Get the shard of a value, such as the argument of a singleton fn.
+    ///
+    /// Equal values always get the same shard.
+    fn shard_of<H>(value: &H) -> usize
+    where
+        H: Hash + ?Sized,
+    {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        (hasher.finish() % Self::SHARDS as u64) as usize
+    }
+
+    /// Get the shard of a call, from the `shard_by` argument of the singleton fn.
+    #[doc(hidden)]
+    fn route_args<Args, I>(args: &Args) -> usize
+    where
+        Args: ShardArg<Self::ShardBy, I> + ?Sized,
+        Args::Arg: Hash,
+    {
+        Self::shard_of(args.shard_arg())
+    }
+
+    /// Initialize all the shards, with the value created for each shard index.
+    ///
+    /// This can fail if a shard has already been initialized.
+    fn init_shards<F>(mut init: F) -> anyhow::Result<()>
+    where
+        F: FnMut(usize) -> Self::Inner,
+    {
+        for shard in 0..Self::SHARDS {
+            Self::init_singleton_keyed(&shard, init(shard))?;
+        }
+        Ok(())
+    }
+
+    /// Lock all the shards and get a guard for each of them, in the order of the shards.
+    ///
+    /// The shards are locked one at a time and stay locked until their guards are dropped, so the
+    /// guards give a consistent view of the whole state. This panics if a shard has not been
+    /// initialized.
+    async fn read_shards() -> Vec<<Self as GuardKeyedSingleton>::ReadGuard>
+    where
+        Self: GuardKeyedSingleton,
+        <Self as GuardKeyedSingleton>::ReadGuard: Send,
+    {
+        let mut guards = Vec::with_capacity(Self::SHARDS);
+        for shard in 0..Self::SHARDS {
+            guards.push(Self::read_guard_keyed(&shard).await);
+        }
+        guards
+    }
+}
@@ -0,0 +1,127 @@
+//! Lock for read-mostly singletons.
+//!
+//! A [SwapLock] holds its value in an [Arc] that is swapped atomically: readers load a snapshot
+//! of the value without locking, while writers (serialized by an async mutex) modify a clone of
+//! the value and publish it when they are done.
+//!
+//! This is the lock behind `#[singleton(lock = "swap")]`.
+
+use arc_swap::ArcSwap;
+
+use async_lock::Mutex;
+use async_lock::MutexGuard;
+
+use std::fmt;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::Arc;
+use std::thread;
+
+/// A lock whose readers get snapshots of the value and whose writers publish new values.
+pub struct SwapLock<T> {
+    value: ArcSwap<T>,
+    writer: Mutex<()>,
+}
+
+impl<T> SwapLock<T> {
+    /// Create a new swap lock.
+    pub fn new(value: T) -> Self {
+        Self {
+            value: ArcSwap::from_pointee(value),
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// Load a snapshot of the value, without locking.
+    ///
+    /// The snapshot is not affected by the writes published after it was loaded.
+    pub fn load(&self) -> Arc<T> {
+        self.value.load_full()
+    }
+}
+
+impl<T> SwapLock<T>
+where
+    T: Clone,
+{
+    /// Wait for the other writers and get a clone of the value that is published when the guard
+    /// is dropped.
+    pub async fn write(&self) -> SwapWriteGuard<'_, T> {
+        let writer = self.writer.lock().await;
+        self.write_with(writer)
+    }
+
+    /// Block the current thread until the other writers are done and get a clone of the value
+    /// that is published when the guard is dropped.
+    ///
+    /// This must not be called within an asynchronous execution context, whose thread it would
+    /// block.
+    pub fn blocking_write(&self) -> SwapWriteGuard<'_, T> {
+        let writer = self.writer.lock_blocking();
+        self.write_with(writer)
+    }
+
+    /// Get a clone of the value that is published when the guard is dropped, if there is no
+    /// other writer.
+    pub fn try_write(&self) -> Option<SwapWriteGuard<'_, T>> {
+        let writer = self.writer.try_lock()?;
+        Some(self.write_with(writer))
+    }
+
+    /// Create a write guard that holds the writer lock.
+    fn write_with<'l>(&'l self, writer: MutexGuard<'l, ()>) -> SwapWriteGuard<'l, T> {
+        SwapWriteGuard {
+            lock: self,
+            value: Some(T::clone(&self.value.load())),
+            _writer: writer,
+        }
+    }
+}
+
+impl<T> fmt::Debug for SwapLock<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SwapLock")
+            .field("value", &self.value.load())
+            .finish()
+    }
+}
+
+/// Guard of a writer of a [SwapLock].
+///
+/// The modified value is published when the guard is dropped, unless the writer panicked.
+pub struct SwapWriteGuard<'l, T> {
+    lock: &'l SwapLock<T>,
+    value: Option<T>,
+    _writer: MutexGuard<'l, ()>,
+}
+
+impl<'l, T> Deref for SwapWriteGuard<'l, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+            .as_ref()
+            .expect("swap write guard: value already published")
+    }
+}
+
+impl<'l, T> DerefMut for SwapWriteGuard<'l, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+            .as_mut()
+            .expect("swap write guard: value already published")
+    }
+}
+
+impl<'l, T> Drop for SwapWriteGuard<'l, T> {
+    fn drop(&mut self) {
+        if let Some(value) = self.value.take() {
+            if !thread::panicking() {
+                self.lock.value.store(Arc::new(value));
+            }
+        }
+    }
+}
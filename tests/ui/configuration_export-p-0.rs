@@ -0,0 +1,95 @@
+//! Configuration export ui test #0 - round trip through env vars and secret policies.
+
+#![cfg(all(feature = "configuration_export", feature = "env_configuration"))]
+
+use blockz::configuration::export;
+use blockz::configuration::export::FileFormat;
+use blockz::configuration::types::Duration;
+use blockz::configuration::types::Secret;
+use blockz::configuration::types::SecretPolicy;
+use blockz::configuration::EasyConfiguration;
+use blockz::configuration::EnvConfiguration;
+use blockz::prelude::*;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Configuration, Deserialize, Serialize, PartialEq, Debug)]
+#[configuration(env(prefix = "EXPORT_"))]
+struct MyConfig {
+    server_port: u16,
+    peers: Vec<String>,
+    timeout: Duration,
+    nickname: Option<String>,
+    #[serde(skip_serializing_if = "Secret::is_omitted")]
+    token: Secret<String>,
+    #[serde(default)]
+    key: Option<Secret<String>>,
+}
+
+#[tokio::main]
+async fn main() {
+    std::env::set_var("EXPORT_SERVER_PORT", "8080");
+    std::env::set_var("EXPORT_PEERS", "a,b,c");
+    std::env::set_var("EXPORT_TIMEOUT", "90s");
+    std::env::set_var("EXPORT_TOKEN", "hunter2");
+
+    let conf = <MyConfig as EasyConfiguration>::load().await.unwrap();
+
+    // the exported variables are the ones the loader reads
+    let vars = export::to_env_vars(&conf, Some("EXPORT_"), SecretPolicy::Include).unwrap();
+    assert_eq!(
+        vars,
+        vec![
+            ("EXPORT_SERVER_PORT".to_string(), "8080".to_string()),
+            ("EXPORT_PEERS".to_string(), "a,b,c".to_string()),
+            ("EXPORT_TIMEOUT".to_string(), "1m30s".to_string()),
+            ("EXPORT_TOKEN".to_string(), "hunter2".to_string()),
+        ]
+    );
+    for (name, value) in vars.iter() {
+        std::env::set_var(name.replace("EXPORT_", "REIMPORT_"), value);
+    }
+    let reimported = <EnvConfiguration<MyConfig> as Configuration>::load(Some("REIMPORT_".into()))
+        .await
+        .unwrap();
+    assert_eq!(reimported, conf);
+
+    // secrets can be omitted or redacted
+    let vars = export::to_env_vars(&conf, None, SecretPolicy::Omit).unwrap();
+    assert!(vars.iter().all(|(name, _)| name != "TOKEN"));
+    let vars = export::to_env_vars(&conf, None, SecretPolicy::Redact).unwrap();
+    assert!(vars.contains(&("TOKEN".to_string(), "[REDACTED]".to_string())));
+    assert_eq!(format!("{:?}", conf.token), "Secret([REDACTED])");
+
+    // files
+    let json = export::to_string(&conf, FileFormat::Json, SecretPolicy::Omit).unwrap();
+    assert!(json.contains("\"nickname\": null"));
+    assert!(!json.contains("token"));
+    assert!(!json.contains("blockz"));
+    let toml = export::to_string(&conf, FileFormat::Toml, SecretPolicy::Redact).unwrap();
+    assert_eq!(
+        toml,
+        "server_port = 8080\npeers = [\"a\", \"b\", \"c\"]\ntimeout = \"1m30s\"\ntoken = \"[REDACTED]\"\n"
+    );
+
+    // secrets that are not skipped are omitted as nulls
+    let conf = MyConfig {
+        key: Some(Secret::new("swordfish".to_string())),
+        ..conf
+    };
+    let json = export::to_string(&conf, FileFormat::Json, SecretPolicy::Omit).unwrap();
+    assert!(json.contains("\"key\": null"));
+    let vars = export::to_env_vars(&conf, None, SecretPolicy::Omit).unwrap();
+    assert!(vars.iter().all(|(name, _)| name != "KEY"));
+
+    // sequence items with commas can't be exported as env vars
+    let conf = MyConfig {
+        peers: vec!["a,b".to_string()],
+        ..conf
+    };
+    assert!(matches!(
+        export::to_env_vars(&conf, None, SecretPolicy::Include),
+        Err(export::ExportError::UnsupportedValue(field)) if field == "peers"
+    ));
+}
@@ -0,0 +1,70 @@
+//! Configuration types ui test #0 - env configuration with typed values.
+
+#![cfg(all(feature = "configuration_types", feature = "env_configuration"))]
+
+use blockz::configuration::types::*;
+use blockz::configuration::EasyConfiguration;
+use blockz::prelude::*;
+
+use serde::Deserialize;
+
+use std::net::IpAddr;
+
+#[derive(Configuration, Deserialize)]
+#[configuration(env(prefix = "TYPES_"))]
+struct MyConfig {
+    timeout: Duration,
+    max_size: ByteSize,
+    bind: SocketAddrList,
+    database: Url,
+    log_level: LogLevel,
+    ratio: Percent,
+    allowed: Cidr,
+}
+
+#[tokio::main]
+async fn main() {
+    std::env::set_var("TYPES_TIMEOUT", "1h30m15s");
+    std::env::set_var("TYPES_MAX_SIZE", "1.5KiB");
+    std::env::set_var("TYPES_BIND", "0.0.0.0:8080, [::]:8080");
+    std::env::set_var("TYPES_DATABASE", "postgres://user@localhost:5432/db");
+    std::env::set_var("TYPES_LOG_LEVEL", "WARNING");
+    std::env::set_var("TYPES_RATIO", "12.5%");
+    std::env::set_var("TYPES_ALLOWED", "10.0.0.0/8");
+
+    let conf = <MyConfig as EasyConfiguration>::load().await.unwrap();
+    assert_eq!(conf.timeout.as_secs(), 5415);
+    assert_eq!(conf.timeout.to_string(), "1h30m15s");
+    assert_eq!(conf.max_size.as_u64(), 1536);
+    assert_eq!(conf.max_size.to_string(), "1536B");
+    assert_eq!(conf.bind.len(), 2);
+    assert_eq!(conf.bind.to_string(), "0.0.0.0:8080,[::]:8080");
+    assert_eq!(conf.database.scheme(), "postgres");
+    assert_eq!(conf.log_level, LogLevel::Warn);
+    assert_eq!(conf.ratio.as_fraction(), 0.125);
+    assert!(conf.allowed.contains(&"10.42.0.1".parse::<IpAddr>().unwrap()));
+    assert!(!conf.allowed.contains(&"11.0.0.1".parse::<IpAddr>().unwrap()));
+
+    // the error message names the variable that provided the bad value, even if other variables
+    // with the same prefix hold the same value
+    std::env::set_var("TYPES_OTHER_SERVICE_MAX_SIZE", "12 parsecs");
+    std::env::set_var("TYPES_MAX_SIZE", "12 parsecs");
+    let err = <MyConfig as EasyConfiguration>::load().await.err().unwrap();
+    assert!(err.to_string().starts_with("invalid byte size: unknown unit 'parsecs'"));
+    assert!(err.to_string().ends_with("while parsing value '12 parsecs' provided by TYPES_MAX_SIZE"));
+    std::env::set_var("TYPES_MAX_SIZE", "1KB");
+
+    // bad values are rejected
+    assert!("10.0.0.1/8".parse::<Cidr>().is_err());
+    assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+    assert!("150%".parse::<Percent>().is_err());
+    assert!("1q".parse::<Duration>().is_err());
+    assert!("".parse::<SocketAddrList>().is_err());
+    assert!("verbose".parse::<LogLevel>().is_err());
+    assert!("not a url".parse::<Url>().is_err());
+
+    // values round-trip through their string representation
+    assert_eq!("90s".parse::<Duration>().unwrap().to_string(), "1m30s");
+    assert_eq!("1MB".parse::<ByteSize>().unwrap().to_string(), "1MB");
+    assert_eq!("fd00::/8".parse::<Cidr>().unwrap().to_string(), "fd00::/8");
+}
@@ -0,0 +1,33 @@
+//! Direct configuration ui test #3 - blocking.
+
+#![cfg(all(feature = "configuration", not(any(feature = "env_configuration"))))]
+
+use blockz::configuration::EasyBlockingConfiguration;
+use blockz::configuration::EasyConfiguration;
+use blockz::prelude::*;
+
+#[derive(Configuration, Default, PartialEq)]
+struct MyConfig {
+    worker_threads: usize,
+}
+
+fn main() {
+    let conf1 = <MyConfig as EasyBlockingConfiguration>::load_blocking();
+    let conf2 = <MyConfig as BlockingConfiguration>::load_blocking(MyConfig { worker_threads: 4 });
+    assert!(conf1 == MyConfig::default());
+    assert!(conf2.worker_threads == 4);
+
+    // async sources can be loaded without a runtime
+    let conf3 = blockz::configuration::block_on(<MyConfig as EasyConfiguration>::load());
+    assert!(conf1 == conf3);
+
+    // the configuration can be used to build the runtime
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(conf2.worker_threads)
+        .build()
+        .unwrap();
+    runtime.block_on(async move {
+        let conf4 = <MyConfig as Configuration>::load(conf2).await;
+        assert!(conf4.worker_threads == 4);
+    });
+}
@@ -0,0 +1,24 @@
+//! Direct configuration ui test #4 - global configuration.
+
+#![cfg(all(feature = "configuration", not(any(feature = "env_configuration"))))]
+
+use blockz::prelude::*;
+
+#[derive(Configuration, Default, PartialEq, Debug)]
+#[configuration(direct, global)]
+struct MyConfig {
+    worker_threads: usize,
+}
+
+#[tokio::main]
+async fn main() {
+    assert!(MyConfig::try_global().is_none());
+    let config: Result<&'static MyConfig, std::convert::Infallible> = MyConfig::init_global().await;
+    let config = config.unwrap();
+    assert_eq!(config, &MyConfig::default());
+    assert!(std::ptr::eq(config, MyConfig::global()));
+    assert!(std::ptr::eq(
+        MyConfig::init_global().await.unwrap(),
+        MyConfig::global()
+    ));
+}
@@ -0,0 +1,31 @@
+//! Direct configuration ui test #5 - generics.
+
+#![cfg(all(feature = "configuration", not(any(feature = "env_configuration"))))]
+
+use blockz::prelude::*;
+
+use std::fmt::Debug;
+
+#[derive(Configuration, PartialEq, Debug)]
+struct MyConfig<'a, T, const N: usize>
+where
+    T: Debug,
+{
+    name: &'a str,
+    values: [T; N],
+}
+
+#[tokio::main]
+async fn main() {
+    let conf = <MyConfig<'static, u8, 2> as Configuration>::load(MyConfig {
+        name: "bytes",
+        values: [4, 2],
+    })
+    .await;
+    assert_eq!(conf.values, [4, 2]);
+    let conf = <MyConfig<String, 1> as BlockingConfiguration>::load_blocking(MyConfig {
+        name: "strings",
+        values: ["42".to_string()],
+    });
+    assert_eq!(conf.name, "strings");
+}
@@ -0,0 +1,17 @@
+//! Env configuration ui test #4 - enum variants with more than one unnamed field.
+
+#![cfg(feature = "env_configuration")]
+
+use blockz::prelude::*;
+
+#[derive(Configuration)]
+#[configuration(env(prefix = "STORAGE_"))]
+enum StorageConfig {
+    S3(String, String),
+    Local { path: String },
+}
+
+#[tokio::main]
+async fn main() {
+    panic!("This should not run!");
+}
@@ -0,0 +1,18 @@
+//! Env configuration ui test #5 - generic global configuration.
+
+#![cfg(feature = "env_configuration")]
+
+use blockz::prelude::*;
+
+use serde::Deserialize;
+
+#[derive(Configuration, Deserialize)]
+#[configuration(env(prefix = "GENERIC_"), global)]
+struct MyConfig<T> {
+    server_port: T,
+}
+
+#[tokio::main]
+async fn main() {
+    panic!("This should not run!");
+}
@@ -0,0 +1,41 @@
+//! Env configuration ui test #10 - prefix source (async function).
+
+#![cfg(feature = "env_configuration")]
+
+use blockz::configuration::EasyConfiguration;
+use blockz::prelude::*;
+
+use serde::Deserialize;
+
+async fn get_prefix() -> Result<String, envy::Error> {
+    std::env::var("BLOCKZ_UI_TEST_ASYNC_ENV_PREFIX")
+        .map_err(|err| envy::Error::Custom(format!("{}", err)))
+}
+
+#[derive(Configuration, Deserialize, PartialEq)]
+#[configuration(env(prefix_source = "self::get_prefix().await?"))]
+struct MyConfig {
+    server_port: u32,
+}
+
+#[derive(Configuration, PartialEq)]
+#[configuration(env(prefix_source = "self::get_prefix().await?"))]
+enum MyBackend {
+    Local { path: String },
+}
+
+#[tokio::main]
+async fn main() {
+    // set the required env variables
+    std::env::set_var("BLOCKZ_UI_TEST_ASYNC_ENV_PREFIX", "ASYNC_SOURCED_PREFIX_");
+    std::env::set_var("ASYNC_SOURCED_PREFIX_SERVER_PORT", "1234");
+    std::env::set_var("ASYNC_SOURCED_PREFIX_KIND", "local");
+    std::env::set_var("ASYNC_SOURCED_PREFIX_LOCAL_PATH", "/tmp");
+
+    let conf1 = <MyConfig as EasyConfiguration>::load().await.unwrap();
+    let conf2 = <MyConfig as Configuration>::load(()).await.unwrap();
+    assert!(conf1 == conf2);
+
+    let backend = <MyBackend as EasyConfiguration>::load().await.unwrap();
+    assert!(backend == MyBackend::Local { path: "/tmp".to_string() });
+}
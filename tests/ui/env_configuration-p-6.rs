@@ -0,0 +1,48 @@
+//! Env configuration ui test #6 - blocking.
+
+#![cfg(feature = "env_configuration")]
+
+use blockz::configuration::EasyBlockingConfiguration;
+use blockz::prelude::*;
+
+use serde::Deserialize;
+
+fn get_prefix() -> Result<String, envy::Error> {
+    std::env::var("BLOCKZ_UI_TEST_BLOCKING_PREFIX")
+        .map_err(|err| envy::Error::Custom(format!("{}", err)))
+}
+
+#[derive(Configuration, Deserialize, PartialEq)]
+#[configuration(env(prefix = "BLOCKING_"))]
+struct MyConfig {
+    worker_threads: usize,
+}
+
+#[derive(Configuration, Deserialize, PartialEq)]
+#[configuration(env(prefix_source = "self::get_prefix()?"))]
+struct MySourcedConfig {
+    worker_threads: usize,
+}
+
+#[derive(Configuration, Deserialize, PartialEq)]
+#[configuration(env())]
+struct MyUnprefixedConfig {
+    blocking_worker_threads: usize,
+}
+
+fn main() {
+    std::env::set_var("BLOCKING_WORKER_THREADS", "4");
+
+    let conf1 = <MyConfig as EasyBlockingConfiguration>::load_blocking().unwrap();
+    let conf2 = <MyConfig as BlockingConfiguration>::load_blocking(()).unwrap();
+    assert!(conf1 == conf2);
+    assert!(conf1.worker_threads == 4);
+
+    assert!(<MySourcedConfig as EasyBlockingConfiguration>::load_blocking().is_err());
+    std::env::set_var("BLOCKZ_UI_TEST_BLOCKING_PREFIX", "BLOCKING_");
+    let conf3 = <MySourcedConfig as EasyBlockingConfiguration>::load_blocking().unwrap();
+    assert!(conf3.worker_threads == 4);
+
+    let conf4 = <MyUnprefixedConfig as BlockingConfiguration>::load_blocking(None).unwrap();
+    assert!(conf4.blocking_worker_threads == 4);
+}
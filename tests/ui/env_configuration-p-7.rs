@@ -0,0 +1,74 @@
+//! Env configuration ui test #7 - enums selected by a tag.
+
+#![cfg(feature = "env_configuration")]
+
+use blockz::prelude::*;
+
+use serde::Deserialize;
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct LocalStorage {
+    path: String,
+}
+
+#[derive(Configuration, PartialEq, Debug)]
+#[configuration(env(prefix = "STORAGE_"))]
+enum StorageConfig {
+    S3 {
+        bucket: String,
+        #[serde(default)]
+        region: Option<String>,
+    },
+    Local(LocalStorage),
+    #[configuration(rename = "mem")]
+    InMemory,
+}
+
+#[derive(Configuration, PartialEq, Debug)]
+#[configuration(env(tag = "backend"))]
+enum CacheConfig {
+    Redis { url: String },
+    NoCache,
+}
+
+#[tokio::main]
+async fn main() {
+    assert!(<StorageConfig as EasyConfiguration>::load().await.is_err());
+
+    std::env::set_var("STORAGE_KIND", "s3");
+    assert!(<StorageConfig as EasyConfiguration>::load().await.is_err());
+    std::env::set_var("STORAGE_S3_BUCKET", "my-bucket");
+    let conf = <StorageConfig as EasyConfiguration>::load().await.unwrap();
+    assert_eq!(
+        conf,
+        StorageConfig::S3 {
+            bucket: "my-bucket".to_string(),
+            region: None,
+        }
+    );
+
+    std::env::set_var("STORAGE_KIND", "Local");
+    std::env::set_var("STORAGE_LOCAL_PATH", "/var/lib/storage");
+    let conf = <StorageConfig as EasyConfiguration>::load().await.unwrap();
+    assert_eq!(
+        conf,
+        StorageConfig::Local(LocalStorage {
+            path: "/var/lib/storage".to_string(),
+        })
+    );
+
+    std::env::set_var("STORAGE_KIND", "mem");
+    let conf = <StorageConfig as EasyBlockingConfiguration>::load_blocking().unwrap();
+    assert_eq!(conf, StorageConfig::InMemory);
+
+    std::env::set_var("STORAGE_KIND", "gcs");
+    let err = <StorageConfig as EasyConfiguration>::load().await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unknown variant 'gcs' provided by STORAGE_KIND (expected one of: s3, local, mem)"
+    );
+
+    std::env::set_var("CACHE_BACKEND", "no_cache");
+    let conf = <CacheConfig as Configuration>::load(Some("CACHE_".to_string())).await.unwrap();
+    assert_eq!(conf, CacheConfig::NoCache);
+}
@@ -0,0 +1,74 @@
+//! Env configuration ui test #8 - global configuration.
+
+#![cfg(feature = "env_configuration")]
+
+use blockz::prelude::*;
+
+use serde::Deserialize;
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+static LOADS: AtomicUsize = AtomicUsize::new(0);
+
+async fn slow_prefix() -> String {
+    LOADS.fetch_add(1, Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    "GLOBAL_".to_string()
+}
+
+#[derive(Configuration, Deserialize, PartialEq, Debug)]
+#[configuration(env(prefix = "GLOBAL_"), global)]
+struct MyConfig {
+    server_port: u16,
+}
+
+#[derive(Configuration, PartialEq, Debug)]
+#[configuration(env(prefix = "GLOBAL_"), global)]
+enum MyBackend {
+    Local { path: String },
+}
+
+#[derive(Configuration, Deserialize, PartialEq, Debug)]
+#[configuration(env(prefix_source = "self::slow_prefix().await"), global)]
+struct SlowConfig {
+    server_port: u16,
+}
+
+async fn init() -> anyhow::Result<()> {
+    MyConfig::init_global().await?;
+    MyBackend::init_global().await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    assert!(MyConfig::init_global().await.is_err());
+    assert!(MyConfig::try_global().is_none());
+    assert!(std::panic::catch_unwind(MyConfig::global).is_err());
+
+    std::env::set_var("GLOBAL_SERVER_PORT", "8080");
+    std::env::set_var("GLOBAL_KIND", "local");
+    std::env::set_var("GLOBAL_LOCAL_PATH", "/tmp");
+    init().await.unwrap();
+
+    // concurrent initializations wait for a single load
+    let mut tasks = Vec::new();
+    for _ in 0..8 {
+        tasks.push(tokio::spawn(SlowConfig::init_global()));
+    }
+    for task in tasks {
+        assert_eq!(task.await.unwrap().unwrap().server_port, 8080);
+    }
+    assert_eq!(LOADS.load(Ordering::SeqCst), 1);
+
+    let handle = tokio::spawn(async { MyConfig::global().server_port });
+    assert_eq!(handle.await.unwrap(), 8080);
+    assert_eq!(
+        MyBackend::global(),
+        &MyBackend::Local {
+            path: "/tmp".to_string()
+        }
+    );
+}
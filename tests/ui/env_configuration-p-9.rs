@@ -0,0 +1,64 @@
+//! Env configuration ui test #9 - generics.
+
+#![cfg(feature = "env_configuration")]
+
+use blockz::prelude::*;
+
+use serde::Deserialize;
+
+use std::fmt::Debug;
+
+#[derive(Configuration, Deserialize, PartialEq, Debug)]
+#[configuration(env(prefix = "GENERIC_"))]
+struct MyConfig<T>
+where
+    T: Debug,
+{
+    server_port: T,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct Local<P> {
+    path: P,
+}
+
+#[derive(Configuration, PartialEq, Debug)]
+#[configuration(env(prefix = "GENERIC_"))]
+enum MyBackend<P, R> {
+    Local(Local<P>),
+    Remote {
+        urls: Vec<R>,
+        #[serde(default)]
+        retries: Option<u8>,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    std::env::set_var("GENERIC_SERVER_PORT", "8080");
+    let conf = <MyConfig<u16> as EasyConfiguration>::load().await.unwrap();
+    assert_eq!(conf.server_port, 8080);
+    let conf = <MyConfig<String> as EasyBlockingConfiguration>::load_blocking().unwrap();
+    assert_eq!(conf.server_port, "8080");
+
+    std::env::set_var("GENERIC_KIND", "local");
+    std::env::set_var("GENERIC_LOCAL_PATH", "/tmp");
+    let conf = <MyBackend<String, u8> as EasyConfiguration>::load().await.unwrap();
+    assert_eq!(
+        conf,
+        MyBackend::Local(Local {
+            path: "/tmp".to_string()
+        })
+    );
+
+    std::env::set_var("GENERIC_KIND", "remote");
+    std::env::set_var("GENERIC_REMOTE_URLS", "1,2,3");
+    let conf = <MyBackend<String, u8> as EasyConfiguration>::load().await.unwrap();
+    assert_eq!(
+        conf,
+        MyBackend::Remote {
+            urls: vec![1, 2, 3],
+            retries: None,
+        }
+    );
+}
@@ -0,0 +1,20 @@
+//! Singleton test fail #11 - unknown singleton fn option.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+struct Counter(u64);
+
+impl Counter {
+    #[singleton_fn(falible)]
+    async fn get(&self) -> u64 {
+        self.0
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    panic!("This should not run!");
+}
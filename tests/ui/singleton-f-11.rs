@@ -0,0 +1,22 @@
+//! Singleton test fail #12 - invalid lazy initialization options.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(init_backoff = "1s")]
+struct NoInit(usize);
+
+#[derive(Singleton)]
+#[singleton(init = "connect", init_backoff = "1 fortnight")]
+struct BadBackoff(usize);
+
+async fn connect() -> anyhow::Result<BadBackoff> {
+    Ok(BadBackoff(0))
+}
+
+#[tokio::main]
+async fn main() {
+    panic!("This should not run!");
+}
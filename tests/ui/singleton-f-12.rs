@@ -0,0 +1,24 @@
+//! Singleton test fail #13 - invalid timeouts.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(lock_timeout = "2 seconds")]
+struct Device(u64);
+
+#[derive(Singleton)]
+struct Sensor(u64);
+
+impl Sensor {
+    #[singleton_fn(timeout = "500")]
+    async fn read(&self) -> u64 {
+        self.0
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    panic!("This should not run!");
+}
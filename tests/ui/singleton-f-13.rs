@@ -0,0 +1,27 @@
+//! Singleton test fail #14 - mutable uses of a singleton without a lock.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(lock = "none")]
+struct Routes {
+    routes: Vec<String>,
+}
+
+impl Routes {
+    #[singleton_fn]
+    async fn add(&mut self, route: String) {
+        self.routes.push(route);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let _ = Routes::try_use_mut_singleton(|routes| {
+        Box::pin(async move { routes.routes.clear() })
+    })
+    .await;
+    panic!("This should not run!");
+}
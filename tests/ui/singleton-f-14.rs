@@ -0,0 +1,31 @@
+//! Singleton test fail #15 - unsupported options of sync singletons.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(lock = "std_mutex", init = "Registry::load")]
+struct Registry {
+    names: Vec<String>,
+}
+
+impl Registry {
+    async fn load() -> anyhow::Result<Registry> {
+        Ok(Registry { names: Vec::new() })
+    }
+}
+
+#[derive(Singleton)]
+struct Counter(u64);
+
+impl Counter {
+    #[singleton_fn(timeout = "1s")]
+    fn get(&self) -> u64 {
+        self.0
+    }
+}
+
+fn main() {
+    panic!("This should not run!");
+}
@@ -0,0 +1,36 @@
+//! Singleton test fail #16 - misused guard singleton fns.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+struct Routes {
+    names: Vec<String>,
+}
+
+impl Routes {
+    #[singleton_fn(guard)]
+    fn count(&self) -> usize {
+        self.names.len()
+    }
+
+    #[singleton_fn(guard)]
+    fn names_mut(&mut self) -> &Vec<String> {
+        &self.names
+    }
+
+    #[singleton_fn(guard)]
+    async fn names(&self) -> &Vec<String> {
+        &self.names
+    }
+
+    #[singleton_fn(guard, timeout = "1s")]
+    fn first(&self) -> &String {
+        &self.names[0]
+    }
+}
+
+fn main() {
+    panic!("This should not run!");
+}
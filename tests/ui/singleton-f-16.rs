@@ -0,0 +1,25 @@
+//! Singleton test fail #17 - misused watched singletons.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(lock = "none", watch)]
+struct Limits {
+    max: u32,
+}
+
+#[derive(Singleton)]
+#[singleton(watch = "Vec<")]
+struct Routes {
+    names: Vec<String>,
+}
+
+#[derive(Singleton)]
+#[singleton(watch)]
+struct Counter(u64);
+
+fn main() {
+    panic!("This should not run!");
+}
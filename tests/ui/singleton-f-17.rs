@@ -0,0 +1,30 @@
+//! Singleton test fail #18 - misused keyed singletons.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(keyed, watch)]
+struct Watched(u32);
+
+#[derive(Singleton)]
+#[singleton(keyed = "u32<")]
+struct Invalid(u32);
+
+#[derive(Singleton)]
+#[singleton(lock = "none", keyed)]
+struct Limits {
+    max: u32,
+}
+
+impl Limits {
+    #[singleton_fn(keyed)]
+    async fn set_max(&mut self, max: u32) {
+        self.max = max;
+    }
+}
+
+fn main() {
+    panic!("This should not run!");
+}
@@ -0,0 +1,45 @@
+//! Singleton test fail #19 - misused sharded singletons.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(shards = 0)]
+struct Empty(u32);
+
+#[derive(Singleton)]
+#[singleton(shards = 4, keyed)]
+struct Both(u32);
+
+#[derive(Singleton)]
+#[singleton(shards = 4, init = "Lazy::load")]
+struct Lazy(u32);
+
+#[derive(Singleton)]
+#[singleton(shard_by = "user_id")]
+struct Unsharded(u32);
+
+#[derive(Singleton)]
+#[singleton(shards = 4, shard_by = "user id")]
+struct Spaced(u32);
+
+#[derive(Singleton)]
+#[singleton(shards = 4)]
+struct Counter(u64);
+
+impl Counter {
+    #[singleton_fn(shard_by = "user")]
+    async fn add(&mut self, user_id: u64) {
+        self.0 += user_id;
+    }
+
+    #[singleton_fn(shard_by = "user_id", keyed)]
+    async fn get(&self, user_id: u64) -> u64 {
+        self.0 + user_id
+    }
+}
+
+fn main() {
+    panic!("This should not run!");
+}
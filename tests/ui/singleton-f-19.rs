@@ -0,0 +1,33 @@
+//! Singleton test fail #20 - misused actor singletons.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(actor, lock = "rwlock")]
+struct Locked(u32);
+
+#[derive(Singleton)]
+#[singleton(actor(mailbox = 0))]
+struct Closed(u32);
+
+#[derive(Singleton)]
+#[singleton(actor, init = "Lazy::load")]
+struct Lazy(u32);
+
+#[derive(Singleton)]
+#[singleton(actor(overflow = "drop"))]
+struct Dropping(u32);
+
+#[derive(Singleton)]
+#[singleton(actor, keyed)]
+struct Keyed(u32);
+
+#[derive(Singleton)]
+#[singleton(actor, shards = 4)]
+struct Sharded(u32);
+
+fn main() {
+    panic!("This should not run!");
+}
@@ -0,0 +1,14 @@
+//! Singleton test fail #21 - misused scoped singletons.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(keyed)]
+struct Pool(u32);
+
+#[tokio::main]
+async fn main() {
+    Pool::scope(Pool(1), async {}).await;
+}
@@ -0,0 +1,31 @@
+//! Singleton test fail #22 - singleton impls with methods that can't be trait methods.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(trait = "RoutesApi")]
+struct Routes(Vec<String>);
+
+#[singleton_impl(trait = "RoutesApi")]
+impl Routes {
+    #[singleton_fn(guard)]
+    fn routes(&self) -> &Vec<String> {
+        &self.0
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(trait = "StoreApi")]
+struct Store(Vec<String>);
+
+#[singleton_impl(trait = "StoreApi")]
+impl Store {
+    #[singleton_fn]
+    async fn put<K: Into<String>>(&mut self, key: K) {
+        self.0.push(key.into());
+    }
+}
+
+fn main() {}
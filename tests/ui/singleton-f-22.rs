@@ -0,0 +1,17 @@
+//! Singleton test fail #23 - replacing and taking the value of a singleton without a lock.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(lock = "none")]
+struct Routes {
+    routes: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() {
+    Routes::replace_singleton(Routes { routes: Vec::new() }).await;
+    Routes::take_singleton().await;
+}
@@ -0,0 +1,31 @@
+//! Singleton test fail #24 - guards of singletons with a sync lock.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(lock = "std_mutex")]
+struct Counter(u64);
+
+impl Counter {
+    #[singleton_fn(guard)]
+    fn value(&self) -> &u64 {
+        &self.0
+    }
+
+    #[singleton_fn(guard)]
+    fn value_mut(&mut self) -> &mut u64 {
+        &mut self.0
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(lock = "parking_lot")]
+struct Routes(Vec<String>);
+
+#[tokio::main]
+async fn main() {
+    let _ = Routes::read_guard().await;
+    let _ = Routes::write_guard().await;
+}
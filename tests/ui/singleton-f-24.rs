@@ -0,0 +1,41 @@
+//! Singleton test fail #24 - singleton fns without the shard_by argument of their singleton.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(shards = 4, shard_by = "user_id")]
+struct Hits(u64);
+
+impl Hits {
+    #[singleton_fn]
+    async fn add(&mut self, user: u64) {
+        self.0 += user;
+    }
+
+    #[singleton_fn]
+    async fn total(&self) -> u64 {
+        self.0
+    }
+
+    #[singleton_fn]
+    fn ratio(&self, (user_id, count): (u64, u64)) -> u64 {
+        (self.0 + user_id) / count
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(shards = 4)]
+struct Counter(u64);
+
+impl Counter {
+    #[singleton_fn]
+    async fn add(&mut self, user_id: u64) {
+        self.0 += user_id;
+    }
+}
+
+fn main() {
+    panic!("This should not run!");
+}
@@ -0,0 +1,20 @@
+//! Singleton test fail #26 - actor singleton fns that borrow their arguments.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(actor)]
+struct Names(Vec<String>);
+
+impl Names {
+    #[singleton_fn]
+    async fn contains(&self, name: &str) -> bool {
+        self.0.iter().any(|n| n == name)
+    }
+}
+
+fn main() {
+    panic!("This should not run!");
+}
@@ -0,0 +1,11 @@
+//! Singleton test fail #27 - trait singletons without a lock.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(trait = "CfgApi", lock = "none")]
+struct Cfg(u32);
+
+fn main() {}
@@ -0,0 +1,36 @@
+//! Singleton test fail #28 - async singleton fns of singletons with a sync lock.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(lock = "std_mutex")]
+struct Counter(u64);
+
+impl Counter {
+    #[singleton_fn]
+    async fn value(&self) -> u64 {
+        self.0
+    }
+
+    #[singleton_fn(fallible)]
+    async fn increment(&mut self) {
+        self.0 += 1;
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(lock = "parking_lot", keyed)]
+struct Limits(u32);
+
+impl Limits {
+    #[singleton_fn(keyed, timeout = "1s")]
+    async fn max(&self) -> u32 {
+        self.0
+    }
+}
+
+fn main() {
+    panic!("This should not run!");
+}
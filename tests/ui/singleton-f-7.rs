@@ -1,4 +1,4 @@
-//! Singleton test fail #7 - function arguments without identifiers.
+//! Singleton test fail #7 - function arguments with refutable patterns.
 
 #![cfg(feature = "singleton")]
 
@@ -14,17 +14,12 @@ impl Dummy {
     }
 
     #[singleton_fn]
-    async fn clear(&mut self) {
+    pub async fn get_set(&mut self, get: usize, Some(set): Option<i32>) -> Option<i32> {
         panic!("This should not run!");
     }
 
     #[singleton_fn]
-    pub async fn get_set(&mut self, get: usize, _: i32) -> Option<i32> {
-        panic!("This should not run!");
-    }
-
-    #[singleton_fn]
-    pub async fn check_equals(&self, _: Box<[i32]>) -> bool {
+    pub async fn check_equals(&self, Ok(other): Result<i32, ()>) -> bool {
         panic!("This should not run!");
     }
 }
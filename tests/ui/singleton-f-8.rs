@@ -1,6 +1,4 @@
-//! Singleton test fail #8 - function parameter is a reference
-//!
-//! The error message is pretty unpleasant.
+//! Singleton test fail #8 - function output borrows from the singleton
 
 #![cfg(feature = "singleton")]
 
@@ -11,22 +9,17 @@ struct Dummy(Vec<i32>);
 
 impl Dummy {
     #[singleton_fn]
-    pub async fn is_vec_empty(&self) -> bool {
+    pub async fn first(&self) -> Option<&i32> {
         panic!("This should not run!");
     }
 
     #[singleton_fn]
-    async fn clear(&mut self) {
+    pub async fn find(&self, value: i32) -> Box<dyn Iterator<Item = &i32> + '_> {
         panic!("This should not run!");
     }
 
     #[singleton_fn]
-    pub async fn get_set(&mut self, get: &usize, set: &i32) -> Option<i32> {
-        panic!("This should not run!");
-    }
-
-    #[singleton_fn]
-    pub async fn check_equals(&self, other: &[i32]) -> bool {
+    pub fn last<'a>(&'a mut self) -> Option<&'a mut i32> {
         panic!("This should not run!");
     }
 }
@@ -0,0 +1,21 @@
+//! Singleton test fail #10 - generic singleton without instances.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+struct Cache<V> {
+    entries: Vec<V>,
+}
+
+#[derive(Singleton)]
+#[singleton(instance = "Store<u32>")]
+struct Queue<V> {
+    entries: Vec<V>,
+}
+
+#[tokio::main]
+async fn main() {
+    panic!("This should not run!");
+}
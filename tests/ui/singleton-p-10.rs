@@ -0,0 +1,103 @@
+//! Singleton test pass #11 - sync singleton fns.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+use blockz::singleton::SingletonError;
+
+#[derive(Singleton)]
+#[singleton(lock = "std_mutex")]
+struct Registry {
+    names: Vec<String>,
+}
+
+impl Registry {
+    #[singleton_fn]
+    fn register(&mut self, name: String) -> usize {
+        self.names.push(name);
+        self.names.len()
+    }
+
+    #[singleton_fn(fallible)]
+    fn names(&self) -> Vec<String> {
+        self.names.clone()
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(lock = "parking_lot")]
+struct Counter(u64);
+
+impl Counter {
+    #[singleton_fn]
+    fn add(&mut self, first: u64, second: u64) {
+        self.0 += first + second;
+    }
+
+    #[singleton_fn]
+    fn get(&self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(lock = "rwlock")]
+struct Config {
+    level: u8,
+}
+
+impl Config {
+    #[singleton_fn]
+    fn level(&self) -> u8 {
+        self.level
+    }
+
+    #[singleton_fn]
+    async fn set_level(&mut self, level: u8) {
+        self.level = level;
+    }
+}
+
+fn main() {
+    assert_eq!(Registry::names(), Err(SingletonError::NotInitialized("Registry")));
+    Registry::init_singleton(Registry { names: Vec::new() }).unwrap();
+    assert_eq!(Registry::register("tux".to_string()), 1);
+    assert_eq!(Registry::register("ferris".to_string()), 2);
+    assert_eq!(
+        Registry::names(),
+        Ok(vec!["tux".to_string(), "ferris".to_string()])
+    );
+    let len = Registry::use_singleton_blocking(|registry| registry.names.len());
+    assert_eq!(len, 2);
+
+    Counter::init_singleton(Counter(0)).unwrap();
+    let threads: Vec<_> = (0..8)
+        .map(|_| std::thread::spawn(|| Counter::add(1, 2)))
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(Counter::get(), 24);
+
+    // singletons with an async lock can be used by sync code outside of the async context
+    Config::init_singleton(Config { level: 1 }).unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        Config::set_level(2).await;
+        let level = tokio::task::spawn_blocking(Config::level).await.unwrap();
+        assert_eq!(level, 2);
+
+        // singletons with a sync lock can't be used by async singleton fns (a compile error), and
+        // their async use functions panic
+        let panic = tokio::spawn(Registry::use_singleton(|_: &_| async { 0 }))
+            .await
+            .unwrap_err();
+        let panic = panic.into_panic();
+        assert_eq!(
+            panic.downcast_ref::<String>().map(String::as_str),
+            Some("Registry: singleton: singletons with a sync lock can only be used by blocking functions")
+        );
+    });
+    Config::use_mut_singleton_blocking(|config| config.level = 3);
+    assert_eq!(Config::level(), 3);
+}
@@ -0,0 +1,81 @@
+//! Singleton test pass #12 - reference arguments.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+use std::collections::HashMap;
+
+#[derive(Singleton)]
+#[singleton(lock = "rwlock")]
+struct Store {
+    values: HashMap<String, Vec<u8>>,
+}
+
+impl Store {
+    #[singleton_fn]
+    async fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    #[singleton_fn]
+    async fn put(&mut self, key: &str, value: &[u8]) -> Option<Vec<u8>> {
+        self.values.insert(key.to_string(), value.to_vec())
+    }
+
+    #[singleton_fn(fallible)]
+    async fn equals(&self, key: &str, value: &[u8]) -> bool {
+        self.values.get(key).map(Vec::as_slice) == Some(value)
+    }
+
+    #[singleton_fn(timeout = "1s")]
+    async fn extend(&mut self, key: &String, value: &mut Vec<u8>) {
+        if let Some(stored) = self.values.get_mut(key) {
+            stored.append(value);
+        }
+    }
+
+    #[singleton_fn]
+    async fn first_missing<'a>(&self, keys: &'a [&'a str]) -> Option<&'a str> {
+        keys.iter().copied().find(|key| !self.values.contains_key(*key))
+    }
+
+    #[singleton_fn]
+    fn len_of(&self, key: &str) -> Option<usize> {
+        self.values.get(key).map(Vec::len)
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    Store::init_singleton(Store {
+        values: HashMap::new(),
+    })
+    .unwrap();
+
+    let key = String::from("hello");
+    assert!(!Store::contains(&key).await);
+    assert_eq!(Store::put(&key, b"wor").await, None);
+    assert!(Store::contains("hello").await);
+    assert_eq!(Store::equals(&key, b"wor").await, Ok(true));
+
+    let mut value = vec![b'l', b'd'];
+    assert_eq!(Store::extend(&key, &mut value).await, Ok(()));
+    assert!(value.is_empty());
+    assert_eq!(Store::equals("hello", b"world").await, Ok(true));
+
+    let keys = ["hello", "missing"];
+    assert_eq!(Store::first_missing(&keys).await, Some("missing"));
+
+    // borrowed arguments can be used from spawned tasks
+    let task = tokio::spawn(async {
+        let key = "hello".to_string();
+        Store::contains(&key).await
+    });
+    assert!(task.await.unwrap());
+
+    let len = tokio::task::spawn_blocking(|| Store::len_of("hello"))
+        .await
+        .unwrap();
+    assert_eq!(len, Some(5));
+}
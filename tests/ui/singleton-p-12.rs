@@ -0,0 +1,77 @@
+//! Singleton test pass #13 - generic and impl Trait arguments.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+#[derive(Singleton)]
+#[singleton(lock = "rwlock")]
+struct Store {
+    values: HashMap<String, Vec<u8>>,
+}
+
+impl Store {
+    #[singleton_fn]
+    async fn put<K: Into<String>>(&mut self, key: K, value: impl AsRef<[u8]>) {
+        self.values.insert(key.into(), value.as_ref().to_vec());
+    }
+
+    #[singleton_fn]
+    async fn contains<K>(&self, key: &K) -> bool
+    where
+        K: AsRef<str> + ?Sized,
+    {
+        self.values.contains_key(key.as_ref())
+    }
+
+    #[singleton_fn(fallible)]
+    async fn parse<T>(&self, key: impl AsRef<str>) -> Option<T>
+    where
+        T: FromStr,
+    {
+        let value = self.values.get(key.as_ref())?;
+        std::str::from_utf8(value).ok()?.parse().ok()
+    }
+
+    #[singleton_fn(timeout = "1s")]
+    async fn extend<I>(&mut self, key: &str, values: I) -> usize
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let stored = self.values.entry(key.to_string()).or_default();
+        stored.extend(values);
+        stored.len()
+    }
+
+    #[singleton_fn]
+    fn describe(&self, key: impl Display) -> Option<usize> {
+        self.values.get(&key.to_string()).map(Vec::len)
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    Store::init_singleton(Store {
+        values: HashMap::new(),
+    })
+    .unwrap();
+
+    Store::put("answer", b"42").await;
+    Store::put(String::from("name"), vec![b't', b'u', b'x']).await;
+    assert!(Store::contains("answer").await);
+    assert!(Store::contains(&String::from("name")).await);
+
+    assert_eq!(Store::parse::<u32>("answer").await, Ok(Some(42)));
+    assert_eq!(Store::parse::<u32>(String::from("name")).await, Ok(None));
+
+    assert_eq!(Store::extend("name", b"ux".iter().copied()).await, Ok(5));
+
+    let len = tokio::task::spawn_blocking(|| Store::describe("name"))
+        .await
+        .unwrap();
+    assert_eq!(len, Some(5));
+}
@@ -0,0 +1,80 @@
+//! Singleton test pass #14 - destructuring patterns in arguments.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Point {
+    x: i64,
+    y: i64,
+}
+
+#[derive(Singleton)]
+struct Canvas {
+    points: Vec<Point>,
+}
+
+impl Canvas {
+    #[singleton_fn]
+    async fn add(&mut self, Point { x, y }: Point) -> usize {
+        self.points.push(Point { x, y });
+        self.points.len()
+    }
+
+    #[singleton_fn]
+    async fn add_line(&mut self, (x0, y0): (i64, i64), (x1, y1): (i64, i64), _: &str) {
+        self.points.push(Point { x: x0, y: y0 });
+        self.points.push(Point { x: x1, y: y1 });
+    }
+
+    #[singleton_fn]
+    async fn translate(&mut self, mut dx: i64, [dy]: [i64; 1]) -> Vec<Point> {
+        dx *= 1;
+        self.points
+            .iter()
+            .map(|point| Point {
+                x: point.x + dx,
+                y: point.y + dy,
+            })
+            .collect()
+    }
+
+    #[singleton_fn(fallible)]
+    async fn contains(&self, &Point { x, y }: &Point) -> bool {
+        self.points.iter().any(|point| point.x == x && point.y == y)
+    }
+
+    #[singleton_fn]
+    fn count(&self, (min_x, max_x): (i64, i64), ref label: String) -> (usize, String) {
+        let count = self
+            .points
+            .iter()
+            .filter(|point| min_x <= point.x && point.x <= max_x)
+            .count();
+        (count, label.clone())
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    Canvas::init_singleton(Canvas { points: Vec::new() }).unwrap();
+
+    assert_eq!(Canvas::add(Point { x: 1, y: 2 }).await, 1);
+    Canvas::add_line((3, 4), (5, 6), "line").await;
+    assert_eq!(Canvas::contains(&Point { x: 3, y: 4 }).await, Ok(true));
+    assert_eq!(Canvas::contains(&Point { x: 4, y: 3 }).await, Ok(false));
+    assert_eq!(
+        Canvas::translate(1, [1]).await,
+        vec![
+            Point { x: 2, y: 3 },
+            Point { x: 4, y: 5 },
+            Point { x: 6, y: 7 },
+        ]
+    );
+
+    let count = tokio::task::spawn_blocking(|| Canvas::count((2, 6), "x".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(count, (2, "x".to_string()));
+}
@@ -0,0 +1,71 @@
+//! Singleton test pass #15 - argument names used by other identifiers in the body.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+struct Inventory {
+    items: Vec<String>,
+    count: usize,
+}
+
+impl Inventory {
+    fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    #[singleton_fn]
+    async fn add(&mut self, items: Vec<String>, count: usize) -> usize {
+        // the arguments share their names with fields and methods
+        self.items.extend(items.into_iter().take(count));
+        self.count = self.items().len();
+        self.count
+    }
+
+    #[singleton_fn]
+    async fn describe(&self, prefix: &str, items: usize) -> String {
+        let prefix = format!("{}:", prefix);
+        let names = self.items.iter().take(items).fold(String::new(), |items, item| {
+            format!("{} {}", items, item)
+        });
+        format!("{}{} ({} of {})", prefix, names, items, self.count)
+    }
+
+    #[singleton_fn]
+    async fn replace(&mut self, index: usize, args: String) -> String {
+        std::mem::replace(&mut self.items[index], args)
+    }
+
+    #[singleton_fn]
+    fn contains(&self, item: &str, count: usize) -> bool {
+        macro_rules! count {
+            ($items: expr, $item: expr) => {
+                $items.iter().filter(|other| *other == $item).count()
+            };
+        }
+        count!(self.items, item) >= count
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    Inventory::init_singleton(Inventory {
+        items: Vec::new(),
+        count: 0,
+    })
+    .unwrap();
+
+    let items = vec!["apple".to_string(), "pear".to_string(), "plum".to_string()];
+    assert_eq!(Inventory::add(items, 2).await, 2);
+    assert_eq!(
+        Inventory::describe("fruits", 1).await,
+        "fruits: apple (1 of 2)".to_string()
+    );
+    assert_eq!(Inventory::replace(0, "kiwi".to_string()).await, "apple");
+
+    let contains = tokio::task::spawn_blocking(|| Inventory::contains("pear", 1))
+        .await
+        .unwrap();
+    assert!(contains);
+}
@@ -0,0 +1,114 @@
+//! Singleton test pass #16 - guards.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+use blockz::singleton::SingletonError;
+use blockz::singleton::SingletonReadGuard;
+use blockz::singleton::SingletonWriteGuard;
+
+use std::collections::HashMap;
+
+#[derive(Singleton)]
+#[singleton(lock = "rwlock")]
+struct Routes {
+    table: HashMap<String, Vec<String>>,
+    version: u64,
+}
+
+impl Routes {
+    #[singleton_fn(guard)]
+    fn table(&self) -> &HashMap<String, Vec<String>> {
+        &self.table
+    }
+
+    #[singleton_fn(guard, fallible)]
+    fn hops(&self, dest: &str, limit: usize) -> &[String] {
+        let hops = &self.table[dest];
+        &hops[..limit.min(hops.len())]
+    }
+
+    #[singleton_fn(guard)]
+    fn hops_mut(&mut self, dest: String) -> &mut Vec<String> {
+        self.version += 1;
+        self.table.entry(dest).or_default()
+    }
+}
+
+#[derive(Clone, Singleton)]
+#[singleton(lock = "swap")]
+struct Snapshot {
+    names: Vec<String>,
+}
+
+#[derive(Singleton)]
+#[singleton(lock = "none")]
+struct Limits {
+    max: u32,
+}
+
+#[derive(Singleton)]
+#[singleton(lock = "mutex")]
+struct Empty;
+
+#[tokio::main]
+async fn main() {
+    Routes::init_singleton(Routes {
+        table: HashMap::new(),
+        version: 0,
+    })
+    .unwrap();
+
+    // write through a mapped write guard
+    {
+        let mut hops = SingletonWriteGuard::map(Routes::write_guard().await, |routes| {
+            routes.table.entry("a".to_string()).or_default()
+        });
+        hops.push("gateway".to_string());
+    }
+    Routes::hops_mut("a".to_string()).await.push("router".to_string());
+
+    // read through a mapped read guard
+    {
+        let version = SingletonReadGuard::map(Routes::read_guard().await, |routes| &routes.version);
+        assert_eq!(*version, 1);
+        let table = Routes::table().await;
+        assert_eq!(table["a"], ["gateway", "router"]);
+        assert_eq!(&*Routes::hops("a", 1).await.unwrap(), ["gateway"]);
+    }
+
+    // projections that fail return the original guard
+    let guard = Routes::read_guard().await;
+    let guard = SingletonReadGuard::try_map(guard, |routes| routes.table.get("b")).unwrap_err();
+    assert!(guard.table.contains_key("a"));
+    drop(guard);
+
+    // guards can be held across await points
+    let handle = tokio::spawn(async {
+        let guard = Routes::read_guard().await;
+        tokio::task::yield_now().await;
+        guard.version
+    });
+    assert_eq!(handle.await.unwrap(), 1);
+
+    // swap guards are snapshots, which don't block writers
+    Snapshot::init_singleton(Snapshot {
+        names: vec!["old".to_string()],
+    })
+    .unwrap();
+    let old = Snapshot::read_guard().await;
+    Snapshot::write_guard().await.names = vec!["new".to_string()];
+    assert_eq!(old.names, ["old"]);
+    assert_eq!(Snapshot::read_guard().await.names, ["new"]);
+
+    // singletons without a lock have read guards
+    Limits::init_singleton(Limits { max: 42 }).unwrap();
+    assert_eq!(Limits::read_guard().await.max, 42);
+
+    // uninitialized singletons
+    assert_eq!(
+        Empty::try_read_guard().await.err(),
+        Some(SingletonError::NotInitialized("Empty"))
+    );
+    assert!(Empty::try_write_guard().await.is_err());
+}
@@ -0,0 +1,108 @@
+//! Singleton test pass #17 - watched singletons.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+use std::collections::BTreeMap;
+
+#[derive(Clone, Singleton)]
+#[singleton(lock = "rwlock", watch)]
+struct Routes {
+    table: BTreeMap<String, String>,
+}
+
+impl Routes {
+    #[singleton_fn]
+    async fn add(&mut self, dest: String, via: String) {
+        self.table.insert(dest, via);
+    }
+
+    #[singleton_fn]
+    async fn via(&self, dest: String) -> Option<String> {
+        self.table.get(&dest).cloned()
+    }
+}
+
+/// The number of routes, published instead of the whole routing table.
+#[derive(Debug, PartialEq)]
+struct RouteCount(usize);
+
+impl From<&Counted> for RouteCount {
+    fn from(routes: &Counted) -> Self {
+        RouteCount(routes.0.len())
+    }
+}
+
+#[derive(Clone, Singleton)]
+#[singleton(lock = "swap", watch = "RouteCount")]
+struct Counted(Vec<String>);
+
+impl Counted {
+    #[singleton_fn]
+    async fn push(&mut self, route: String) {
+        self.0.push(route);
+    }
+}
+
+#[derive(Clone, Singleton)]
+#[singleton(lock = "mutex", watch, init = "Lazy::load")]
+struct Lazy(u32);
+
+impl Lazy {
+    async fn load() -> anyhow::Result<Lazy> {
+        Ok(Lazy(7))
+    }
+
+    #[singleton_fn]
+    async fn get(&self) -> u32 {
+        self.0
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let mut routes = Routes::subscribe();
+    assert!(routes.borrow().is_none());
+
+    // the initialization is published
+    Routes::init_singleton(Routes {
+        table: BTreeMap::new(),
+    })
+    .unwrap();
+    routes.changed().await.unwrap();
+    assert!(routes.borrow_and_update().as_ref().unwrap().table.is_empty());
+
+    // mutable singleton fns are published, immutable ones are not
+    let watcher = tokio::spawn(async move {
+        routes.changed().await.unwrap();
+        let via = routes.borrow_and_update().as_ref().unwrap().table["10.0.0.0/8"].clone();
+        assert!(!routes.has_changed().unwrap());
+        via
+    });
+    assert_eq!(Routes::via("10.0.0.0/8".to_string()).await, None);
+    Routes::add("10.0.0.0/8".to_string(), "gateway".to_string()).await;
+    assert_eq!(watcher.await.unwrap(), "gateway");
+
+    // write guards are published when they are released
+    let mut routes = Routes::subscribe();
+    Routes::write_guard().await.table.clear();
+    assert!(routes.has_changed().unwrap());
+    assert!(routes.borrow_and_update().as_ref().unwrap().table.is_empty());
+
+    // taking the value out is published
+    Routes::take_singleton().await;
+    assert!(routes.borrow_and_update().is_none());
+
+    // projections
+    let mut counted = Counted::subscribe();
+    Counted::init_singleton(Counted(Vec::new())).unwrap();
+    Counted::push("a".to_string()).await;
+    Counted::push("b".to_string()).await;
+    assert_eq!(*counted.borrow_and_update(), Some(RouteCount(2)));
+
+    // lazy initializations are published
+    let lazy = Lazy::subscribe();
+    assert_eq!(Lazy::get().await, 7);
+    assert_eq!(lazy.borrow().as_ref().map(|lazy| lazy.0), Some(7));
+}
@@ -0,0 +1,114 @@
+//! Singleton test pass #18 - keyed singletons.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(lock = "rwlock", keyed)]
+struct Pool {
+    url: String,
+    queries: u32,
+}
+
+impl Pool {
+    #[singleton_fn(keyed)]
+    async fn query(&mut self, sql: &'static str) -> String {
+        self.queries += 1;
+        format!("{}: {}", self.url, sql)
+    }
+
+    #[singleton_fn(keyed, fallible)]
+    async fn queries(&self) -> u32 {
+        self.queries
+    }
+
+    #[singleton_fn(keyed)]
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    #[singleton_fn(keyed, guard)]
+    fn url_ref(&self) -> &str {
+        &self.url
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+enum Role {
+    Primary,
+    Replica,
+}
+
+#[derive(Singleton)]
+#[singleton(lock = "none", keyed = "Role")]
+struct Limits {
+    max_connections: u32,
+}
+
+impl Limits {
+    #[singleton_fn(keyed)]
+    async fn max_connections(&self) -> u32 {
+        self.max_connections
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    Pool::init_singleton_keyed("primary", Pool {
+        url: "postgres://primary".to_string(),
+        queries: 0,
+    })
+    .unwrap();
+    Pool::init_singleton_keyed("replica", Pool {
+        url: "postgres://replica".to_string(),
+        queries: 0,
+    })
+    .unwrap();
+    // keys are initialized once
+    assert!(Pool::init_singleton_keyed("replica", Pool {
+        url: "postgres://other".to_string(),
+        queries: 0,
+    })
+    .is_err());
+
+    // each key holds its own value
+    assert_eq!(
+        Pool::query_on("replica", "SELECT 1").await,
+        "postgres://replica: SELECT 1"
+    );
+    assert_eq!(Pool::queries_on("replica").await.unwrap(), 1);
+    assert_eq!(Pool::queries_on("primary").await.unwrap(), 0);
+    let url = tokio::task::spawn_blocking(|| Pool::url_on("primary"))
+        .await
+        .unwrap();
+    assert_eq!(url, "postgres://primary");
+    assert_eq!(&*Pool::url_ref_on("replica").await, "postgres://replica");
+
+    // uninitialized keys
+    assert!(!Pool::is_initialized_keyed("analytics"));
+    assert!(Pool::queries_on("analytics").await.is_err());
+    let mut keys = Pool::singleton_keys();
+    keys.sort();
+    assert_eq!(keys, vec!["primary".to_string(), "replica".to_string()]);
+
+    // taking the value out leaves the key uninitialized
+    let primary = Pool::take_singleton_keyed("primary").await.unwrap();
+    assert_eq!(primary.url, "postgres://primary");
+    assert_eq!(Pool::singleton_keys(), vec!["replica".to_string()]);
+
+    // typed keys
+    Limits::init_singleton_keyed(&Role::Primary, Limits {
+        max_connections: 100,
+    })
+    .unwrap();
+    Limits::init_singleton_keyed(&Role::Replica, Limits {
+        max_connections: 20,
+    })
+    .unwrap();
+    assert_eq!(Limits::max_connections_on(&Role::Replica).await, 20);
+    assert_eq!(
+        Limits::get_singleton_keyed(&Role::Primary).max_connections,
+        100
+    );
+}
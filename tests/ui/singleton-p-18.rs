@@ -0,0 +1,114 @@
+//! Singleton test pass #19 - sharded singletons.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+use std::collections::HashMap;
+
+#[derive(Default, Singleton)]
+#[singleton(lock = "mutex", shards = 8, shard_by = "user_id")]
+struct Hits {
+    by_user: HashMap<u64, u32>,
+}
+
+impl Hits {
+    #[singleton_fn]
+    async fn hit(&mut self, user_id: u64, count: u32) {
+        *self.by_user.entry(user_id).or_default() += count;
+    }
+
+    #[singleton_fn]
+    async fn hits(&self, user_id: u64) -> u32 {
+        self.by_user.get(&user_id).copied().unwrap_or_default()
+    }
+
+    #[singleton_fn(fallible)]
+    async fn try_hits(&self, user_id: u64) -> u32 {
+        self.by_user.get(&user_id).copied().unwrap_or_default()
+    }
+
+    // the shard_by argument of the singleton can be overridden
+    #[singleton_fn(shard_by = "name")]
+    fn hits_by_name(&self, name: &str) -> usize {
+        name.len() + self.by_user.len()
+    }
+}
+
+#[derive(Clone, Default, Singleton)]
+#[singleton(lock = "swap", shards = 4, shard_by = "r#type")]
+struct Labels(Vec<String>);
+
+impl Labels {
+    #[singleton_fn]
+    async fn add(&mut self, r#type: String) {
+        self.0.push(r#type);
+    }
+
+    // the shard_by argument can be anywhere in the arguments
+    #[singleton_fn]
+    fn count(&self, prefix: &str, r#type: &str) -> usize {
+        self.0
+            .iter()
+            .filter(|label| label.starts_with(prefix) && label.as_str() == r#type)
+            .count()
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    assert_eq!(<Hits as ShardedSingleton>::SHARDS, 8);
+    assert!(Hits::try_hits(1).await.is_err());
+
+    Hits::init_shards(|_| Hits::default()).unwrap();
+    assert_eq!(Hits::singleton_keys().len(), 8);
+    assert!(Hits::init_shards(|_| Hits::default()).is_err());
+
+    // calls are routed to the shard of the user
+    let mut tasks = Vec::new();
+    for user_id in 0..64 {
+        tasks.push(tokio::spawn(Hits::hit(user_id, 2)));
+        tasks.push(tokio::spawn(Hits::hit(user_id, 1)));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(Hits::hits(7).await, 3);
+    assert_eq!(Hits::try_hits(63).await.unwrap(), 3);
+    let shard = Hits::shard_of(&7u64);
+    assert!(Hits::read_guard_keyed(&shard).await.by_user.contains_key(&7));
+
+    // sync singleton fns
+    let len = tokio::task::spawn_blocking(|| Hits::hits_by_name("alice"))
+        .await
+        .unwrap();
+    assert!(len >= 5);
+
+    // aggregating read over all the shards
+    let shards = Hits::read_shards().await;
+    assert_eq!(shards.len(), 8);
+    let users: usize = shards.iter().map(|shard| shard.by_user.len()).sum();
+    let hits: u32 = shards
+        .iter()
+        .flat_map(|shard| shard.by_user.values())
+        .sum();
+    assert_eq!(users, 64);
+    assert_eq!(hits, 64 * 3);
+    drop(shards);
+
+    // the shards of a swap lock
+    Labels::init_shards(|_| Labels::default()).unwrap();
+    for label in ["a", "b", "c", "d", "e"] {
+        Labels::add(label.to_string()).await;
+    }
+    let labels: usize = Labels::read_shards()
+        .await
+        .iter()
+        .map(|shard| shard.0.len())
+        .sum();
+    assert_eq!(labels, 5);
+    for label in ["a", "b", "c", "d", "e"] {
+        assert_eq!(Labels::count("", label), 1);
+    }
+    assert_eq!(Labels::count("b", "a"), 0);
+}
@@ -0,0 +1,164 @@
+//! Singleton test pass #20 - actor singletons.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+use blockz::singleton::SingletonError;
+
+use std::time::Duration;
+
+#[derive(Debug, Singleton)]
+#[singleton(actor)]
+struct Counter(u64);
+
+impl Counter {
+    #[singleton_fn]
+    async fn incr(&mut self, by: u64) {
+        self.0 += by;
+    }
+
+    #[singleton_fn]
+    async fn get(&self) -> u64 {
+        self.0
+    }
+
+    #[singleton_fn(fallible)]
+    async fn try_get(&self) -> u64 {
+        self.0
+    }
+
+    #[singleton_fn]
+    fn get_sync(&self) -> u64 {
+        self.0
+    }
+
+    #[singleton_fn]
+    fn incr_sync(&mut self, by: u64) {
+        self.0 += by;
+    }
+}
+
+#[derive(Clone, Singleton)]
+#[singleton(actor, watch)]
+struct Worker(Option<tokio::task::Id>);
+
+impl Worker {
+    #[singleton_fn]
+    async fn record(&mut self) {
+        self.0 = Some(tokio::task::id());
+    }
+
+    #[singleton_fn]
+    async fn boom(&self) {
+        panic!("boom");
+    }
+
+    #[singleton_fn]
+    async fn task_id(&self) -> Option<tokio::task::Id> {
+        self.0
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(actor(mailbox = 1, overflow = "fail"))]
+struct Small(u64);
+
+impl Small {
+    #[singleton_fn(fallible)]
+    async fn try_get(&self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(actor(stop = "abort"))]
+struct Aborted(u64);
+
+impl Aborted {
+    #[singleton_fn(fallible)]
+    async fn try_get(&self) -> u64 {
+        self.0
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    // uses before the initialization fail
+    assert!(matches!(
+        Counter::try_get().await,
+        Err(SingletonError::NotInitialized(_))
+    ));
+    Counter::init_singleton(Counter(0)).unwrap();
+    assert!(Counter::init_singleton(Counter(0)).is_err());
+
+    // the mailbox orders the uses
+    let mut tasks = Vec::new();
+    for _ in 0..100 {
+        tasks.push(tokio::spawn(Counter::incr(1)));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(Counter::get().await, 100);
+    assert_eq!(Counter::read_guard().await.0, 100);
+
+    // sync singleton fns
+    tokio::task::spawn_blocking(|| Counter::incr_sync(5))
+        .await
+        .unwrap();
+    let value = tokio::task::spawn_blocking(Counter::get_sync)
+        .await
+        .unwrap();
+    assert_eq!(value, 105);
+
+    // taking the value out stops the actor
+    assert_eq!(Counter::take_singleton().await.unwrap().0, 105);
+    assert!(!Counter::is_initialized());
+    assert!(Counter::try_get().await.is_err());
+    Counter::init_singleton(Counter(1)).unwrap();
+    assert_eq!(Counter::get().await, 1);
+
+    // singleton fns are calls run by the task of the actor, which publishes the changes
+    let mut worker = Worker::subscribe();
+    Worker::init_singleton(Worker(None)).unwrap();
+    let caller = tokio::spawn(async {
+        Worker::record().await;
+        tokio::task::id()
+    })
+    .await
+    .unwrap();
+    let actor = Worker::task_id().await.unwrap();
+    assert_ne!(actor, caller);
+    worker.changed().await.unwrap();
+    assert_eq!(worker.borrow_and_update().as_ref().unwrap().0, Some(actor));
+
+    // a call that panics resumes the panic in the caller, and the actor keeps serving
+    assert!(tokio::spawn(Worker::boom()).await.unwrap_err().is_panic());
+    assert_eq!(Worker::task_id().await, Some(actor));
+
+    // a full mailbox fails the uses
+    Small::init_singleton(Small(7)).unwrap();
+    let guard = Small::write_guard().await;
+    let queued = tokio::spawn(Small::try_get());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(matches!(
+        Small::try_get().await,
+        Err(SingletonError::MailboxFull(_))
+    ));
+    drop(guard);
+    assert_eq!(queued.await.unwrap().unwrap(), 7);
+
+    // an aborting stop fails the queued uses
+    Aborted::init_singleton(Aborted(3)).unwrap();
+    let guard = Aborted::write_guard().await;
+    let queued = tokio::spawn(Aborted::try_get());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let take = tokio::spawn(Aborted::take_singleton());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    drop(guard);
+    assert!(matches!(
+        queued.await.unwrap(),
+        Err(SingletonError::Stopped(_))
+    ));
+    assert_eq!(take.await.unwrap().unwrap().0, 3);
+}
@@ -0,0 +1,134 @@
+//! Singleton test pass #21 - scoped singletons.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Singleton)]
+#[singleton(lock = "rwlock", watch)]
+struct Config {
+    name: String,
+}
+
+impl Config {
+    #[singleton_fn]
+    async fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[singleton_fn]
+    async fn rename(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(init = "Remote::connect")]
+struct Remote(u32);
+
+impl Remote {
+    async fn connect() -> anyhow::Result<Self> {
+        Err(anyhow::anyhow!("unreachable"))
+    }
+
+    #[singleton_fn]
+    async fn port(&self) -> u32 {
+        self.0
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(lock = "none")]
+struct Limits(u32);
+
+#[derive(Singleton)]
+#[singleton(actor)]
+struct Counter(u64);
+
+impl Counter {
+    #[singleton_fn]
+    async fn incr(&mut self) -> u64 {
+        self.0 += 1;
+        self.0
+    }
+}
+
+fn config(name: &str) -> Config {
+    Config {
+        name: name.to_string(),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    Config::init_singleton(config("global")).unwrap();
+    let mut subscriber = Config::subscribe();
+    subscriber.borrow_and_update();
+
+    // the scope overrides the singleton, which is left untouched
+    let name = Config::scope(config("scoped"), async {
+        assert!(Config::is_initialized());
+        Config::rename("renamed").await;
+        assert_eq!(Config::read_guard().await.name, "renamed");
+        Config::name().await
+    })
+    .await;
+    assert_eq!(name, "renamed");
+    assert_eq!(Config::name().await, "global");
+    assert!(!subscriber.has_changed().unwrap());
+
+    // concurrent scopes don't see each other
+    let mut tasks = Vec::new();
+    for i in 0..16 {
+        let name = format!("test-{}", i);
+        tasks.push(tokio::spawn(Config::scope(config(&name), async move {
+            tokio::task::yield_now().await;
+            assert_eq!(Config::name().await, name);
+        })));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    // nested scopes override the outer ones
+    Config::scope(config("outer"), async {
+        Config::scope(config("inner"), async {
+            assert_eq!(Config::name().await, "inner");
+        })
+        .await;
+        assert_eq!(Config::name().await, "outer");
+        assert_eq!(Config::take_singleton().await, Some(config("outer")));
+        assert!(!Config::is_initialized());
+    })
+    .await;
+    assert_eq!(Config::name().await, "global");
+
+    // scoped singletons are not initialized lazily
+    let port = Remote::scope(Remote(8080), Remote::port()).await;
+    assert_eq!(port, 8080);
+    assert!(!Remote::is_initialized());
+
+    // singletons without a lock and actors
+    Limits::scope(Limits(5), async {
+        assert_eq!(Limits::read_guard().await.0, 5);
+        assert!(std::panic::catch_unwind(Limits::get_singleton).is_err());
+    })
+    .await;
+    assert!(!Limits::is_initialized());
+    Counter::init_singleton(Counter(100)).unwrap();
+    let count = Counter::scope(Counter(0), Counter::incr()).await;
+    assert_eq!(count, 1);
+    assert_eq!(Counter::incr().await, 101);
+
+    // a scope that panics still releases its value, which guards keep alive
+    let scope = tokio::spawn(Config::scope(config("panicked"), async {
+        panic!("scope");
+    }));
+    assert!(scope.await.unwrap_err().is_panic());
+    assert_eq!(Config::name().await, "global");
+    let guard = Config::scope(config("kept"), Config::read_guard()).await;
+    assert_eq!(guard.name, "kept");
+    drop(guard);
+    let actor = Counter::scope(Counter(7), async { Counter::incr().await }).await;
+    assert_eq!(actor, 8);
+}
@@ -0,0 +1,127 @@
+//! Singleton test pass #22 - trait singletons.
+
+#![cfg(feature = "singleton")]
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use blockz::prelude::*;
+
+#[derive(Singleton)]
+#[singleton(trait = "DbApi")]
+struct DbConnPool {
+    addr: String,
+    limit: u32,
+}
+
+#[singleton_impl(trait = "DbApi")]
+impl DbConnPool {
+    fn new(addr: &str) -> Self {
+        Self {
+            addr: addr.to_string(),
+            limit: 10,
+        }
+    }
+
+    /// The address of the database.
+    #[singleton_fn]
+    pub async fn addr(&self) -> String {
+        self.addr.clone()
+    }
+
+    #[singleton_fn]
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    #[singleton_fn]
+    pub async fn set_limit(&mut self, limit: u32, reason: &str) -> String {
+        self.limit = limit;
+        format!("{} ({})", limit, reason)
+    }
+
+    #[singleton_fn(fallible)]
+    pub async fn query(&self, (table, id): (&str, u64)) -> String {
+        format!("{}/{}/{}", self.addr, table, id)
+    }
+}
+
+/// A mock that records the calls it receives.
+#[derive(Clone, Default)]
+struct MockDb {
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockDb {
+    fn record(&self, call: String) {
+        self.calls.lock().unwrap().push(call);
+    }
+
+    fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl DbApi for MockDb {
+    async fn addr(&self) -> String {
+        self.record("addr".to_string());
+        "mock".to_string()
+    }
+
+    fn limit(&self) -> u32 {
+        self.record("limit".to_string());
+        0
+    }
+
+    async fn set_limit(&mut self, limit: u32, reason: &str) -> String {
+        self.record(format!("set_limit({}, {})", limit, reason));
+        String::new()
+    }
+
+    async fn query(&self, (table, id): (&str, u64)) -> String {
+        self.record(format!("query({}, {})", table, id));
+        String::new()
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    // the trait is implemented by the type
+    let pool = DbConnPool::new("db:5432");
+    assert_eq!(DbApi::addr(&pool).await, "db:5432");
+    assert!(matches!(
+        DbConnPool::query(("users", 1)).await,
+        Err(blockz::singleton::SingletonError::NotInitialized(_))
+    ));
+
+    // the real implementation
+    DbConnPool::init_singleton(Box::new(pool)).unwrap();
+    assert_eq!(DbConnPool::addr().await, "db:5432");
+    assert_eq!(DbConnPool::set_limit(20, "load").await, "20 (load)");
+    assert_eq!(DbConnPool::query(("users", 1)).await.unwrap(), "db:5432/users/1");
+    let limit = tokio::task::spawn_blocking(DbConnPool::limit).await.unwrap();
+    assert_eq!(limit, 20);
+
+    // a mock installed in a scope
+    let mock = MockDb::default();
+    DbConnPool::scope(Box::new(mock.clone()), async {
+        assert_eq!(DbConnPool::addr().await, "mock");
+        DbConnPool::set_limit(5, "test").await;
+        DbConnPool::query(("orders", 7)).await.unwrap();
+    })
+    .await;
+    assert_eq!(
+        mock.calls(),
+        vec!["addr", "set_limit(5, test)", "query(orders, 7)"]
+    );
+    assert_eq!(DbConnPool::addr().await, "db:5432");
+
+    // a mock installed as the singleton
+    let mock = MockDb::default();
+    DbConnPool::replace_singleton(Box::new(mock.clone())).await;
+    assert_eq!(DbConnPool::addr().await, "mock");
+    let limit = tokio::task::spawn_blocking(DbConnPool::limit).await.unwrap();
+    assert_eq!(limit, 0);
+    assert_eq!(mock.calls(), vec!["addr", "limit"]);
+}
@@ -0,0 +1,61 @@
+//! Singleton test pass #23 - trait singletons with generated mocks.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+use mockall::predicate::eq;
+
+#[derive(Singleton)]
+#[singleton(trait = "UserApi")]
+struct Users(Vec<String>);
+
+#[singleton_impl(trait = "UserApi", trait_attr = "mockall::automock")]
+impl Users {
+    #[singleton_fn]
+    async fn name(&self, id: usize) -> Option<String> {
+        self.0.get(id).cloned()
+    }
+
+    #[singleton_fn]
+    async fn add(&mut self, name: String) -> usize {
+        self.0.push(name);
+        self.0.len() - 1
+    }
+
+    #[singleton_fn]
+    fn count(&self) -> usize {
+        self.0.len()
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    // a mock installed in a scope, with call expectations
+    let mut mock = MockUserApi::new();
+    mock.expect_add()
+        .with(eq("ada".to_string()))
+        .times(1)
+        .returning(|_| 7);
+    mock.expect_name()
+        .with(eq(7))
+        .times(1)
+        .returning(|_| Some("ada".to_string()));
+    Users::scope(Box::new(mock), async {
+        assert_eq!(Users::add("ada".to_string()).await, 7);
+        assert_eq!(Users::name(7).await.as_deref(), Some("ada"));
+    })
+    .await;
+
+    // a mock installed as the singleton
+    let mut mock = MockUserApi::new();
+    mock.expect_count().times(1).return_const(3usize);
+    Users::init_singleton(Box::new(mock)).unwrap();
+    let count = tokio::task::spawn_blocking(Users::count).await.unwrap();
+    assert_eq!(count, 3);
+
+    // the real implementation replaces it
+    Users::replace_singleton(Box::new(Users(Vec::new()))).await;
+    assert_eq!(Users::add("grace".to_string()).await, 0);
+    assert_eq!(Users::name(0).await.as_deref(), Some("grace"));
+}
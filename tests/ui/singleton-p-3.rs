@@ -0,0 +1,56 @@
+//! Singleton test pass #4 - generic singletons.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Singleton)]
+#[singleton(instance = "Cache<String, u32>", instance = "Cache<u32, Vec<String>>")]
+struct Cache<K, V>
+where
+    K: Eq + Hash,
+{
+    entries: HashMap<K, V>,
+}
+
+impl Cache<String, u32> {
+    #[singleton_fn]
+    async fn insert(&mut self, key: String, value: u32) {
+        self.entries.insert(key, value);
+    }
+
+    #[singleton_fn]
+    async fn get(&self, key: String) -> Option<u32> {
+        self.entries.get(&key).copied()
+    }
+}
+
+async fn insert_value(cache: &mut Cache<u32, Vec<String>>, key: u32) {
+    cache.entries.insert(key, vec!["value".to_string()]);
+}
+
+async fn len(cache: &Cache<u32, Vec<String>>) -> usize {
+    cache.entries.len()
+}
+
+#[tokio::main]
+async fn main() {
+    Cache::<String, u32>::init_singleton(Cache {
+        entries: HashMap::new(),
+    })
+    .unwrap();
+    Cache::<u32, Vec<String>>::init_singleton(Cache {
+        entries: HashMap::new(),
+    })
+    .unwrap();
+
+    Cache::<String, u32>::insert("answer".to_string(), 42).await;
+    assert_eq!(Cache::<String, u32>::get("answer".to_string()).await, Some(42));
+
+    Cache::<u32, Vec<String>>::use_mut_singleton_with_arg(insert_value, 42).await;
+    assert_eq!(Cache::<u32, Vec<String>>::use_singleton(len).await, 1);
+    assert_eq!(Cache::<String, u32>::use_singleton(|_: &_| async { 0 }).await, 0);
+}
@@ -0,0 +1,60 @@
+//! Singleton test pass #5 - non-panicking access.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+use blockz::singleton::SingletonError;
+
+#[derive(Singleton)]
+struct Counter(u64);
+
+impl Counter {
+    #[singleton_fn(fallible)]
+    async fn get(&self) -> u64 {
+        self.0
+    }
+
+    #[singleton_fn(fallible)]
+    async fn add(&mut self, value: u64) {
+        self.0 += value;
+    }
+
+    #[singleton_fn]
+    async fn reset(&mut self) {
+        self.0 = 0;
+    }
+}
+
+async fn double(counter: &mut Counter) -> u64 {
+    counter.0 *= 2;
+    counter.0
+}
+
+#[tokio::main]
+async fn main() {
+    assert!(!Counter::is_initialized());
+    assert_eq!(
+        Counter::get().await,
+        Err(SingletonError::NotInitialized("Counter"))
+    );
+    assert_eq!(
+        Counter::add(1).await.unwrap_err().to_string(),
+        "Counter: singleton: not initialized"
+    );
+    assert!(Counter::try_use_mut_singleton(double).await.is_err());
+
+    let panic = tokio::spawn(Counter::reset()).await.unwrap_err();
+    let panic = panic.into_panic();
+    assert_eq!(
+        panic.downcast_ref::<String>().map(String::as_str),
+        Some("Counter: singleton: not initialized (call Counter::init_singleton first)")
+    );
+
+    Counter::init_singleton(Counter(0)).unwrap();
+    assert!(Counter::is_initialized());
+    Counter::add(21).await.unwrap();
+    assert_eq!(Counter::try_use_mut_singleton(double).await, Ok(42));
+    assert_eq!(Counter::get().await, Ok(42));
+    Counter::reset().await;
+    assert_eq!(Counter::get().await, Ok(0));
+}
@@ -0,0 +1,74 @@
+//! Singleton test pass #6 - lazy initialization.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+use blockz::singleton::SingletonError;
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+static POOL_INITS: AtomicUsize = AtomicUsize::new(0);
+static FLAKY_INITS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Singleton)]
+#[singleton(init = "Pool::connect")]
+struct Pool {
+    connections: usize,
+}
+
+impl Pool {
+    async fn connect() -> anyhow::Result<Pool> {
+        POOL_INITS.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Ok(Pool { connections: 4 })
+    }
+
+    #[singleton_fn]
+    async fn connections(&self) -> usize {
+        self.connections
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(lock = "rwlock", init = "connect_flaky", init_backoff = "200ms")]
+struct Flaky(usize);
+
+async fn connect_flaky() -> Result<Flaky, std::io::Error> {
+    let attempt = FLAKY_INITS.fetch_add(1, Ordering::SeqCst);
+    if attempt == 0 {
+        Err(std::io::Error::new(std::io::ErrorKind::Other, "connection refused"))
+    } else {
+        Ok(Flaky(attempt))
+    }
+}
+
+impl Flaky {
+    #[singleton_fn(fallible)]
+    async fn attempt(&self) -> usize {
+        self.0
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    // concurrent first accesses initialize the singleton exactly once
+    assert!(!Pool::is_initialized());
+    let handles: Vec<_> = (0..8).map(|_| tokio::spawn(Pool::connections())).collect();
+    for handle in handles {
+        assert_eq!(handle.await.unwrap(), 4);
+    }
+    assert!(Pool::is_initialized());
+    assert_eq!(POOL_INITS.load(Ordering::SeqCst), 1);
+
+    // failed initializations are retried after the backoff
+    let expected_err = SingletonError::InitFailed("Flaky", "connection refused".to_string());
+    assert_eq!(Flaky::attempt().await, Err(expected_err.clone()));
+    assert_eq!(Flaky::attempt().await, Err(expected_err));
+    assert_eq!(FLAKY_INITS.load(Ordering::SeqCst), 1);
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert_eq!(Flaky::attempt().await, Ok(1));
+    assert_eq!(Flaky::attempt().await, Ok(1));
+    assert_eq!(FLAKY_INITS.load(Ordering::SeqCst), 2);
+}
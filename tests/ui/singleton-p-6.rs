@@ -0,0 +1,77 @@
+//! Singleton test pass #7 - replacing and taking the value.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+use blockz::singleton::SingletonError;
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+static CLIENT_INITS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, PartialEq, Singleton)]
+#[singleton(lock = "rwlock")]
+struct Endpoint(String);
+
+impl Endpoint {
+    #[singleton_fn(fallible)]
+    async fn get(&self) -> String {
+        self.0.clone()
+    }
+
+    #[singleton_fn]
+    async fn slow_get(&self) -> String {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        self.0.clone()
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(init = "Client::connect")]
+struct Client(usize);
+
+impl Client {
+    async fn connect() -> anyhow::Result<Client> {
+        Ok(Client(CLIENT_INITS.fetch_add(1, Ordering::SeqCst)))
+    }
+
+    #[singleton_fn]
+    async fn generation(&self) -> usize {
+        self.0
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    // replacing an uninitialized singleton initializes it
+    assert_eq!(Endpoint::replace_singleton(Endpoint("a".to_string())).await, None);
+    assert!(Endpoint::is_initialized());
+    assert!(Endpoint::init_singleton(Endpoint("b".to_string())).is_err());
+
+    // in-flight uses finish against the old value
+    let in_flight = tokio::spawn(Endpoint::slow_get());
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let old = Endpoint::replace_singleton(Endpoint("b".to_string())).await;
+    assert_eq!(old, Some(Endpoint("a".to_string())));
+    assert_eq!(in_flight.await.unwrap(), "a");
+    assert_eq!(Endpoint::get().await, Ok("b".to_string()));
+
+    // taking the value leaves the singleton uninitialized
+    assert_eq!(Endpoint::take_singleton().await, Some(Endpoint("b".to_string())));
+    assert_eq!(Endpoint::take_singleton().await, None);
+    assert!(!Endpoint::is_initialized());
+    assert_eq!(Endpoint::get().await, Err(SingletonError::NotInitialized("Endpoint")));
+    Endpoint::init_singleton(Endpoint("c".to_string())).unwrap();
+    assert_eq!(Endpoint::get().await, Ok("c".to_string()));
+
+    // resetting a lazy singleton initializes it again on the next use
+    assert_eq!(Client::generation().await, 0);
+    #[cfg(feature = "test-util")]
+    {
+        Client::reset_singleton().await;
+        assert!(!Client::is_initialized());
+        assert_eq!(Client::generation().await, 1);
+    }
+}
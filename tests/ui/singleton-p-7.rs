@@ -0,0 +1,76 @@
+//! Singleton test pass #8 - timeouts.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+use blockz::singleton::SingletonError;
+
+use std::time::Duration;
+
+#[derive(Singleton)]
+#[singleton(lock_timeout = "50ms")]
+struct Device(u64);
+
+impl Device {
+    #[singleton_fn]
+    async fn stuck(&mut self) {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+    }
+
+    #[singleton_fn(fallible)]
+    async fn read(&self) -> u64 {
+        self.0
+    }
+
+    #[singleton_fn(timeout = "100ms")]
+    async fn write(&mut self, value: u64) {
+        self.0 = value;
+    }
+}
+
+#[derive(Singleton)]
+struct Sensor(u64);
+
+impl Sensor {
+    #[singleton_fn(timeout = "50ms")]
+    async fn slow_read(&self) -> u64 {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        self.0
+    }
+
+    #[singleton_fn]
+    async fn read(&self) -> u64 {
+        self.0
+    }
+}
+
+async fn read_sensor(sensor: &Sensor) -> u64 {
+    sensor.0
+}
+
+#[tokio::main]
+async fn main() {
+    Device::init_singleton(Device(1)).unwrap();
+    Sensor::init_singleton(Sensor(2)).unwrap();
+
+    // lock timeouts stop the uses waiting for a stuck singleton fn
+    let stuck = tokio::spawn(Device::stuck());
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(Device::read().await, Err(SingletonError::LockTimeout("Device")));
+    assert_eq!(
+        Device::write(2).await.unwrap_err().to_string(),
+        "Device: singleton: timed out"
+    );
+    stuck.abort();
+    let _ = stuck.await;
+    assert_eq!(Device::write(3).await, Ok(()));
+    assert_eq!(Device::read().await, Ok(3));
+
+    // call timeouts drop the call, releasing the lock
+    assert_eq!(Sensor::slow_read().await, Err(SingletonError::LockTimeout("Sensor")));
+    assert_eq!(Sensor::read().await, 2);
+    assert_eq!(
+        Sensor::use_singleton_timeout(read_sensor, Duration::from_millis(50)).await,
+        Ok(2)
+    );
+}
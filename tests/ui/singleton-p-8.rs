@@ -0,0 +1,71 @@
+//! Singleton test pass #9 - swap lock.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Clone, Singleton)]
+#[singleton(lock = "swap")]
+struct Routes {
+    routes: HashMap<String, String>,
+}
+
+impl Routes {
+    #[singleton_fn]
+    async fn resolve(&self, path: String) -> Option<String> {
+        self.routes.get(&path).cloned()
+    }
+
+    #[singleton_fn]
+    async fn slow_resolve(&self, path: String) -> Option<String> {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        self.routes.get(&path).cloned()
+    }
+
+    #[singleton_fn]
+    async fn add(&mut self, path: String, target: String) {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        self.routes.insert(path, target);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    Routes::init_singleton(Routes {
+        routes: HashMap::new(),
+    })
+    .unwrap();
+
+    // readers keep their snapshot while writers publish new values
+    let reader = tokio::spawn(Routes::slow_resolve("/a".to_string()));
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    Routes::add("/a".to_string(), "a".to_string()).await;
+    assert_eq!(Routes::resolve("/a".to_string()).await, Some("a".to_string()));
+    assert_eq!(reader.await.unwrap(), None);
+
+    // writers are serialized
+    let writers: Vec<_> = (0..16)
+        .map(|index| tokio::spawn(Routes::add(format!("/{}", index), index.to_string())))
+        .collect();
+    for writer in writers {
+        writer.await.unwrap();
+    }
+    for index in 0..16 {
+        assert_eq!(
+            Routes::resolve(format!("/{}", index)).await,
+            Some(index.to_string())
+        );
+    }
+
+    // replacing the value waits for the writers only
+    let old = Routes::replace_singleton(Routes {
+        routes: HashMap::new(),
+    })
+    .await
+    .unwrap();
+    assert_eq!(old.routes.len(), 17);
+    assert_eq!(Routes::resolve("/a".to_string()).await, None);
+}
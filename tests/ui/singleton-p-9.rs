@@ -0,0 +1,70 @@
+//! Singleton test pass #10 - singletons without a lock.
+
+#![cfg(feature = "singleton")]
+
+use blockz::prelude::*;
+use blockz::singleton::SingletonError;
+
+use std::collections::HashMap;
+
+#[derive(Singleton)]
+#[singleton(lock = "none")]
+struct Routes {
+    routes: HashMap<String, String>,
+}
+
+impl Routes {
+    #[singleton_fn]
+    async fn resolve(&self, path: String) -> Option<String> {
+        self.routes.get(&path).cloned()
+    }
+
+    #[singleton_fn(fallible)]
+    async fn len(&self) -> usize {
+        self.routes.len()
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(lock = "none", init = "Templates::compile")]
+struct Templates {
+    greeting: String,
+}
+
+impl Templates {
+    async fn compile() -> anyhow::Result<Templates> {
+        Ok(Templates {
+            greeting: "Hello, {}!".to_string(),
+        })
+    }
+
+    #[singleton_fn]
+    async fn greet(&self, name: String) -> String {
+        self.greeting.replace("{}", &name)
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    assert_eq!(Routes::len().await, Err(SingletonError::NotInitialized("Routes")));
+
+    let mut routes = HashMap::new();
+    routes.insert("/".to_string(), "index".to_string());
+    Routes::init_singleton(Routes { routes }).unwrap();
+    assert!(Routes::is_initialized());
+    assert!(Routes::init_singleton(Routes {
+        routes: HashMap::new()
+    })
+    .is_err());
+
+    // the async facades and the sync accessor read the same value
+    assert_eq!(Routes::resolve("/".to_string()).await, Some("index".to_string()));
+    assert_eq!(Routes::len().await, Ok(1));
+    let routes: &'static Routes = Routes::get_singleton();
+    assert_eq!(routes.routes["/"], "index");
+
+    // lazily initialized singletons are available synchronously after their first use
+    assert!(!Templates::is_initialized());
+    assert_eq!(Templates::greet("Tux".to_string()).await, "Hello, Tux!");
+    assert_eq!(Templates::get_singleton().greeting, "Hello, {}!");
+}
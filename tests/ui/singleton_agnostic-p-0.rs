@@ -0,0 +1,155 @@
+//! Singleton agnostic ui test #0 - singletons used without a tokio runtime.
+
+#![cfg(feature = "singleton_agnostic")]
+
+use blockz::prelude::*;
+use blockz::singleton::SingletonError;
+
+use futures_lite::future;
+
+use std::time::Duration;
+
+#[derive(Singleton)]
+#[singleton(runtime = "agnostic", lock_timeout = "50ms")]
+struct Counter(u64);
+
+impl Counter {
+    #[singleton_fn]
+    async fn increment(&mut self) -> u64 {
+        self.0 += 1;
+        self.0
+    }
+
+    #[singleton_fn(fallible)]
+    async fn get(&self) -> u64 {
+        self.0
+    }
+
+    #[singleton_fn]
+    async fn stuck(&mut self) {
+        future::pending::<()>().await;
+    }
+
+    #[singleton_fn(timeout = "50ms")]
+    async fn slow(&self) {
+        future::pending::<()>().await;
+    }
+
+    #[singleton_fn]
+    fn reset(&mut self) {
+        self.0 = 0;
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(runtime = "agnostic", lock = "rwlock", init = "Config::load")]
+struct Config {
+    name: String,
+}
+
+impl Config {
+    async fn load() -> anyhow::Result<Config> {
+        Ok(Config {
+            name: "agnostic".to_string(),
+        })
+    }
+
+    #[singleton_fn(timeout = "50ms")]
+    async fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[singleton_fn]
+    async fn rename(&mut self, name: String) {
+        self.name = name;
+    }
+}
+
+#[derive(Clone, Singleton)]
+#[singleton(runtime = "agnostic", lock = "swap")]
+struct Routes(Vec<String>);
+
+impl Routes {
+    #[singleton_fn]
+    async fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    #[singleton_fn]
+    async fn add(&mut self, route: String) {
+        self.0.push(route);
+    }
+}
+
+#[derive(Singleton)]
+#[singleton(runtime = "agnostic", lock = "none", init = "Version::detect")]
+struct Version(u32);
+
+impl Version {
+    async fn detect() -> anyhow::Result<Version> {
+        Ok(Version(3))
+    }
+
+    #[singleton_fn]
+    async fn major(&self) -> u32 {
+        self.0
+    }
+}
+
+async fn read_counter(counter: &Counter) -> u64 {
+    counter.0
+}
+
+fn main() {
+    future::block_on(async {
+        // mutex singletons
+        Counter::init_singleton(Counter(0)).unwrap();
+        assert_eq!(Counter::increment().await, 1);
+        assert_eq!(Counter::increment().await, 2);
+        assert_eq!(Counter::get().await, Ok(2));
+        Counter::reset();
+        assert_eq!(Counter::get().await, Ok(0));
+
+        // lock timeouts are driven without tokio
+        let stuck = Counter::stuck();
+        let timed_out = future::or(
+            async {
+                stuck.await;
+                None
+            },
+            async { Some(Counter::get().await) },
+        )
+        .await;
+        assert_eq!(timed_out, Some(Err(SingletonError::LockTimeout("Counter"))));
+        assert_eq!(Counter::get().await, Ok(0));
+
+        // lazily initialized rwlock singletons
+        assert_eq!(Config::name().await, Ok("agnostic".to_string()));
+        Config::rename("renamed".to_string()).await;
+        assert_eq!(Config::name().await, Ok("renamed".to_string()));
+
+        // swap singletons
+        Routes::init_singleton(Routes(Vec::new())).unwrap();
+        Routes::add("/".to_string()).await;
+        assert_eq!(Routes::count().await, 1);
+
+        // scopes override singletons without tokio
+        let scoped = Counter::scope(Counter(10), async {
+            assert_eq!(Counter::increment().await, 11);
+            Counter::get().await
+        });
+        assert_eq!(scoped.await, Ok(11));
+        assert_eq!(Counter::get().await, Ok(0));
+
+        // lazily initialized singletons without a lock
+        assert_eq!(Version::major().await, 3);
+        assert_eq!(Version::get_singleton().0, 3);
+
+        // call timeouts are driven without tokio
+        assert_eq!(Counter::slow().await, Err(SingletonError::LockTimeout("Counter")));
+        assert_eq!(
+            Counter::use_singleton_timeout(read_counter, Duration::from_millis(50)).await,
+            Ok(0)
+        );
+    });
+}